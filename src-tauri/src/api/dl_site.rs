@@ -4,7 +4,10 @@ use scraper::{Html, Selector, error::SelectorErrorKind};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use crate::{api::get_client, core::Language};
+use crate::{
+    api::get_client,
+    core::{Language, data::metadata::ContentInfo},
+};
 
 /// Types of content available on the DLSite.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
@@ -63,8 +66,27 @@ impl DLContentType {
     pub fn build_id(&self, id: &str) -> String {
         format!("{}{}", self.name_prefix(), id)
     }
+
+    /// The content type of a URL segment from [Self::name_url]
+    pub fn from_name_url(name: &str) -> Option<Self> {
+        [
+            Self::Doujin,
+            Self::Comics,
+            Self::PcGames,
+            Self::SmartphoneGames,
+            Self::DoujinR18,
+            Self::ComicsR18,
+            Self::HGames,
+            Self::SmartphoneGamesR18,
+        ]
+        .into_iter()
+        .find(|t| t.name_url() == name)
+    }
 }
 
+/// Site root all work pages are under
+pub(super) const DL_SITE_BASE: &str = "https://www.dlsite.com";
+
 trait DLSiteLang {
     fn dl_lang_code(&self) -> &'static str;
     fn dl_tag_scenario(&self) -> &'static str;
@@ -108,6 +130,8 @@ pub struct DLFetchInfo {
     pub tags: Vec<String>,
     pub description: Vec<String>,
     pub og_image: Option<String>,
+    /// Content type guessed from the categories
+    pub content_info: ContentInfo,
 }
 
 pub trait DLContentFetch {
@@ -120,8 +144,20 @@ pub trait DLContentFetch {
 
 impl DLContentFetch for DLContentType {
     async fn fetch_info(&self, id: &str, lang: &Language) -> Result<DLFetchInfo> {
+        self.fetch_info_at(DL_SITE_BASE, id, lang).await
+    }
+}
+
+impl DLContentType {
+    /// Fetches the work page from the site at `base`
+    pub(super) async fn fetch_info_at(
+        &self,
+        base: &str,
+        id: &str,
+        lang: &Language,
+    ) -> Result<DLFetchInfo> {
         let client = get_client();
-        let url = build_url(self, &self.build_id(id), lang);
+        let url = build_url(base, self, &self.build_id(id), lang);
 
         info!("Requesting {url} for content type: {:?}", self);
 
@@ -219,6 +255,8 @@ impl DLContentFetch for DLContentType {
             .and_then(|el| el.value().attr("content"))
             .map(|content| content.to_string());
 
+        let content_info = ContentInfo::from_dl_site(self, id, &category);
+
         Ok(DLFetchInfo {
             title,
             circle,
@@ -228,13 +266,15 @@ impl DLContentFetch for DLContentType {
             tags,
             description,
             og_image,
+            content_info,
         })
     }
 }
 
-fn build_url(content_type: &DLContentType, id: &str, lang: &Language) -> String {
+fn build_url(base: &str, content_type: &DLContentType, id: &str, lang: &Language) -> String {
     format!(
-        "https://www.dlsite.com/{}/work/=/product_id/{}.html/?locale={}",
+        "{}/{}/work/=/product_id/{}.html/?locale={}",
+        base,
         content_type.name_url(),
         id,
         lang.dl_lang_code()
@@ -5,12 +5,14 @@ use reqwest::Client;
 
 pub mod dl_site;
 pub mod http;
+pub mod provider;
 
 static CLIENT: OnceLock<Client> = OnceLock::new();
 
 /// Initializes the API components of the application.
 pub fn init_api() -> Result<()> {
     init_client()?;
+    provider::init_providers()?;
     Ok(())
 }
 
@@ -24,6 +26,10 @@ fn init_client() -> Result<()> {
             .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))
     }
 
+    // Tests of several modules share the client
+    if CLIENT.get().is_some() {
+        return Ok(());
+    }
     CLIENT
         .set(create_client()?)
         .map_err(|_| anyhow!("HTTP client already initialized"))?;
@@ -0,0 +1,266 @@
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    api::provider::{
+        MetadataProvider, ProviderCapabilities, ProviderFuture, ProviderHit, ProviderResult,
+        get_json, post_json,
+    },
+    core::{
+        Language,
+        data::metadata::{
+            AudioData, ComicData, ContentInfo, GameData, GameDistribution, NovelData,
+            OtherDistributionData, VideoData,
+        },
+    },
+};
+
+const BANGUMI_BASE: &str = "https://api.bgm.tv";
+/// Infobox keys naming the people or companies behind a subject
+const CREATOR_KEYS: &[&str] = &[
+    "作者",
+    "开发",
+    "发行",
+    "出版社",
+    "动画制作",
+    "导演",
+    "艺术家",
+    "社团",
+];
+
+/// Bangumi, looked up by subject id
+pub struct BangumiProvider {
+    base: String,
+}
+
+impl Default for BangumiProvider {
+    fn default() -> Self {
+        Self::with_base(BANGUMI_BASE)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Subject {
+    id: u64,
+    /// 1 book, 2 anime, 3 music, 4 game, 6 real
+    #[serde(rename = "type")]
+    subject_type: u8,
+    name: String,
+    name_cn: String,
+    summary: String,
+    platform: String,
+    images: Option<Images>,
+    infobox: Vec<InfoboxItem>,
+    tags: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Images {
+    large: Option<String>,
+    common: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InfoboxItem {
+    key: String,
+    value: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchPage {
+    data: Vec<Subject>,
+}
+
+impl InfoboxItem {
+    /// Values are either a string or a list of `{"v": ...}`
+    fn values(&self) -> Vec<String> {
+        match &self.value {
+            Value::String(v) => vec![v.clone()],
+            Value::Array(items) => items
+                .iter()
+                .filter_map(|item| item.get("v").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Subject {
+    /// Title in the language, the Chinese name is empty if there is none
+    fn titles(&self, lang: &Language) -> (String, Option<String>) {
+        let chinese = (!self.name_cn.is_empty()).then(|| self.name_cn.clone());
+        match (lang, chinese) {
+            (Language::ZhCn, Some(cn)) => (cn, Some(self.name.clone())),
+            (_, cn) => (self.name.clone(), cn),
+        }
+    }
+}
+
+impl BangumiProvider {
+    pub fn with_base(base: impl Into<String>) -> Self {
+        Self { base: base.into() }
+    }
+
+    async fn subject(&self, id: &str, lang: &Language) -> Result<ProviderResult> {
+        let id = id.trim();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+            return Err(anyhow!("Invalid Bangumi subject id '{}'", id));
+        }
+        let subject = get_json::<Subject>(&format!("{}/v0/subjects/{}", self.base, id)).await?;
+
+        let (title, other) = subject.titles(lang);
+        let mut alias = other.into_iter().collect::<Vec<_>>();
+        let mut creators = Vec::new();
+        for item in &subject.infobox {
+            let target = match item.key.as_str() {
+                "别名" => &mut alias,
+                key if CREATOR_KEYS.contains(&key) => &mut creators,
+                _ => continue,
+            };
+            for value in item.values() {
+                if value != title && !target.contains(&value) {
+                    target.push(value);
+                }
+            }
+        }
+
+        let creator = creators.first().cloned();
+        let distribution = GameDistribution::Other(OtherDistributionData {
+            name: String::from("Bangumi"),
+            id: subject.id.to_string(),
+        });
+        let content_info = match subject.subject_type {
+            1 if subject.platform == "小说" => ContentInfo::Novel(NovelData {
+                author: creator,
+                distribution,
+                ..Default::default()
+            }),
+            1 => ContentInfo::Comic(ComicData {
+                author: creator,
+                distribution,
+                ..Default::default()
+            }),
+            2 => ContentInfo::Video(VideoData {
+                studio: creator,
+                distribution,
+                ..Default::default()
+            }),
+            3 => ContentInfo::Audio(AudioData {
+                circle: creator,
+                distribution,
+                ..Default::default()
+            }),
+            4 => ContentInfo::Game(GameData {
+                developer: creator,
+                distribution,
+                ..Default::default()
+            }),
+            _ => ContentInfo::Undefined,
+        };
+        Ok(ProviderResult {
+            provider: self.name().to_string(),
+            id: subject.id.to_string(),
+            title,
+            alias,
+            description: Some(subject.summary).filter(|s| !s.is_empty()),
+            tags: subject.tags.into_iter().map(|t| t.name).collect(),
+            creators,
+            images: subject.images.and_then(|i| i.large).into_iter().collect(),
+            content_info,
+        })
+    }
+
+    async fn subject_search(&self, keyword: &str, lang: &Language) -> Result<Vec<ProviderHit>> {
+        let url = format!("{}/v0/search/subjects?limit=10", self.base);
+        let page = post_json::<SearchPage>(&url, &json!({ "keyword": keyword })).await?;
+        Ok(page
+            .data
+            .into_iter()
+            .map(|subject| ProviderHit {
+                id: subject.id.to_string(),
+                title: subject.titles(lang).0,
+                image: subject.images.and_then(|i| i.common),
+            })
+            .collect())
+    }
+}
+
+impl MetadataProvider for BangumiProvider {
+    fn name(&self) -> &'static str {
+        "bangumi"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            lookup: true,
+            search: true,
+            images: true,
+        }
+    }
+
+    fn lookup<'a>(&'a self, id: &'a str, lang: &'a Language) -> ProviderFuture<'a, ProviderResult> {
+        Box::pin(self.subject(id, lang))
+    }
+
+    fn search<'a>(
+        &'a self,
+        keyword: &'a str,
+        lang: &'a Language,
+    ) -> ProviderFuture<'a, Vec<ProviderHit>> {
+        Box::pin(self.subject_search(keyword, lang))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::{
+        init_client,
+        provider::stand_in::{Route, StandIn},
+    };
+
+    #[tokio::test]
+    async fn test_bangumi_provider() {
+        init_client().unwrap();
+        let server = StandIn::serve(vec![
+            Route {
+                request: "GET /v0/subjects/51 ",
+                body: "",
+                response: include_str!("fixtures/bangumi_subject.json"),
+            },
+            Route {
+                request: "POST /v0/search/subjects",
+                body: "CLANNAD",
+                response: include_str!("fixtures/bangumi_search.json"),
+            },
+        ]);
+        let provider = BangumiProvider::with_base(server.url());
+
+        let result = provider.lookup("51", &Language::ZhCn).await.unwrap();
+        assert_eq!(result.title, "团子大家族");
+        assert_eq!(result.alias, vec!["CLANNAD", "クラナド", "Clannad"]);
+        assert_eq!(result.creators, vec!["京都アニメーション"]);
+        assert_eq!(result.tags, vec!["京都动画", "Key"]);
+        let ContentInfo::Video(data) = &result.content_info else {
+            panic!("expected video, got {:?}", result.content_info);
+        };
+        assert_eq!(data.studio.as_deref(), Some("京都アニメーション"));
+
+        let result = provider.lookup("51", &Language::JaJp).await.unwrap();
+        assert_eq!(result.title, "CLANNAD");
+        assert!(provider.lookup("52", &Language::JaJp).await.is_err());
+        assert!(provider.lookup("../51", &Language::JaJp).await.is_err());
+
+        let hits = provider.search("CLANNAD", &Language::EnUs).await.unwrap();
+        assert_eq!(hits[0].id, "51");
+        assert_eq!(hits[0].title, "CLANNAD");
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Result, anyhow};
+
+use crate::{
+    api::{
+        dl_site::{DL_SITE_BASE, DLContentType},
+        provider::{MetadataProvider, ProviderCapabilities, ProviderFuture, ProviderResult},
+    },
+    core::Language,
+};
+
+/// DLSite work pages, looked up by the full id such as `RJ01239331`. The store
+/// is guessed from the prefix unless given as in `home/RJ01239331`.
+pub struct DLSiteProvider {
+    base: String,
+}
+
+impl Default for DLSiteProvider {
+    fn default() -> Self {
+        Self::with_base(DL_SITE_BASE)
+    }
+}
+
+impl DLSiteProvider {
+    pub fn with_base(base: impl Into<String>) -> Self {
+        Self { base: base.into() }
+    }
+
+    /// Splits an id into its store and the number without prefix
+    fn parse_id(id: &str) -> Result<(DLContentType, &str)> {
+        let invalid = || anyhow!("Invalid DLSite id '{}'", id);
+        let (store, full_id) = match id.trim().split_once('/') {
+            Some((store, full_id)) => (Some(store), full_id),
+            None => (None, id.trim()),
+        };
+        let prefix = full_id.get(..2).ok_or_else(invalid)?.to_uppercase();
+        let content_type = match store {
+            Some(store) => DLContentType::from_name_url(store).ok_or_else(invalid)?,
+            None => match prefix.as_str() {
+                "RJ" => DLContentType::DoujinR18,
+                "BJ" => DLContentType::ComicsR18,
+                "VJ" => DLContentType::HGames,
+                _ => return Err(invalid()),
+            },
+        };
+        let number = &full_id[2..];
+        if prefix != content_type.name_prefix()
+            || number.is_empty()
+            || !number.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        Ok((content_type, number))
+    }
+
+    async fn work(&self, id: &str, lang: &Language) -> Result<ProviderResult> {
+        let (content_type, number) = Self::parse_id(id)?;
+        let info = content_type.fetch_info_at(&self.base, number, lang).await?;
+        if info.title.is_empty() {
+            return Err(anyhow!("DLSite work '{}' not found", id));
+        }
+        let mut creators = vec![info.circle];
+        for name in info.scenario.into_iter().chain(info.illustration) {
+            if !creators.contains(&name) {
+                creators.push(name);
+            }
+        }
+        creators.retain(|c| !c.is_empty());
+        Ok(ProviderResult {
+            provider: self.name().to_string(),
+            id: content_type.build_id(number),
+            title: info.title,
+            alias: Vec::new(),
+            description: (!info.description.is_empty()).then(|| info.description.join("\n")),
+            tags: info.tags,
+            creators,
+            images: info.og_image.into_iter().collect(),
+            content_info: info.content_info,
+        })
+    }
+}
+
+impl MetadataProvider for DLSiteProvider {
+    fn name(&self) -> &'static str {
+        "dlsite"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            lookup: true,
+            search: false,
+            images: true,
+        }
+    }
+
+    fn lookup<'a>(&'a self, id: &'a str, lang: &'a Language) -> ProviderFuture<'a, ProviderResult> {
+        Box::pin(self.work(id, lang))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        api::{
+            init_client,
+            provider::stand_in::{Route, StandIn},
+        },
+        core::data::metadata::ContentInfo,
+    };
+
+    #[tokio::test]
+    async fn test_dl_site_provider() {
+        init_client().unwrap();
+        let server = StandIn::serve(vec![Route {
+            request: "GET /maniax/work/=/product_id/RJ01239331.html",
+            body: "",
+            response: include_str!("fixtures/dlsite_work.html"),
+        }]);
+        let provider = DLSiteProvider::with_base(server.url());
+
+        let result = provider
+            .lookup("RJ01239331", &Language::JaJp)
+            .await
+            .unwrap();
+        assert_eq!(result.id, "RJ01239331");
+        assert_eq!(result.title, "Recorded Voice Work");
+        assert_eq!(result.creators, vec!["Recorded Circle", "Writer", "Artist"]);
+        assert_eq!(result.tags, vec!["癒し", "バイノーラル"]);
+        assert_eq!(
+            result.description.as_deref(),
+            Some("First line\nSecond line")
+        );
+        assert_eq!(result.images.len(), 1);
+        assert!(matches!(result.content_info, ContentInfo::Audio(_)));
+
+        assert!(
+            provider
+                .lookup("home/RJ01239331", &Language::JaJp)
+                .await
+                .is_err()
+        );
+        assert!(
+            provider
+                .lookup("home/BJ01239331", &Language::JaJp)
+                .await
+                .is_err()
+        );
+        assert!(provider.lookup("XX123", &Language::JaJp).await.is_err());
+    }
+}
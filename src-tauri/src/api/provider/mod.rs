@@ -0,0 +1,246 @@
+//! Metadata providers look up works on external sites and normalize what they
+//! find into a [ProviderResult], which maps onto a [MetadataOption].
+
+use std::{
+    pin::Pin,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Result, anyhow};
+use log::info;
+use reqwest::{RequestBuilder, header::USER_AGENT};
+use serde::{Serialize, de::DeserializeOwned};
+use ts_rs::TS;
+
+pub use self::{
+    bangumi::BangumiProvider, dl_site::DLSiteProvider, steam::SteamProvider, vndb::VndbProvider,
+};
+use crate::{
+    api::get_client,
+    core::{
+        Language,
+        data::metadata::{ContentInfo, MetadataOption},
+    },
+};
+
+mod bangumi;
+mod dl_site;
+#[cfg(test)]
+mod stand_in;
+mod steam;
+mod vndb;
+
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+static PROVIDERS: RwLock<Vec<Arc<dyn MetadataProvider>>> = RwLock::new(Vec::new());
+
+/// What a provider supports
+#[derive(Debug, Clone, Copy, Default, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ProviderCapabilities {
+    /// [MetadataProvider::lookup] by the id on the site
+    pub lookup: bool,
+    /// [MetadataProvider::search] by keyword
+    pub search: bool,
+    /// Results carry image URLs
+    pub images: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ProviderInfo {
+    pub name: String,
+    pub capabilities: ProviderCapabilities,
+}
+
+/// A work as found by a provider
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ProviderResult {
+    pub provider: String,
+    /// Id on the site, accepted by [MetadataProvider::lookup]
+    pub id: String,
+    pub title: String,
+    pub alias: Vec<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// Developers, authors and performers
+    pub creators: Vec<String>,
+    /// Image URLs, the cover first
+    pub images: Vec<String>,
+    pub content_info: ContentInfo,
+}
+
+/// A search result, looked up by its id
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ProviderHit {
+    pub id: String,
+    pub title: String,
+    pub image: Option<String>,
+}
+
+impl ProviderResult {
+    /// Fields of an entry. Images are URLs and are left to the caller, which
+    /// stores them through [crate::cmd::util_process_img_web].
+    pub fn into_option(self) -> MetadataOption {
+        MetadataOption {
+            id: None,
+            title: (!self.title.is_empty()).then_some(self.title),
+            alias: (!self.alias.is_empty()).then_some(self.alias),
+            tags: (!self.tags.is_empty()).then_some(self.tags),
+            collection: None,
+            description: self.description,
+            image: None,
+            images: None,
+            rating: None,
+            custom: None,
+            content_info: Some(self.content_info),
+            archive_info: None,
+            archive_version: None,
+            flag_create_archive: false,
+        }
+    }
+}
+
+pub trait MetadataProvider: Send + Sync {
+    /// Unique name the provider is registered under
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> ProviderCapabilities;
+
+    fn lookup<'a>(&'a self, id: &'a str, lang: &'a Language) -> ProviderFuture<'a, ProviderResult>;
+
+    fn search<'a>(
+        &'a self,
+        keyword: &'a str,
+        lang: &'a Language,
+    ) -> ProviderFuture<'a, Vec<ProviderHit>> {
+        let _ = (keyword, lang);
+        Box::pin(async move { Err(anyhow!("Provider '{}' can not search", self.name())) })
+    }
+}
+
+/// Registers the built-in providers.
+pub(super) fn init_providers() -> Result<()> {
+    provider_register(DLSiteProvider::default())?;
+    provider_register(SteamProvider::default())?;
+    provider_register(VndbProvider::default())?;
+    provider_register(BangumiProvider::default())?;
+    Ok(())
+}
+
+/// Adds a provider, its name must not be taken.
+pub fn provider_register(provider: impl MetadataProvider + 'static) -> Result<()> {
+    let mut providers = PROVIDERS
+        .write()
+        .map_err(|_| anyhow!("Provider registry poisoned"))?;
+    if providers.iter().any(|p| p.name() == provider.name()) {
+        return Err(anyhow!("Provider '{}' already registered", provider.name()));
+    }
+    info!("Registered metadata provider '{}'", provider.name());
+    providers.push(Arc::new(provider));
+    Ok(())
+}
+
+pub fn provider_get(name: &str) -> Result<Arc<dyn MetadataProvider>> {
+    PROVIDERS
+        .read()
+        .map_err(|_| anyhow!("Provider registry poisoned"))?
+        .iter()
+        .find(|p| p.name() == name)
+        .cloned()
+        .ok_or_else(|| anyhow!("Provider '{}' not found", name))
+}
+
+pub fn provider_list() -> Vec<ProviderInfo> {
+    PROVIDERS
+        .read()
+        .map(|providers| {
+            providers
+                .iter()
+                .map(|p| ProviderInfo {
+                    name: p.name().to_string(),
+                    capabilities: p.capabilities(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Sends a request and parses the JSON response. Some sites refuse requests
+/// without a user agent.
+async fn request_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+    let response = request
+        .header(USER_AGENT, concat!("composer/", env!("CARGO_PKG_VERSION")))
+        .send()
+        .await?
+        .error_for_status()?;
+    let url = response.url().to_string();
+    let text = response.text().await?;
+    serde_json::from_str(&text).map_err(|e| anyhow!("Invalid response from {}: {}", url, e))
+}
+
+async fn get_json<T: DeserializeOwned>(url: &str) -> Result<T> {
+    request_json(get_client().get(url)).await
+}
+
+async fn post_json<T: DeserializeOwned>(url: &str, body: &serde_json::Value) -> Result<T> {
+    let request = get_client()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string());
+    request_json(request).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_provider_registry() {
+        struct Dummy(&'static str);
+        impl MetadataProvider for Dummy {
+            fn name(&self) -> &'static str {
+                self.0
+            }
+
+            fn capabilities(&self) -> ProviderCapabilities {
+                ProviderCapabilities {
+                    lookup: true,
+                    ..Default::default()
+                }
+            }
+
+            fn lookup<'a>(
+                &'a self,
+                id: &'a str,
+                _: &'a Language,
+            ) -> ProviderFuture<'a, ProviderResult> {
+                Box::pin(async move {
+                    Ok(ProviderResult {
+                        provider: self.0.to_string(),
+                        id: id.to_string(),
+                        title: String::from("Title"),
+                        ..Default::default()
+                    })
+                })
+            }
+        }
+
+        provider_register(Dummy("dummy")).unwrap();
+        assert!(provider_register(Dummy("dummy")).is_err());
+        assert!(provider_get("dummy").is_ok());
+        assert!(provider_get("missing").is_err());
+        let info = provider_list();
+        let dummy = info.iter().find(|p| p.name == "dummy").unwrap();
+        assert!(dummy.capabilities.lookup && !dummy.capabilities.search);
+
+        let provider = provider_get("dummy").unwrap();
+        let result = provider.lookup("1", &Language::EnUs).await.unwrap();
+        assert!(provider.search("title", &Language::EnUs).await.is_err());
+        let option = result.into_option();
+        assert_eq!(option.title.as_deref(), Some("Title"));
+        assert!(option.tags.is_none());
+    }
+}
@@ -0,0 +1,77 @@
+//! A local HTTP server replaying recorded responses, so providers are tested
+//! without the sites they talk to.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+/// A recorded response, served for requests whose `METHOD /path?query` starts
+/// with `request` and whose body contains `body`
+pub struct Route {
+    pub request: &'static str,
+    pub body: &'static str,
+    pub response: &'static str,
+}
+
+pub struct StandIn {
+    url: String,
+}
+
+impl StandIn {
+    /// Starts serving `routes` on a free port. Unmatched requests get a 404.
+    pub fn serve(routes: Vec<Route>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind stand-in");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                let _ = Self::respond(stream, &routes);
+            }
+        });
+        Self { url }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn respond(mut stream: TcpStream, routes: &[Route]) -> std::io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                length = value.trim().parse().unwrap_or_default();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        let body = String::from_utf8_lossy(&body);
+
+        let route = routes
+            .iter()
+            .find(|r| request.starts_with(r.request) && body.contains(r.body));
+        let (status, response) = match route {
+            Some(route) => ("200 OK", route.response),
+            None => ("404 Not Found", ""),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            response.len(),
+            response
+        )?;
+        stream.flush()
+    }
+}
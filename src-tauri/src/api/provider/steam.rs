@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::{
+    api::provider::{
+        MetadataProvider, ProviderCapabilities, ProviderFuture, ProviderHit, ProviderResult,
+        get_json,
+    },
+    core::{
+        Language,
+        data::metadata::{
+            AudioData, ContentInfo, GameData, GameDistribution, GameSysPlatform,
+            SteamDistributionData, VideoData,
+        },
+    },
+};
+
+const STEAM_BASE: &str = "https://store.steampowered.com";
+
+/// Steam store, looked up by app id
+pub struct SteamProvider {
+    base: String,
+}
+
+impl Default for SteamProvider {
+    fn default() -> Self {
+        Self::with_base(STEAM_BASE)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AppDetails {
+    success: bool,
+    data: Option<AppData>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AppData {
+    #[serde(rename = "type")]
+    app_type: String,
+    name: String,
+    short_description: String,
+    header_image: Option<String>,
+    developers: Vec<String>,
+    publishers: Vec<String>,
+    platforms: Platforms,
+    genres: Vec<Described>,
+    screenshots: Vec<Screenshot>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Platforms {
+    windows: bool,
+    mac: bool,
+    linux: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Described {
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Screenshot {
+    path_full: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoreSearch {
+    items: Vec<StoreItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoreItem {
+    id: u64,
+    name: String,
+    tiny_image: Option<String>,
+}
+
+impl SteamProvider {
+    pub fn with_base(base: impl Into<String>) -> Self {
+        Self { base: base.into() }
+    }
+
+    fn lang_code(lang: &Language) -> &'static str {
+        match lang {
+            Language::EnUs => "english",
+            Language::ZhCn => "schinese",
+            Language::JaJp => "japanese",
+        }
+    }
+
+    async fn app_details(&self, id: &str, lang: &Language) -> Result<ProviderResult> {
+        let app_id = id.trim();
+        let url = Url::parse_with_params(
+            &format!("{}/api/appdetails", self.base),
+            &[("appids", app_id), ("l", Self::lang_code(lang))],
+        )?;
+        let mut details = get_json::<HashMap<String, AppDetails>>(url.as_str()).await?;
+        let data = details
+            .remove(app_id)
+            .filter(|d| d.success)
+            .and_then(|d| d.data)
+            .ok_or_else(|| anyhow!("Steam app '{}' not found", app_id))?;
+
+        let distribution = GameDistribution::Steam(SteamDistributionData {
+            app_id: app_id.to_string(),
+        });
+        let content_info = match data.app_type.as_str() {
+            "music" => ContentInfo::Audio(AudioData {
+                circle: data.developers.first().cloned(),
+                distribution,
+                ..Default::default()
+            }),
+            "video" | "series" | "episode" => ContentInfo::Video(VideoData {
+                studio: data.developers.first().cloned(),
+                distribution,
+                ..Default::default()
+            }),
+            _ => ContentInfo::Game(GameData {
+                developer: data.developers.first().cloned(),
+                publisher: data.publishers.first().cloned(),
+                sys_platform: [
+                    (data.platforms.windows, GameSysPlatform::Windows),
+                    (data.platforms.mac, GameSysPlatform::MacOS),
+                    (data.platforms.linux, GameSysPlatform::Linux),
+                ]
+                .into_iter()
+                .filter_map(|(supported, platform)| supported.then_some(platform))
+                .collect(),
+                distribution,
+                ..Default::default()
+            }),
+        };
+        let mut creators = data.developers;
+        for publisher in data.publishers {
+            if !creators.contains(&publisher) {
+                creators.push(publisher);
+            }
+        }
+        Ok(ProviderResult {
+            provider: self.name().to_string(),
+            id: app_id.to_string(),
+            title: data.name,
+            alias: Vec::new(),
+            description: Some(data.short_description).filter(|d| !d.is_empty()),
+            tags: data.genres.into_iter().map(|g| g.description).collect(),
+            creators,
+            images: data
+                .header_image
+                .into_iter()
+                .chain(data.screenshots.into_iter().map(|s| s.path_full))
+                .collect(),
+            content_info,
+        })
+    }
+
+    async fn store_search(&self, keyword: &str, lang: &Language) -> Result<Vec<ProviderHit>> {
+        let url = Url::parse_with_params(
+            &format!("{}/api/storesearch/", self.base),
+            &[
+                ("term", keyword),
+                ("l", Self::lang_code(lang)),
+                ("cc", "US"),
+            ],
+        )?;
+        let search = get_json::<StoreSearch>(url.as_str()).await?;
+        Ok(search
+            .items
+            .into_iter()
+            .map(|item| ProviderHit {
+                id: item.id.to_string(),
+                title: item.name,
+                image: item.tiny_image,
+            })
+            .collect())
+    }
+}
+
+impl MetadataProvider for SteamProvider {
+    fn name(&self) -> &'static str {
+        "steam"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            lookup: true,
+            search: true,
+            images: true,
+        }
+    }
+
+    fn lookup<'a>(&'a self, id: &'a str, lang: &'a Language) -> ProviderFuture<'a, ProviderResult> {
+        Box::pin(self.app_details(id, lang))
+    }
+
+    fn search<'a>(
+        &'a self,
+        keyword: &'a str,
+        lang: &'a Language,
+    ) -> ProviderFuture<'a, Vec<ProviderHit>> {
+        Box::pin(self.store_search(keyword, lang))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::{
+        init_client,
+        provider::stand_in::{Route, StandIn},
+    };
+
+    #[tokio::test]
+    async fn test_steam_provider() {
+        init_client().unwrap();
+        let server = StandIn::serve(vec![
+            Route {
+                request: "GET /api/appdetails?appids=1245620&",
+                body: "",
+                response: include_str!("fixtures/steam_appdetails.json"),
+            },
+            Route {
+                request: "GET /api/appdetails?appids=1&",
+                body: "",
+                response: include_str!("fixtures/steam_appdetails_missing.json"),
+            },
+            Route {
+                request: "GET /api/storesearch/?term=elden+ring",
+                body: "",
+                response: include_str!("fixtures/steam_storesearch.json"),
+            },
+        ]);
+        let provider = SteamProvider::with_base(server.url());
+
+        let result = provider.lookup("1245620", &Language::EnUs).await.unwrap();
+        assert_eq!(result.title, "ELDEN RING");
+        assert_eq!(result.tags, vec!["Action", "RPG"]);
+        assert_eq!(
+            result.creators,
+            vec!["FromSoftware, Inc.", "Bandai Namco Entertainment"]
+        );
+        assert_eq!(result.images.len(), 2);
+        let ContentInfo::Game(data) = &result.content_info else {
+            panic!("expected game, got {:?}", result.content_info);
+        };
+        assert_eq!(data.sys_platform, vec![GameSysPlatform::Windows]);
+        assert!(matches!(
+            result.content_info.distribution(),
+            Some(GameDistribution::Steam(d)) if d.app_id == "1245620"
+        ));
+
+        assert!(provider.lookup("1", &Language::EnUs).await.is_err());
+        assert!(provider.lookup("2", &Language::EnUs).await.is_err());
+
+        let hits = provider
+            .search("elden ring", &Language::EnUs)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].id, "1245620");
+    }
+}
@@ -0,0 +1,248 @@
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    api::provider::{
+        MetadataProvider, ProviderCapabilities, ProviderFuture, ProviderHit, ProviderResult,
+        post_json,
+    },
+    core::{
+        Language,
+        data::metadata::{
+            ContentInfo, GameData, GameDistribution, GameSysPlatform, GameType,
+            OtherDistributionData,
+        },
+    },
+};
+
+const VNDB_BASE: &str = "https://api.vndb.org/kana";
+const VN_FIELDS: &str = "title, alttitle, aliases, description, image.url, screenshots.url, \
+                         tags.name, tags.spoiler, developers.name, platforms";
+
+/// The visual novel database, looked up by VN id such as `v17`
+pub struct VndbProvider {
+    base: String,
+}
+
+impl Default for VndbProvider {
+    fn default() -> Self {
+        Self::with_base(VNDB_BASE)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Results<T> {
+    results: Vec<T>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Vn {
+    id: String,
+    title: String,
+    alttitle: Option<String>,
+    aliases: Vec<String>,
+    description: Option<String>,
+    image: Option<Image>,
+    screenshots: Vec<Image>,
+    tags: Vec<Tag>,
+    developers: Vec<Developer>,
+    platforms: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Image {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+    name: String,
+    spoiler: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct Developer {
+    name: String,
+}
+
+impl VndbProvider {
+    pub fn with_base(base: impl Into<String>) -> Self {
+        Self { base: base.into() }
+    }
+
+    fn platform(code: &str) -> Option<GameSysPlatform> {
+        match code {
+            "win" => Some(GameSysPlatform::Windows),
+            "lin" => Some(GameSysPlatform::Linux),
+            "mac" => Some(GameSysPlatform::MacOS),
+            "and" => Some(GameSysPlatform::Android),
+            "ios" => Some(GameSysPlatform::IOS),
+            "web" => Some(GameSysPlatform::Web),
+            _ => None,
+        }
+    }
+
+    async fn vn(&self, id: &str, lang: &Language) -> Result<ProviderResult> {
+        let id = id.trim().to_lowercase();
+        let id = if id.starts_with('v') {
+            id
+        } else {
+            format!("v{id}")
+        };
+        let body = json!({ "filters": ["id", "=", id], "fields": VN_FIELDS });
+        let vn = post_json::<Results<Vn>>(&format!("{}/vn", self.base), &body)
+            .await?
+            .results
+            .pop()
+            .ok_or_else(|| anyhow!("VNDB entry '{}' not found", id))?;
+
+        // The main title is romanized, the original one is in `alttitle`
+        let (title, other) = match (lang, vn.alttitle) {
+            (Language::JaJp, Some(alt)) => (alt, Some(vn.title)),
+            (_, alt) => (vn.title, alt),
+        };
+        let mut alias = other
+            .into_iter()
+            .filter(|a| *a != title)
+            .collect::<Vec<_>>();
+        alias.extend(vn.aliases);
+        let creators = vn
+            .developers
+            .into_iter()
+            .map(|d| d.name)
+            .collect::<Vec<_>>();
+        let content_info = ContentInfo::Game(GameData {
+            game_type: GameType::AVG,
+            developer: creators.first().cloned(),
+            sys_platform: vn
+                .platforms
+                .iter()
+                .filter_map(|p| Self::platform(p))
+                .collect(),
+            distribution: GameDistribution::Other(OtherDistributionData {
+                name: String::from("VNDB"),
+                id: vn.id.clone(),
+            }),
+            ..Default::default()
+        });
+        Ok(ProviderResult {
+            provider: self.name().to_string(),
+            id: vn.id,
+            title,
+            alias,
+            description: vn.description,
+            tags: vn
+                .tags
+                .into_iter()
+                .filter(|t| t.spoiler == 0)
+                .map(|t| t.name)
+                .collect(),
+            creators,
+            images: vn
+                .image
+                .into_iter()
+                .chain(vn.screenshots)
+                .map(|i| i.url)
+                .collect(),
+            content_info,
+        })
+    }
+
+    async fn vn_search(&self, keyword: &str) -> Result<Vec<ProviderHit>> {
+        let body = json!({
+            "filters": ["search", "=", keyword],
+            "fields": "title, image.url",
+            "results": 10,
+        });
+        let results = post_json::<Results<Vn>>(&format!("{}/vn", self.base), &body).await?;
+        Ok(results
+            .results
+            .into_iter()
+            .map(|vn| ProviderHit {
+                id: vn.id,
+                title: vn.title,
+                image: vn.image.map(|i| i.url),
+            })
+            .collect())
+    }
+}
+
+impl MetadataProvider for VndbProvider {
+    fn name(&self) -> &'static str {
+        "vndb"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            lookup: true,
+            search: true,
+            images: true,
+        }
+    }
+
+    fn lookup<'a>(&'a self, id: &'a str, lang: &'a Language) -> ProviderFuture<'a, ProviderResult> {
+        Box::pin(self.vn(id, lang))
+    }
+
+    fn search<'a>(
+        &'a self,
+        keyword: &'a str,
+        _: &'a Language,
+    ) -> ProviderFuture<'a, Vec<ProviderHit>> {
+        Box::pin(self.vn_search(keyword))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::{
+        init_client,
+        provider::stand_in::{Route, StandIn},
+    };
+
+    #[tokio::test]
+    async fn test_vndb_provider() {
+        init_client().unwrap();
+        let server = StandIn::serve(vec![
+            Route {
+                request: "POST /vn",
+                body: r#"["id","=","v17"]"#,
+                response: include_str!("fixtures/vndb_vn.json"),
+            },
+            Route {
+                request: "POST /vn",
+                body: r#"["search","=","ever17"]"#,
+                response: include_str!("fixtures/vndb_search.json"),
+            },
+            Route {
+                request: "POST /vn",
+                body: "",
+                response: r#"{"results":[],"more":false}"#,
+            },
+        ]);
+        let provider = VndbProvider::with_base(server.url());
+
+        let result = provider.lookup("17", &Language::EnUs).await.unwrap();
+        assert_eq!(result.id, "v17");
+        assert_eq!(result.title, "Ever17 -the out of infinity-");
+        assert_eq!(result.alias, vec!["E17"]);
+        // Spoiler tags are left out
+        assert_eq!(result.tags, vec!["Mystery"]);
+        assert_eq!(result.creators, vec!["KID"]);
+        assert_eq!(result.images.len(), 2);
+        let ContentInfo::Game(data) = &result.content_info else {
+            panic!("expected game, got {:?}", result.content_info);
+        };
+        assert_eq!(data.game_type, GameType::AVG);
+        assert_eq!(data.sys_platform, vec![GameSysPlatform::Windows]);
+
+        assert!(provider.lookup("v1", &Language::EnUs).await.is_err());
+
+        let hits = provider.search("ever17", &Language::EnUs).await.unwrap();
+        assert_eq!(hits[0].id, "v17");
+        assert!(hits[0].image.is_some());
+    }
+}
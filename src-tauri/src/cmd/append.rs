@@ -1,13 +1,23 @@
 use serde::Deserialize;
 use ts_rs::TS;
 
-use crate::api::dl_site::DLContentType;
+use crate::{
+    api::dl_site::DLContentType,
+    core::data::{
+        library::{ExportFormat, ImageSource, ImportMode, MergeArchive},
+        metadata::{BulkEdit, ImageRole},
+        query::SortKey,
+    },
+};
 
 #[derive(Debug, Deserialize, TS)]
 #[ts(export, export_to = "../../src/api/types.ts")]
 pub struct DeployArg {
     pub use_config_dir: bool,
     pub target_dir: Option<String>,
+    /// Archive version to deploy, the latest one if not set
+    #[serde(default)]
+    pub version: Option<String>,
 }
 
 #[derive(Debug, Deserialize, TS)]
@@ -17,6 +27,139 @@ pub struct DLFetchArg {
     pub content_type: DLContentType,
 }
 
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ProviderLookupArg {
+    /// Name of a registered provider
+    pub provider: String,
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ProviderSearchArg {
+    /// Name of a registered provider
+    pub provider: String,
+    pub keyword: String,
+}
+
+/// Conditions looked up through the library index tables, all given conditions
+/// must match
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct IndexQueryArg {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub rating_min: Option<u8>,
+    #[serde(default)]
+    pub rating_max: Option<u8>,
+}
+
+/// Query string with sorting and pagination, see [crate::core::data::query]
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct QueryArg {
+    pub query: String,
+    #[serde(default)]
+    pub sort: SortKey,
+    #[serde(default)]
+    pub desc: bool,
+    #[serde(default)]
+    pub offset: u32,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// Sorting and pagination of the summary listing
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ListArg {
+    #[serde(default)]
+    pub sort: SortKey,
+    #[serde(default)]
+    pub desc: bool,
+    #[serde(default)]
+    pub offset: u32,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// Applies `edit` to every entry in `ids` within one transaction
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct BulkEditArg {
+    pub ids: Vec<String>,
+    pub edit: BulkEdit,
+}
+
+/// Description and cover of a collection, its members are kept
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct CollectionArg {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub cover: Option<String>,
+}
+
+/// Notes and pin of an archive version, fields not given are kept
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ArchiveVersionArg {
+    pub id: String,
+    pub version: String,
+    /// New notes, an empty string clears them
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub pinned: Option<bool>,
+}
+
+/// Merges the duplicate `remove` into `keep`
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct DuplicateMergeArg {
+    pub keep: String,
+    pub remove: String,
+    #[serde(default)]
+    pub archive: MergeArchive,
+}
+
+/// Library import from a JSON export, `path` defaults to the export file in the
+/// data directory
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ImportArg {
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub mode: ImportMode,
+}
+
+/// Library export, `path` defaults to `lib.<ext>` in the data directory.
+/// Entries are selected by `ids` and `query` when given, `fields` limits the
+/// written fields or CSV columns, bundles always hold whole entries.
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ExportArg {
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub ids: Option<Vec<String>>,
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,3 +167,15 @@ pub(super) struct FrontendConfig {
     pub dev_mode: bool,
     pub dark_mode: bool,
 }
+
+/// Images from `source` added to entry `id` with `role`, at most `limit`
+/// images are processed, 50 by default
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ImageImportArg {
+    pub id: String,
+    pub source: ImageSource,
+    pub role: ImageRole,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
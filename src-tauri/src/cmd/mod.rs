@@ -10,14 +10,27 @@ use tauri_plugin_pinia::ManagerExt;
 
 use self::append::FrontendConfig;
 use crate::{
-    api::dl_site::{DLContentFetch, DLFetchInfo},
-    cmd::append::{DLFetchArg, DeployArg},
+    api::{
+        dl_site::{DLContentFetch, DLFetchInfo},
+        provider::{self, ProviderHit, ProviderInfo, ProviderResult},
+    },
+    cmd::append::{
+        ArchiveVersionArg, BulkEditArg, CollectionArg, DLFetchArg, DeployArg, DuplicateMergeArg,
+        ExportArg, ImageImportArg, ImportArg, IndexQueryArg, ListArg, ProviderLookupArg,
+        ProviderSearchArg, QueryArg,
+    },
     core::{
         Language, StringResult,
-        config::{AppConfig, ConfigState},
+        config::{AppConfig, ConfigState, LibraryProfile},
         data::{
-            library,
-            metadata::{Metadata, MetadataOption},
+            library::{
+                self, BackupInfo, CollectionRecord, DoctorIssue, DoctorReport, DuplicateGroup,
+                HistoryRecord, ImageImportReport, ImportReport, LibraryStats, MigrationReport,
+                PlaySession, RelationGraph, RelationKind, SaveLocations, SaveSnapshot,
+                SearchResult, TagInfo, TagRecord, TrashEntry,
+            },
+            metadata::{BulkEditResult, Metadata, MetadataOption, MetadataSummary, SummaryPage},
+            query::QueryPage,
             state::DataState,
         },
         util,
@@ -34,11 +47,24 @@ pub async fn metadata_update(
     library::metadata_update(opt, data).await.string_result()
 }
 
+#[command]
+pub async fn metadata_bulk_edit(
+    arg: BulkEditArg,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<BulkEditResult>> {
+    library::metadata_bulk_edit(arg, data).await.string_result()
+}
+
 #[command]
 pub async fn metadata_get_all(data: State<'_, DataState>) -> CommandResult<Vec<Metadata>> {
     library::metadata_get_all(data).await.string_result()
 }
 
+#[command]
+pub async fn metadata_list(arg: ListArg, data: State<'_, DataState>) -> CommandResult<SummaryPage> {
+    library::metadata_list(arg, data).await.string_result()
+}
+
 #[command]
 pub async fn metadata_get(
     key: String,
@@ -60,12 +86,42 @@ pub async fn metadata_deploy(key: String, arg: DeployArg, app: AppHandle) -> Com
 }
 
 #[command]
-pub async fn metadata_deploy_off(key: String, data: State<'_, DataState>) -> CommandResult<()> {
-    library::metadata_deploy_off(key, data)
+pub async fn metadata_deploy_off(
+    key: String,
+    force: bool,
+    data: State<'_, DataState>,
+) -> CommandResult<()> {
+    library::metadata_deploy_off(key, force, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_archive_version_update(
+    arg: ArchiveVersionArg,
+    data: State<'_, DataState>,
+) -> CommandResult<()> {
+    library::archive_version_update(arg, data)
         .await
         .string_result()
 }
 
+#[command]
+pub async fn metadata_archive_version_remove(
+    key: String,
+    version: String,
+    data: State<'_, DataState>,
+) -> CommandResult<()> {
+    library::archive_version_remove(key, version, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_archive_retention_apply(app: AppHandle) -> CommandResult<u32> {
+    library::archive_retention_apply(app).await.string_result()
+}
+
 #[command]
 pub fn metadata_collection_cache(data: State<'_, DataState>) -> CommandResult<Vec<String>> {
     library::collection_cache_get(data).string_result()
@@ -77,13 +133,352 @@ pub fn metadata_deployment_cache(data: State<'_, DataState>) -> CommandResult<Ve
 }
 
 #[command]
-pub async fn metadata_export(app: AppHandle) -> CommandResult<()> {
-    library::export_library(app).await.string_result()
+pub async fn metadata_collection_list(
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<CollectionRecord>> {
+    library::collection_list(data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_collection_members(
+    name: String,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<MetadataSummary>> {
+    library::collection_members(name, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_collection_save(
+    arg: CollectionArg,
+    data: State<'_, DataState>,
+) -> CommandResult<CollectionRecord> {
+    library::collection_save(arg, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_collection_rename(
+    from: String,
+    to: String,
+    data: State<'_, DataState>,
+) -> CommandResult<u32> {
+    library::collection_rename(from, to, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_collection_reorder(
+    name: String,
+    ids: Vec<String>,
+    data: State<'_, DataState>,
+) -> CommandResult<()> {
+    library::collection_reorder(name, ids, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_index_query(
+    arg: IndexQueryArg,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<Metadata>> {
+    library::metadata_index_query(arg, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_index_tags(data: State<'_, DataState>) -> CommandResult<Vec<(String, u64)>> {
+    library::index_tag_list(data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_tag_list(data: State<'_, DataState>) -> CommandResult<Vec<TagInfo>> {
+    library::tag_list(data).await.string_result()
 }
 
 #[command]
-pub async fn metadata_import(app: AppHandle) -> CommandResult<()> {
-    library::import_library(app).await.string_result()
+pub async fn metadata_tag_save(tag: TagRecord, data: State<'_, DataState>) -> CommandResult<u32> {
+    library::tag_save(tag, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_tag_rename(
+    from: String,
+    to: String,
+    data: State<'_, DataState>,
+) -> CommandResult<u32> {
+    library::tag_rename(from, to, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_tag_merge(
+    sources: Vec<String>,
+    target: String,
+    data: State<'_, DataState>,
+) -> CommandResult<u32> {
+    library::tag_merge(sources, target, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_tag_delete(name: String, data: State<'_, DataState>) -> CommandResult<u32> {
+    library::tag_delete(name, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_recent(
+    limit: u32,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<Metadata>> {
+    library::metadata_recent(limit, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_search(
+    query: String,
+    limit: Option<u32>,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<SearchResult>> {
+    library::metadata_search(query, limit, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_query(arg: QueryArg, data: State<'_, DataState>) -> CommandResult<QueryPage> {
+    library::metadata_query(arg, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_duplicates(
+    min_score: Option<u32>,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<DuplicateGroup>> {
+    library::duplicate_find(min_score, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_duplicate_merge(
+    arg: DuplicateMergeArg,
+    data: State<'_, DataState>,
+) -> CommandResult<Metadata> {
+    library::duplicate_merge(arg, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_stats(data: State<'_, DataState>) -> CommandResult<LibraryStats> {
+    library::stats_get(data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_image_import(
+    arg: ImageImportArg,
+    app: AppHandle,
+) -> CommandResult<ImageImportReport> {
+    library::image_import(arg, app).await.string_result()
+}
+
+#[command]
+pub async fn metadata_relation_add(
+    from: String,
+    kind: RelationKind,
+    to: String,
+    data: State<'_, DataState>,
+) -> CommandResult<()> {
+    library::relation_add(from, kind, to, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_relation_remove(
+    from: String,
+    kind: RelationKind,
+    to: String,
+    data: State<'_, DataState>,
+) -> CommandResult<bool> {
+    library::relation_remove(from, kind, to, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_relation_graph(
+    id: String,
+    depth: Option<u32>,
+    data: State<'_, DataState>,
+) -> CommandResult<RelationGraph> {
+    library::relation_graph(id, depth, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_launch(key: String, app: AppHandle) -> CommandResult<()> {
+    library::launch(key, app).await.string_result()
+}
+
+#[command]
+pub async fn metadata_launch_candidates(
+    key: String,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<String>> {
+    library::launch_candidates_get(key, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_launch_set(
+    key: String,
+    executable: Option<String>,
+    data: State<'_, DataState>,
+) -> CommandResult<()> {
+    library::launch_set(key, executable, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub fn metadata_launch_running(data: State<'_, DataState>) -> CommandResult<Vec<String>> {
+    library::launch_running(data).string_result()
+}
+
+#[command]
+pub async fn metadata_play_sessions(
+    key: String,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<PlaySession>> {
+    library::play_sessions(key, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_save_locations(
+    key: String,
+    data: State<'_, DataState>,
+) -> CommandResult<SaveLocations> {
+    library::save_locations_get(key, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_save_locations_set(
+    key: String,
+    locations: Vec<String>,
+    data: State<'_, DataState>,
+) -> CommandResult<()> {
+    library::save_locations_set(key, locations, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_save_snapshot(key: String, app: AppHandle) -> CommandResult<SaveSnapshot> {
+    library::save_snapshot(key, app).await.string_result()
+}
+
+#[command]
+pub async fn metadata_save_snapshots(
+    key: String,
+    app: AppHandle,
+) -> CommandResult<Vec<SaveSnapshot>> {
+    library::save_snapshots(key, app).await.string_result()
+}
+
+#[command]
+pub async fn metadata_save_restore(key: String, name: String, app: AppHandle) -> CommandResult<()> {
+    library::save_restore(key, name, app).await.string_result()
+}
+
+#[command]
+pub async fn metadata_history(
+    key: String,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<HistoryRecord>> {
+    library::history_list(key, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_revert(
+    key: String,
+    seq: u64,
+    data: State<'_, DataState>,
+) -> CommandResult<()> {
+    library::history_revert(key, seq, data)
+        .await
+        .string_result()
+}
+
+#[command]
+pub async fn metadata_undo(
+    count: u32,
+    data: State<'_, DataState>,
+) -> CommandResult<Vec<HistoryRecord>> {
+    library::history_undo(count, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_trash_list(data: State<'_, DataState>) -> CommandResult<Vec<TrashEntry>> {
+    library::trash_list(data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_trash_restore(
+    key: String,
+    data: State<'_, DataState>,
+) -> CommandResult<Metadata> {
+    library::trash_restore(key, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_trash_purge(
+    keys: Option<Vec<String>>,
+    data: State<'_, DataState>,
+) -> CommandResult<u32> {
+    library::trash_purge(keys, data).await.string_result()
+}
+
+#[command]
+pub async fn metadata_export(arg: ExportArg, app: AppHandle) -> CommandResult<String> {
+    library::export_library(arg, app).await.string_result()
+}
+
+#[command]
+pub async fn metadata_import(arg: ImportArg, app: AppHandle) -> CommandResult<ImportReport> {
+    library::import_library(arg, app).await.string_result()
+}
+
+#[command]
+pub async fn metadata_import_preview(
+    arg: ImportArg,
+    app: AppHandle,
+) -> CommandResult<ImportReport> {
+    library::import_preview(arg, app).await.string_result()
+}
+
+#[command]
+pub async fn backup_list(app: AppHandle) -> CommandResult<Vec<BackupInfo>> {
+    library::backup_list(app).await.string_result()
+}
+
+#[command]
+pub async fn backup_create(app: AppHandle) -> CommandResult<BackupInfo> {
+    library::backup_create(app).await.string_result()
+}
+
+#[command]
+pub async fn backup_verify(name: String, app: AppHandle) -> CommandResult<BackupInfo> {
+    library::backup_verify(name, app).await.string_result()
+}
+
+#[command]
+pub async fn backup_restore(name: String, app: AppHandle) -> CommandResult<()> {
+    library::backup_restore(name, app).await.string_result()
 }
 
 #[command]
@@ -125,6 +520,21 @@ pub async fn util_recalculate_archive_size(app: AppHandle) -> CommandResult<()>
     library::recalculate_archive_size(app).await.string_result()
 }
 
+#[command]
+pub async fn util_doctor_check(app: AppHandle) -> CommandResult<DoctorReport> {
+    library::doctor_check(app).await.string_result()
+}
+
+#[command]
+pub async fn util_doctor_repair(issues: Vec<DoctorIssue>, app: AppHandle) -> CommandResult<u32> {
+    library::doctor_repair(issues, app).await.string_result()
+}
+
+#[command]
+pub async fn util_migration_report(data: State<'_, DataState>) -> CommandResult<MigrationReport> {
+    library::migration_dry_run(data).await.string_result()
+}
+
 #[command]
 pub async fn util_dl_fetch_info(arg: DLFetchArg) -> CommandResult<DLFetchInfo> {
     arg.content_type
@@ -133,6 +543,41 @@ pub async fn util_dl_fetch_info(arg: DLFetchArg) -> CommandResult<DLFetchInfo> {
         .string_result()
 }
 
+#[command]
+pub fn util_provider_list() -> Vec<ProviderInfo> {
+    provider::provider_list()
+}
+
+#[command]
+pub async fn util_provider_lookup(
+    arg: ProviderLookupArg,
+    app: AppHandle,
+) -> CommandResult<ProviderResult> {
+    let lang = app.state::<ConfigState>().get().lang().clone();
+    async {
+        provider::provider_get(&arg.provider)?
+            .lookup(&arg.id, &lang)
+            .await
+    }
+    .await
+    .string_result()
+}
+
+#[command]
+pub async fn util_provider_search(
+    arg: ProviderSearchArg,
+    app: AppHandle,
+) -> CommandResult<Vec<ProviderHit>> {
+    let lang = app.state::<ConfigState>().get().lang().clone();
+    async {
+        provider::provider_get(&arg.provider)?
+            .search(&arg.keyword, &lang)
+            .await
+    }
+    .await
+    .string_result()
+}
+
 #[command]
 pub fn util_dark_state(app: AppHandle) -> bool {
     app.pinia()
@@ -205,3 +650,30 @@ pub fn config_update(
 ) -> CommandResult<()> {
     config.update_field(&name, value).string_result()
 }
+
+#[command]
+pub fn config_profile_save(
+    profile: LibraryProfile,
+    config: State<'_, ConfigState>,
+) -> CommandResult<AppConfig> {
+    config
+        .profile_save(profile)
+        .map(|_| config.get())
+        .string_result()
+}
+
+#[command]
+pub fn config_profile_remove(
+    name: String,
+    config: State<'_, ConfigState>,
+) -> CommandResult<AppConfig> {
+    config
+        .profile_remove(&name)
+        .map(|_| config.get())
+        .string_result()
+}
+
+#[command]
+pub async fn config_profile_switch(name: String, app: AppHandle) -> CommandResult<AppConfig> {
+    library::library_switch(name, app).await.string_result()
+}
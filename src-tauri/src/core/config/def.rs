@@ -21,10 +21,24 @@ const CONFIG_FILE_NAME: &str = "Config.toml";
 
 const DIR_NAME_ARCHIVE: &str = "archive";
 const DIR_NAME_IMAGE: &str = "image";
+const DIR_NAME_BACKUP: &str = "backup";
+const DIR_NAME_SAVE: &str = "save";
 
 const FIELD_LANG: &str = "lang";
 const FIELD_PATH_DATA: &str = "path_data";
 const FIELD_PATH_DEPLOY: &str = "path_deploy";
+const FIELD_TRASH_RETENTION_DAYS: &str = "trash_retention_days";
+const FIELD_BACKUP: &str = "backup";
+const FIELD_CUSTOM_FIELDS: &str = "custom_fields";
+const FIELD_ARCHIVE_RETENTION: &str = "archive_retention";
+const FIELD_SAVE_RETENTION: &str = "save_retention";
+const FIELD_HISTORY_LIMIT: &str = "history_limit";
+
+const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+const DEFAULT_PROFILE_NAME: &str = "default";
+const DEFAULT_BACKUP_RETENTION: u32 = 5;
+const DEFAULT_SAVE_RETENTION: u32 = 10;
+const DEFAULT_HISTORY_LIMIT: u32 = 5000;
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../../src/api/types.ts")]
@@ -35,6 +49,157 @@ pub struct AppConfig {
     path_data: PathBuf,
     #[serde(default)]
     path_deploy: Option<PathBuf>,
+    /// Days before a deleted entry is purged from trash, `0` keeps it forever
+    #[serde(default = "default_trash_retention_days")]
+    trash_retention_days: u32,
+    #[serde(default)]
+    backup: BackupPolicy,
+    /// Name of the active profile, `lang` and the paths above are its values
+    #[serde(default)]
+    profile: String,
+    #[serde(default)]
+    profiles: Vec<LibraryProfile>,
+    /// Fields users add to every entry, values are stored on each entry
+    #[serde(default)]
+    custom_fields: Vec<CustomField>,
+    #[serde(default)]
+    archive_retention: ArchiveRetention,
+    /// Save snapshots kept per entry, older ones are removed, `0` keeps all
+    #[serde(default = "default_save_retention")]
+    save_retention: u32,
+    /// History records kept across the library, older ones are dropped, `0`
+    /// keeps all
+    #[serde(default = "default_history_limit")]
+    history_limit: u32,
+}
+
+/// A named library with its own data directory, deploy directory and language
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct LibraryProfile {
+    pub name: String,
+    pub path_data: PathBuf,
+    #[serde(default)]
+    pub path_deploy: Option<PathBuf>,
+    #[serde(default)]
+    pub lang: Language,
+}
+
+/// Declaration of a user defined field
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct CustomField {
+    /// Key of the values, letters, digits and `_` only so it can be queried
+    pub name: String,
+    /// Display name, the name is shown if empty
+    #[serde(default)]
+    pub label: String,
+    pub kind: CustomFieldKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", content = "options")]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum CustomFieldKind {
+    String,
+    Number,
+    Bool,
+    Date,
+    /// One of the given options
+    Enum(Vec<String>),
+    Url,
+}
+
+fn custom_fields_check(fields: &[CustomField]) -> Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if field.name.is_empty()
+            || !field
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(anyhow!("Invalid custom field name '{}'", field.name));
+        }
+        if fields[..i].iter().any(|f| f.name == field.name) {
+            return Err(anyhow!("Duplicate custom field '{}'", field.name));
+        }
+        if let CustomFieldKind::Enum(options) = &field.kind
+            && options.is_empty()
+        {
+            return Err(anyhow!("Custom field '{}' has no options", field.name));
+        }
+    }
+    Ok(())
+}
+
+fn default_trash_retention_days() -> u32 {
+    DEFAULT_TRASH_RETENTION_DAYS
+}
+
+fn default_save_retention() -> u32 {
+    DEFAULT_SAVE_RETENTION
+}
+
+fn default_history_limit() -> u32 {
+    DEFAULT_HISTORY_LIMIT
+}
+
+/// When and where database backups are taken
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct BackupPolicy {
+    /// Backups to keep per trigger, older ones are removed, `0` keeps all
+    #[serde(default = "default_backup_retention")]
+    pub retention: u32,
+    #[serde(default = "default_true")]
+    pub on_startup: bool,
+    #[serde(default)]
+    pub on_shutdown: bool,
+    #[serde(default = "default_true")]
+    pub before_import: bool,
+    #[serde(default = "default_true")]
+    pub before_migration: bool,
+    /// Hours between periodic backups while running, `0` disables them
+    #[serde(default)]
+    pub interval_hours: u32,
+    /// Backup directory, defaults to `backup` in the data directory
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        Self {
+            retention: DEFAULT_BACKUP_RETENTION,
+            on_startup: true,
+            on_shutdown: false,
+            before_import: true,
+            before_migration: true,
+            interval_hours: 0,
+            dir: None,
+        }
+    }
+}
+
+fn default_backup_retention() -> u32 {
+    DEFAULT_BACKUP_RETENTION
+}
+
+/// Which old archive versions are removed when an entry gets a new one. The
+/// latest, the deployed and pinned versions are always kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ArchiveRetention {
+    /// Versions to keep per entry, older ones are removed, `0` keeps all
+    #[serde(default)]
+    pub keep_versions: u32,
+    /// Days after which an old version is removed, `0` keeps it forever
+    #[serde(default)]
+    pub keep_days: u32,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for AppConfig {
@@ -43,11 +208,23 @@ impl Default for AppConfig {
             lang: Language::default(),
             path_data: PathBuf::from("."),
             path_deploy: None,
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+            backup: BackupPolicy::default(),
+            profile: String::from(DEFAULT_PROFILE_NAME),
+            profiles: Vec::new(),
+            custom_fields: Vec::new(),
+            archive_retention: ArchiveRetention::default(),
+            save_retention: DEFAULT_SAVE_RETENTION,
+            history_limit: DEFAULT_HISTORY_LIMIT,
         }
     }
 }
 
 impl AppConfig {
+    pub fn lang(&self) -> &Language {
+        &self.lang
+    }
+
     pub fn path_data(&self) -> &Path {
         &self.path_data.as_ref()
     }
@@ -56,6 +233,45 @@ impl AppConfig {
         self.path_deploy.as_deref()
     }
 
+    pub fn trash_retention_days(&self) -> u32 {
+        self.trash_retention_days
+    }
+
+    pub fn backup(&self) -> &BackupPolicy {
+        &self.backup
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    pub fn profiles(&self) -> &[LibraryProfile] {
+        &self.profiles
+    }
+
+    pub fn custom_fields(&self) -> &[CustomField] {
+        &self.custom_fields
+    }
+
+    pub fn archive_retention(&self) -> &ArchiveRetention {
+        &self.archive_retention
+    }
+
+    pub fn save_retention(&self) -> u32 {
+        self.save_retention
+    }
+
+    pub fn history_limit(&self) -> u32 {
+        self.history_limit
+    }
+
+    pub fn dir_backup(&self) -> PathBuf {
+        self.backup
+            .dir
+            .clone()
+            .unwrap_or_else(|| self.path_data.join(DIR_NAME_BACKUP))
+    }
+
     pub fn dir_archive(&self) -> PathBuf {
         self.path_data.join(DIR_NAME_ARCHIVE)
     }
@@ -64,6 +280,98 @@ impl AppConfig {
         self.path_data.join(DIR_NAME_IMAGE)
     }
 
+    pub fn dir_save(&self) -> PathBuf {
+        self.path_data.join(DIR_NAME_SAVE)
+    }
+
+    /// Copies the active values into their entry of `profiles`, creating the
+    /// default profile for configs written before profiles existed.
+    fn profile_sync(&mut self) {
+        if self.profile.is_empty() {
+            self.profile = String::from(DEFAULT_PROFILE_NAME);
+        }
+        let active = LibraryProfile {
+            name: self.profile.clone(),
+            path_data: self.path_data.clone(),
+            path_deploy: self.path_deploy.clone(),
+            lang: self.lang.clone(),
+        };
+        match self.profiles.iter_mut().find(|p| p.name == self.profile) {
+            Some(profile) => *profile = active,
+            None => self.profiles.push(active),
+        }
+    }
+
+    /// Adds or replaces an inactive profile.
+    pub(super) fn profile_save(&mut self, profile: LibraryProfile) -> Result<()> {
+        if profile.name.trim().is_empty() {
+            return Err(anyhow!("Profile name is empty"));
+        }
+        if profile.name == self.profile {
+            return Err(anyhow!(
+                "Profile '{}' is active, change its fields instead",
+                profile.name
+            ));
+        }
+        for path in std::iter::once(&profile.path_data).chain(&profile.path_deploy) {
+            if !path.is_dir() {
+                warn!(
+                    "Path '{}' does not exist or is not a directory.",
+                    path.display()
+                );
+                return Err(anyhow!("Invalid path: {}", path.display()));
+            }
+        }
+        self.write(|c| {
+            match c.profiles.iter_mut().find(|p| p.name == profile.name) {
+                Some(existing) => *existing = profile,
+                None => c.profiles.push(profile),
+            }
+            Ok(())
+        })
+    }
+
+    pub(super) fn profile_remove(&mut self, name: &str) -> Result<()> {
+        if name == self.profile {
+            return Err(anyhow!(
+                "Profile '{}' is active and can not be removed",
+                name
+            ));
+        }
+        self.write(|c| {
+            c.profiles.retain(|p| p.name != name);
+            Ok(())
+        })
+    }
+
+    /// Returns this config with `name` as the active profile, the directories
+    /// are created but nothing is saved.
+    pub fn profile_target(&self, name: &str) -> Result<Self> {
+        let profile = self
+            .profiles
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| anyhow!("Profile '{}' not found", name))?
+            .clone();
+        let mut target = self.clone();
+        target.profile = profile.name;
+        target.path_data = profile.path_data;
+        target.path_deploy = profile.path_deploy;
+        target.lang = profile.lang;
+        target.check()?;
+        Ok(target)
+    }
+
+    pub(super) fn profile_activate(&mut self, name: &str) -> Result<()> {
+        let target = self.profile_target(name)?;
+        self.write(|c| {
+            *c = target;
+            Ok(())
+        })?;
+        info!("Switched to profile '{}'", name);
+        Ok(())
+    }
+
     fn check(&self) -> Result<()> {
         let path_data = &self.path_data;
         if !path_data.exists() {
@@ -151,6 +459,85 @@ impl AppConfig {
                     return Err(anyhow!("Invalid value for path_deploy"));
                 }
             },
+            FIELD_TRASH_RETENTION_DAYS => {
+                let Some(days) = value.as_u64().and_then(|v| u32::try_from(v).ok()) else {
+                    warn!(
+                        "Invalid value for trash_retention_days: expected a number, got {:?}",
+                        value
+                    );
+                    return Err(anyhow!("Invalid value for trash_retention_days"));
+                };
+                self.write(|c| {
+                    c.trash_retention_days = days;
+                    Ok(())
+                })?;
+                info!("Updated trash_retention_days to {}", days);
+            },
+            FIELD_BACKUP => {
+                let backup = serde_json::from_value::<BackupPolicy>(value)
+                    .map_err(|e| anyhow!("Invalid value for backup: {}", e))?;
+                if let Some(dir) = &backup.dir
+                    && (!dir.exists() || !dir.is_dir())
+                {
+                    warn!(
+                        "Path '{}' does not exist or is not a directory.",
+                        dir.display()
+                    );
+                    return Err(anyhow!("Invalid path: {}", dir.display()));
+                }
+                self.write(|c| {
+                    c.backup = backup;
+                    Ok(())
+                })?;
+                info!("Updated backup policy to {:?}", self.backup);
+            },
+            FIELD_CUSTOM_FIELDS => {
+                let fields = serde_json::from_value::<Vec<CustomField>>(value)
+                    .map_err(|e| anyhow!("Invalid value for custom_fields: {}", e))?;
+                custom_fields_check(&fields)?;
+                self.write(|c| {
+                    c.custom_fields = fields;
+                    Ok(())
+                })?;
+                info!("Updated custom fields to {:?}", self.custom_fields);
+            },
+            FIELD_ARCHIVE_RETENTION => {
+                let retention = serde_json::from_value::<ArchiveRetention>(value)
+                    .map_err(|e| anyhow!("Invalid value for archive_retention: {}", e))?;
+                self.write(|c| {
+                    c.archive_retention = retention;
+                    Ok(())
+                })?;
+                info!("Updated archive retention to {:?}", self.archive_retention);
+            },
+            FIELD_SAVE_RETENTION => {
+                let Some(count) = value.as_u64().and_then(|v| u32::try_from(v).ok()) else {
+                    warn!(
+                        "Invalid value for save_retention: expected a number, got {:?}",
+                        value
+                    );
+                    return Err(anyhow!("Invalid value for save_retention"));
+                };
+                self.write(|c| {
+                    c.save_retention = count;
+                    Ok(())
+                })?;
+                info!("Updated save_retention to {}", count);
+            },
+            FIELD_HISTORY_LIMIT => {
+                let Some(limit) = value.as_u64().and_then(|v| u32::try_from(v).ok()) else {
+                    warn!(
+                        "Invalid value for history_limit: expected a number, got {:?}",
+                        value
+                    );
+                    return Err(anyhow!("Invalid value for history_limit"));
+                };
+                self.write(|c| {
+                    c.history_limit = limit;
+                    Ok(())
+                })?;
+                info!("Updated history_limit to {}", limit);
+            },
             _ => {
                 warn!("Unknown field name {} with value {}", name, value);
                 return Err(anyhow!("Unknown field name {}", name));
@@ -163,7 +550,7 @@ impl AppConfig {
         let config_path = app.path().app_config_dir()?.join(CONFIG_FILE_NAME);
         info!("Loading configuration from '{}'", config_path.display());
 
-        let config = match fs::read_to_string(&config_path) {
+        let mut config: Self = match fs::read_to_string(&config_path) {
             Ok(content) => toml::from_str(&content).map_err(|e| {
                 anyhow!(
                     "Failed to parse config file '{}': {}",
@@ -214,6 +601,7 @@ impl AppConfig {
                 _ => return Err(error.into()),
             },
         };
+        config.profile_sync();
         config
             .check()
             .map_err(|e| anyhow!("Configuration check failed: {}", e))?;
@@ -234,6 +622,7 @@ impl AppConfig {
 
     fn write(&mut self, f: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
         f(self)?;
+        self.profile_sync();
         self.save(get_handle_ref())?;
         Ok(())
     }
@@ -247,3 +636,36 @@ impl From<Value> for Language {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_profile_target() {
+        let dir = std::env::temp_dir().join(format!("profile_{}", uuid::Uuid::new_v4()));
+        let mut config = AppConfig {
+            profile: String::new(),
+            path_data: dir.join("main"),
+            ..Default::default()
+        };
+        config.profile_sync();
+        assert_eq!(config.profile(), DEFAULT_PROFILE_NAME);
+        assert_eq!(config.profiles().len(), 1);
+
+        config.profiles.push(LibraryProfile {
+            name: String::from("other"),
+            path_data: dir.join("other"),
+            path_deploy: None,
+            lang: Language::default(),
+        });
+        let target = config.profile_target("other").unwrap();
+        assert_eq!(target.profile(), "other");
+        assert_eq!(target.path_data(), dir.join("other"));
+        assert!(target.dir_archive().is_dir());
+        assert_eq!(target.profiles()[0].path_data, dir.join("main"));
+        assert!(config.profile_target("missing").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
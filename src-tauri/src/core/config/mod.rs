@@ -5,7 +5,12 @@ use tauri::{AppHandle, Manager};
 mod def;
 mod state;
 
-pub use self::{def::AppConfig, state::ConfigState};
+pub use self::{
+    def::{
+        AppConfig, ArchiveRetention, BackupPolicy, CustomField, CustomFieldKind, LibraryProfile,
+    },
+    state::ConfigState,
+};
 
 pub fn init_config(app: &AppHandle) -> Result<()> {
     if app.manage(ConfigState::new(AppConfig::load(app)?)) {
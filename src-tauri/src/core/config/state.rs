@@ -3,7 +3,7 @@ use std::sync::RwLock;
 use anyhow::Result;
 use serde_json::Value;
 
-use super::def::AppConfig;
+use super::def::{AppConfig, LibraryProfile};
 
 pub struct ConfigState(RwLock<AppConfig>);
 
@@ -25,4 +25,18 @@ impl ConfigState {
     pub fn update_field(&self, name: &str, value: Value) -> Result<()> {
         self.write(|config| config.update_field(name, value))
     }
+
+    pub fn profile_save(&self, profile: LibraryProfile) -> Result<()> {
+        self.write(|config| config.profile_save(profile))
+    }
+
+    pub fn profile_remove(&self, name: &str) -> Result<()> {
+        self.write(|config| config.profile_remove(name))
+    }
+
+    /// Makes `name` the active profile, the library has to be reopened by the
+    /// caller.
+    pub fn profile_activate(&self, name: &str) -> Result<()> {
+        self.write(|config| config.profile_activate(name))
+    }
 }
@@ -0,0 +1,249 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use log::{debug, info, warn};
+use redb::{Database, ReadableTable, WriteTransaction};
+use tauri::{AppHandle, State, async_runtime};
+
+use crate::{
+    cmd::append::ArchiveVersionArg,
+    core::{
+        AppStateExt,
+        data::{
+            library::{
+                TABLE_METADATA,
+                delegate::metadata_write,
+                history::history_archives,
+                trash::{TABLE_TRASH, TrashEntry},
+            },
+            metadata::{ArchiveInfo, Metadata},
+            state::DataState,
+        },
+    },
+};
+
+fn archive_entry_get(write: &WriteTransaction, key: &str) -> Result<Metadata> {
+    let table = write.open_table(TABLE_METADATA)?;
+    let value = table.get(key)?;
+    value
+        .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+        .transpose()?
+        .ok_or_else(|| anyhow!("Key '{}' not found in library", key))
+}
+
+/// Removes the stored files of archives dropped from entry `key`, after the
+/// change is committed so a failure only leaves an orphan behind. Files still
+/// named in the history of the entry or by a trashed entry are kept, undoing
+/// or restoring brings them back.
+pub(super) fn archive_files_remove(db: &Database, key: &str, archives: &[ArchiveInfo]) {
+    if archives.is_empty() {
+        return;
+    }
+    let referenced = archive_referenced(db, key).unwrap_or_else(|e| {
+        warn!("Failed to read archive references, keeping files: {}", e);
+        archives.iter().filter_map(archive_file).collect()
+    });
+    for archive in archives {
+        if archive_file(archive).is_some_and(|path| referenced.contains(&path)) {
+            debug!(
+                "Keeping archive {:?} referenced by history or trash",
+                archive
+            );
+            continue;
+        }
+        let _ = archive
+            .remove_stored()
+            .inspect_err(|e| warn!("Failed to remove archive {:?}: {}", archive, e));
+    }
+}
+
+/// The stored path of an archive, relative ones stay relative
+fn archive_file(archive: &ArchiveInfo) -> Option<PathBuf> {
+    archive.path_in(Path::new(""))
+}
+
+fn archive_referenced(db: &Database, key: &str) -> Result<HashSet<PathBuf>> {
+    let read = db.begin_read()?;
+    let mut referenced = history_archives(&read, key)?
+        .iter()
+        .filter_map(archive_file)
+        .collect::<HashSet<_>>();
+    for entry in read.open_table(TABLE_TRASH)?.iter()? {
+        if let Ok(trashed) = bson::from_slice::<TrashEntry>(entry?.1.value().as_slice()) {
+            referenced.extend(
+                trashed
+                    .metadata
+                    .archives
+                    .iter()
+                    .map(|v| &v.archive)
+                    .filter_map(archive_file),
+            );
+        }
+    }
+    Ok(referenced)
+}
+
+/// Updates the notes and pin of a version.
+pub async fn archive_version_update(
+    arg: ArchiveVersionArg,
+    data: State<'_, DataState>,
+) -> Result<()> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let mut metadata = archive_entry_get(&write, &arg.id)?;
+        let title = metadata.title.clone();
+        let version = metadata
+            .archives
+            .iter_mut()
+            .find(|v| v.version == arg.version)
+            .ok_or_else(|| anyhow!("Version '{}' not found in '{}'", arg.version, title))?;
+        if let Some(notes) = arg.notes {
+            version.notes = (!notes.is_empty()).then_some(notes);
+        }
+        if let Some(pinned) = arg.pinned {
+            version.pinned = pinned;
+        }
+        metadata.mark_update();
+        metadata_write(&write, &arg.id, Some(&metadata))?;
+        write.commit()?;
+        Ok(())
+    })
+    .await?
+}
+
+/// Removes a version and its archive file, see [archive_files_remove]. The
+/// deployed version can not be removed.
+pub async fn archive_version_remove(
+    key: String,
+    version: String,
+    data: State<'_, DataState>,
+) -> Result<()> {
+    let db = data.database();
+    let (handle, id) = (db.clone(), key.clone());
+    let removed = async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let mut metadata = archive_entry_get(&write, &key)?;
+        if metadata.archive_deployed() == Some(version.as_str()) {
+            return Err(anyhow!(
+                "Version '{}' of '{}' is deployed",
+                version,
+                metadata.title
+            ));
+        }
+        metadata.archive_version(Some(&version))?;
+        let removed = metadata.archive_set(ArchiveInfo::None, Some(version.clone()));
+        metadata.mark_update();
+        metadata_write(&write, &key, Some(&metadata))?;
+        write.commit()?;
+        info!("Removed version '{}' of '{}'", version, key);
+        Ok::<_, anyhow::Error>(removed)
+    })
+    .await??;
+    archive_files_remove(&handle, &id, removed.as_slice());
+    Ok(())
+}
+
+/// Applies the configured retention to every entry, returns the count of
+/// removed versions.
+pub async fn archive_retention_apply(app: AppHandle) -> Result<u32> {
+    let retention = app.state_config().get().archive_retention().clone();
+    let db = app.state_data().database();
+    let handle = db.clone();
+    let removed = async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let entries = write
+            .open_table(TABLE_METADATA)?
+            .iter()?
+            .filter_map(Result::ok)
+            .filter_map(|(k, v)| {
+                bson::from_slice::<Metadata>(v.value().as_slice())
+                    .ok()
+                    .map(|m| (k.value().to_string(), m))
+            })
+            .collect::<Vec<_>>();
+        let mut removed = Vec::new();
+        for (key, mut metadata) in entries {
+            let pruned = metadata.archive_prune(&retention);
+            if pruned.is_empty() {
+                continue;
+            }
+            metadata.mark_update();
+            metadata_write(&write, &key, Some(&metadata))?;
+            removed.push((
+                key,
+                pruned.into_iter().map(|v| v.archive).collect::<Vec<_>>(),
+            ));
+        }
+        write.commit()?;
+        Ok::<_, anyhow::Error>(removed)
+    })
+    .await??;
+    let mut count = 0;
+    for (key, archives) in &removed {
+        archive_files_remove(&handle, key, archives);
+        count += archives.len();
+    }
+    info!("Removed {} archive versions by retention", count);
+    Ok(count as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use redb::backends::InMemoryBackend;
+
+    use super::*;
+    use crate::core::data::{
+        library::{history::history_record, trash::trash_put},
+        metadata::ArchiveVersion,
+    };
+
+    fn version(path: &str) -> ArchiveVersion {
+        ArchiveVersion {
+            version: path.to_string(),
+            archive: ArchiveInfo::ArchiveFile {
+                size: 1,
+                path: path.to_string(),
+                password: None,
+            },
+            add_time: Utc::now(),
+            notes: None,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_archive_referenced() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let before = Metadata {
+            archives: vec![version("a.7z"), version("b.7z")],
+            ..Default::default()
+        };
+        let mut after = before.clone();
+        let pruned = after.archives.remove(0);
+        let trashed = Metadata {
+            archives: vec![version("c.7z")],
+            ..Default::default()
+        };
+        let key = before.id.to_string();
+
+        let write = db.begin_write().unwrap();
+        history_record(&write, &key, Some(&before), Some(&after), None, None).unwrap();
+        trash_put(&write, &trashed).unwrap();
+        write.commit().unwrap();
+
+        let referenced = archive_referenced(&db, &key).unwrap();
+        assert!(referenced.contains(&archive_file(&pruned.archive).unwrap()));
+        assert!(referenced.contains(Path::new("c.7z")));
+        assert!(!referenced.contains(Path::new("d.7z")));
+        // Records of other entries do not keep files
+        let other = archive_referenced(&db, &trashed.id.to_string()).unwrap();
+        assert!(!other.contains(Path::new("a.7z")));
+    }
+}
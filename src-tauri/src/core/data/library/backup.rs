@@ -0,0 +1,503 @@
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use redb::{Database, ReadableTable, StorageBackend};
+use serde::Serialize;
+use tauri::{AppHandle, async_runtime};
+use tokio::time::sleep;
+use ts_rs::TS;
+
+use crate::core::{
+    AppStateExt,
+    config::AppConfig,
+    data::{
+        library::{
+            LIB_FILE_EXT, LIB_FILE_NAME, LIB_FILE_STEM, TABLE_METADATA,
+            collection::collection_cache_sync_all, deployment::deployment_cache_sync_all,
+            open_library,
+        },
+        metadata::Metadata,
+    },
+};
+
+/// Check interval of the schedule while periodic backups are disabled
+const SCHEDULE_IDLE: Duration = Duration::from_secs(15 * 60);
+
+/// What caused a backup, appended to the file name
+#[derive(Debug, Clone, Copy)]
+pub(super) enum BackupTrigger {
+    Startup,
+    Shutdown,
+    Import,
+    /// Before migrating from the given schema version
+    Migration(u64),
+    Interval,
+    Manual,
+    /// Safety copy of the replaced database
+    Restore,
+    /// Before removing unreadable records
+    Repair,
+}
+
+impl BackupTrigger {
+    fn name(&self) -> String {
+        match self {
+            Self::Startup => String::from("startup"),
+            Self::Shutdown => String::from("shutdown"),
+            Self::Import => String::from("import"),
+            Self::Migration(version) => format!("migration-v{}", version),
+            Self::Interval => String::from("interval"),
+            Self::Manual => String::from("manual"),
+            Self::Restore => String::from("restore"),
+            Self::Repair => String::from("repair"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct BackupInfo {
+    pub name: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub time: DateTime<Utc>,
+    /// Library entries in the backup
+    #[ts(type = "number")]
+    pub entries: u64,
+    /// Entries that can not be read
+    pub unreadable: u32,
+    /// Set if the backup can not be opened
+    pub error: Option<String>,
+}
+
+/// Reads a backup in place. What the database writes while it is open, such
+/// as its recovery flag, is kept in memory so the file is left untouched.
+#[derive(Debug)]
+struct BackupReader {
+    file: Mutex<fs::File>,
+    overlay: Mutex<Overlay>,
+}
+
+/// Length as seen by the database and the writes on top of the file
+#[derive(Debug)]
+struct Overlay {
+    len: u64,
+    writes: Vec<(u64, Vec<u8>)>,
+}
+
+impl BackupReader {
+    fn open(path: &Path) -> Result<Self> {
+        let file = fs::File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            file: Mutex::new(file),
+            overlay: Mutex::new(Overlay {
+                len,
+                writes: Vec::new(),
+            }),
+        })
+    }
+
+    fn lock<T>(mutex: &Mutex<T>) -> io::Result<MutexGuard<'_, T>> {
+        mutex
+            .lock()
+            .map_err(|_| io::Error::other("Backup reader poisoned"))
+    }
+}
+
+impl StorageBackend for BackupReader {
+    fn len(&self) -> io::Result<u64> {
+        Ok(Self::lock(&self.overlay)?.len)
+    }
+
+    fn read(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0; len];
+        let mut file = Self::lock(&self.file)?;
+        let file_len = file.metadata()?.len();
+        let overlay = Self::lock(&self.overlay)?;
+        let end = offset + len as u64;
+        if end > overlay.len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        // Beyond the file the storage was extended with zeros
+        if offset < file_len {
+            let available = (file_len.min(end) - offset) as usize;
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut buffer[..available])?;
+        }
+        for (at, data) in &overlay.writes {
+            let (start, stop) = (offset.max(*at), end.min(at + data.len() as u64));
+            if start < stop {
+                buffer[(start - offset) as usize..(stop - offset) as usize]
+                    .copy_from_slice(&data[(start - at) as usize..(stop - at) as usize]);
+            }
+        }
+        Ok(buffer)
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        Self::lock(&self.overlay)?.len = len;
+        Ok(())
+    }
+
+    fn sync_data(&self, _: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        Self::lock(&self.overlay)?
+            .writes
+            .push((offset, data.to_vec()));
+        Ok(())
+    }
+}
+
+fn is_backup_name(name: &str) -> bool {
+    name.ends_with(&format!(".{}", LIB_FILE_EXT))
+        && name.starts_with(&format!("{}_", LIB_FILE_STEM))
+}
+
+/// Backup files in the directory, newest first
+fn backup_files(dir: &Path) -> Result<Vec<(PathBuf, fs::Metadata)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_str().is_some_and(is_backup_name))
+        .filter_map(|entry| entry.metadata().ok().map(|meta| (entry.path(), meta)))
+        .filter(|(_, meta)| meta.is_file())
+        .collect::<Vec<_>>();
+    files.sort_by_key(|(_, meta)| std::cmp::Reverse(meta.modified().ok()));
+    Ok(files)
+}
+
+/// The trigger part of a backup name, empty for names written before backups
+/// were named after their trigger.
+fn backup_trigger_of(path: &Path) -> &str {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|s| s.rsplit_once('_'))
+        .map(|(_, trigger)| trigger)
+        .filter(|t| !t.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or_default()
+}
+
+/// Resolves a backup name from [backup_list], rejecting anything that is not a
+/// plain backup file name.
+fn backup_path(config: &AppConfig, name: &str) -> Result<PathBuf> {
+    if !is_backup_name(name) || Path::new(name).file_name().and_then(|n| n.to_str()) != Some(name) {
+        return Err(anyhow!("Invalid backup name '{}'", name));
+    }
+    let path = config.dir_backup().join(name);
+    if !path.is_file() {
+        return Err(anyhow!("Backup '{}' not found", name));
+    }
+    Ok(path)
+}
+
+/// Copies the library file into the backup directory without pruning, returns
+/// `None` if there is no library file yet.
+fn backup_copy(config: &AppConfig, trigger: BackupTrigger) -> Result<Option<PathBuf>> {
+    let db_path = config.path_data().join(LIB_FILE_NAME);
+    if !db_path.exists() {
+        return Ok(None);
+    }
+    let dir = config.dir_backup();
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+    }
+
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let backup_path = dir.join(format!(
+        "{}_{}_{}.{}",
+        LIB_FILE_STEM,
+        timestamp,
+        trigger.name(),
+        LIB_FILE_EXT
+    ));
+    fs::copy(&db_path, &backup_path)?;
+    info!("Database backup created at: {}", backup_path.display());
+    Ok(Some(backup_path))
+}
+
+/// Removes the oldest backups above the retention count of each trigger, so
+/// routine backups never rotate out manual or pre-migration ones.
+fn backup_prune(config: &AppConfig) -> Result<()> {
+    backup_rotate(&config.dir_backup(), config.backup().retention as usize)
+}
+
+fn backup_rotate(dir: &Path, retention: usize) -> Result<()> {
+    if retention == 0 {
+        return Ok(());
+    }
+    let mut kept = HashMap::<String, usize>::new();
+    for (path, _) in backup_files(dir)? {
+        let count = kept
+            .entry(backup_trigger_of(&path).to_string())
+            .or_default();
+        *count += 1;
+        if *count <= retention {
+            continue;
+        }
+        if let Err(e) = fs::remove_file(&path) {
+            warn!("Failed to remove old backup file {}: {}", path.display(), e);
+        } else {
+            info!("Removed old backup file: {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Whether a backup taken by `trigger` is still in the backup directory
+pub(super) fn backup_exists(config: &AppConfig, trigger: BackupTrigger) -> Result<bool> {
+    let name = trigger.name();
+    Ok(backup_files(&config.dir_backup())?
+        .iter()
+        .any(|(path, _)| backup_trigger_of(path) == name))
+}
+
+/// Backs up the library file, must be called while the database is closed or
+/// through [backup_online].
+pub(super) fn backup_file(config: &AppConfig, trigger: BackupTrigger) -> Result<Option<PathBuf>> {
+    let path = backup_copy(config, trigger)?;
+    backup_prune(config)?;
+    Ok(path)
+}
+
+/// Backs up an open database, writers are held off by an empty write
+/// transaction while the file is copied.
+pub(super) fn backup_online(
+    db: &Database,
+    config: &AppConfig,
+    trigger: BackupTrigger,
+) -> Result<Option<PathBuf>> {
+    let write = db.begin_write()?;
+    let path = backup_file(config, trigger);
+    write.abort()?;
+    path
+}
+
+/// Reads every entry of a backup through a [BackupReader], so whatever redb
+/// writes while opening it, such as a repair, stays in memory.
+fn backup_inspect(path: &Path, meta: &fs::Metadata) -> BackupInfo {
+    let mut info = BackupInfo {
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        size: meta.len(),
+        time: meta.modified().map(DateTime::from).unwrap_or_default(),
+        entries: 0,
+        unreadable: 0,
+        error: None,
+    };
+
+    let result = (|| {
+        let db = Database::builder().create_with_backend(BackupReader::open(path)?)?;
+        let read = db.begin_read()?;
+        let table = read.open_table(TABLE_METADATA)?;
+        for entry in table.iter()? {
+            let (_, value) = entry?;
+            info.entries += 1;
+            if bson::from_slice::<Metadata>(value.value().as_slice()).is_err() {
+                info.unreadable += 1;
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    })();
+    if let Err(e) = result {
+        info.error = Some(e.to_string());
+    }
+    info
+}
+
+/// Lists the backups with their entry counts, newest first.
+pub async fn backup_list(app: AppHandle) -> Result<Vec<BackupInfo>> {
+    let config = app.state_config().get();
+    async_runtime::spawn_blocking(move || {
+        Ok(backup_files(&config.dir_backup())?
+            .iter()
+            .map(|(path, meta)| backup_inspect(path, meta))
+            .collect())
+    })
+    .await?
+}
+
+/// Checks that a backup opens and all of its entries are readable.
+pub async fn backup_verify(name: String, app: AppHandle) -> Result<BackupInfo> {
+    let config = app.state_config().get();
+    async_runtime::spawn_blocking(move || {
+        let path = backup_path(&config, &name)?;
+        Ok(backup_inspect(&path, &fs::metadata(&path)?))
+    })
+    .await?
+}
+
+pub async fn backup_create(app: AppHandle) -> Result<BackupInfo> {
+    let config = app.state_config().get();
+    let db = app.state_data().database();
+    async_runtime::spawn_blocking(move || {
+        let path = backup_online(&db, &config, BackupTrigger::Manual)?
+            .ok_or_else(|| anyhow!("Library file not found"))?;
+        Ok(backup_inspect(&path, &fs::metadata(&path)?))
+    })
+    .await?
+}
+
+/// Replaces the library with a backup. The current database is copied first,
+/// and put back if the backup fails to open.
+pub async fn backup_restore(name: String, app: AppHandle) -> Result<()> {
+    let config = app.state_config().get();
+    let handle = app.clone();
+    async_runtime::spawn_blocking(move || {
+        let path = backup_path(&config, &name)?;
+        let info = backup_inspect(&path, &fs::metadata(&path)?);
+        if let Some(error) = info.error {
+            return Err(anyhow!("Backup '{}' can not be opened: {}", name, error));
+        }
+
+        let lib = config.path_data().join(LIB_FILE_NAME);
+        let safety = OnceCell::new();
+        let restore = || {
+            safety
+                .set(backup_copy(&config, BackupTrigger::Restore)?)
+                .ok();
+            fs::copy(&path, &lib)?;
+            open_library(&config)
+        };
+        let fallback = || {
+            if let Some(Some(safety)) = safety.get() {
+                fs::copy(safety, &lib)?;
+            }
+            open_library(&config)
+        };
+        handle
+            .state_data()
+            .database_reopen(restore, fallback)
+            .inspect_err(|e| error!("Failed to open restored backup '{}': {}", name, e))?;
+        backup_prune(&config)?;
+
+        info!("Restored library from backup '{}'", name);
+        Ok(())
+    })
+    .await??;
+
+    collection_cache_sync_all(app.state_data())?;
+    deployment_cache_sync_all(app.state_data())?;
+    Ok(())
+}
+
+/// Starts the periodic backup task, the interval is read from the config on
+/// every round.
+pub(super) fn backup_schedule(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            let hours = app.state_config().get().backup().interval_hours;
+            if hours == 0 {
+                sleep(SCHEDULE_IDLE).await;
+                continue;
+            }
+            sleep(Duration::from_secs(hours as u64 * 60 * 60)).await;
+
+            let config = app.state_config().get();
+            if config.backup().interval_hours == 0 {
+                continue;
+            }
+            let db = app.state_data().database();
+            let result = async_runtime::spawn_blocking(move || {
+                backup_online(&db, &config, BackupTrigger::Interval)
+            })
+            .await;
+            match result {
+                Ok(Ok(_)) => {},
+                Ok(Err(e)) => error!("Periodic backup failed: {}", e),
+                Err(e) => error!("Periodic backup task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backup_inspect() {
+        let path = std::env::temp_dir().join(format!("backup_{}.bin", uuid::Uuid::new_v4()));
+        {
+            let db = Database::create(&path).unwrap();
+            let metadata = Metadata::default();
+            let write = db.begin_write().unwrap();
+            {
+                let mut table = write.open_table(TABLE_METADATA).unwrap();
+                let key = metadata.id.to_string();
+                table
+                    .insert(key.as_str(), bson::to_vec(&metadata).unwrap())
+                    .unwrap();
+                table.insert("broken", vec![0u8; 4]).unwrap();
+            }
+            write.commit().unwrap();
+        }
+        let bytes = fs::read(&path).unwrap();
+
+        let info = backup_inspect(&path, &fs::metadata(&path).unwrap());
+        assert_eq!(info.error, None);
+        assert_eq!(info.entries, 2);
+        assert_eq!(info.unreadable, 1);
+        // Inspecting does not touch the backup
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        fs::write(&path, b"not a library").unwrap();
+        let info = backup_inspect(&path, &fs::metadata(&path).unwrap());
+        assert!(info.error.is_some());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_backup_rotate() {
+        let dir = std::env::temp_dir().join(format!("backup_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let names = [
+            "lib_20240101_000000.bin",
+            "lib_20240101_000000_manual.bin",
+            "lib_20240102_000000_startup.bin",
+            "lib_20240103_000000_startup.bin",
+            "lib_20240104_000000_startup.bin",
+        ];
+        for (i, name) in names.iter().enumerate() {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(i as u64))
+                .unwrap();
+        }
+        assert_eq!(backup_trigger_of(Path::new(names[0])), "");
+        assert_eq!(backup_trigger_of(Path::new(names[1])), "manual");
+        let migration = format!(
+            "lib_20240101_000000_{}.bin",
+            BackupTrigger::Migration(2).name()
+        );
+        assert_eq!(backup_trigger_of(Path::new(&migration)), "migration-v2");
+
+        backup_rotate(&dir, 2).unwrap();
+        let mut left = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        left.sort();
+        assert_eq!(left, vec![names[0], names[1], names[3], names[4]]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
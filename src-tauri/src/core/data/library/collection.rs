@@ -1,9 +1,159 @@
+use std::collections::HashSet;
+
 use anyhow::{Result, anyhow};
-use log::warn;
-use redb::ReadableTable;
-use tauri::State;
+use log::info;
+use redb::{Database, ReadableMultimapTable, ReadableTable, TableDefinition, WriteTransaction};
+use serde::{Deserialize, Serialize};
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::{
+    cmd::append::CollectionArg,
+    core::data::{
+        library::{
+            TABLE_METADATA,
+            delegate::metadata_write,
+            index::{TABLE_INDEX_COLLECTION, metadata_get_many},
+        },
+        metadata::{Metadata, MetadataSummary},
+        state::DataState,
+    },
+};
+
+/// Collection name to BSON encoded [CollectionRecord]
+pub(super) const TABLE_COLLECTION: TableDefinition<&str, Vec<u8>> =
+    TableDefinition::new("collection");
+
+/// A collection and its members in series order. A record without description
+/// and cover only lives as long as it has members.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct CollectionRecord {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Image hash, same as [Metadata::image]
+    #[serde(default)]
+    pub cover: Option<String>,
+    /// Member ids in series order
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+impl CollectionRecord {
+    fn is_unused(&self) -> bool {
+        self.members.is_empty() && self.description.is_none() && self.cover.is_none()
+    }
+}
+
+fn collection_read(
+    table: &impl ReadableTable<&'static str, Vec<u8>>,
+    name: &str,
+) -> Result<Option<CollectionRecord>> {
+    Ok(table
+        .get(name)?
+        .map(|v| bson::from_slice::<CollectionRecord>(v.value().as_slice()))
+        .transpose()?)
+}
+
+/// Writes `record`, or removes it once unused.
+fn collection_put(write: &WriteTransaction, record: &CollectionRecord) -> Result<()> {
+    let mut table = write.open_table(TABLE_COLLECTION)?;
+    if record.is_unused() {
+        table.remove(record.name.as_str())?;
+    } else {
+        table.insert(record.name.as_str(), bson::to_vec(record)?)?;
+    }
+    Ok(())
+}
+
+/// Moves the entry between the member lists of its old and new collection, new
+/// members are appended. Must be called in the same transaction that writes
+/// [super::TABLE_METADATA].
+pub(super) fn collection_member_sync(
+    write: &WriteTransaction,
+    old: Option<&Metadata>,
+    new: Option<&Metadata>,
+) -> Result<()> {
+    let from = old.and_then(|m| m.collection.as_deref());
+    let to = new.and_then(|m| m.collection.as_deref());
+    let Some(id) = new.or(old).map(|m| m.id.to_string()) else {
+        return Ok(());
+    };
+    if from == to {
+        return Ok(());
+    }
+
+    if let Some(from) = from {
+        let record = collection_read(&write.open_table(TABLE_COLLECTION)?, from)?;
+        if let Some(mut record) = record {
+            record.members.retain(|m| *m != id);
+            collection_put(write, &record)?;
+        }
+    }
+    if let Some(to) = to {
+        let mut record =
+            collection_read(&write.open_table(TABLE_COLLECTION)?, to)?.unwrap_or_else(|| {
+                CollectionRecord {
+                    name: to.to_string(),
+                    ..Default::default()
+                }
+            });
+        if !record.members.contains(&id) {
+            record.members.push(id);
+        }
+        collection_put(write, &record)?;
+    }
+    Ok(())
+}
 
-use crate::core::data::{library::TABLE_METADATA, metadata::Metadata, state::DataState};
+/// Brings the member lists in line with [TABLE_INDEX_COLLECTION], creating the
+/// records of libraries written before collections were stored.
+pub(super) fn collection_check(db: &Database) -> Result<()> {
+    let write = db.begin_write()?;
+    {
+        let index = write.open_multimap_table(TABLE_INDEX_COLLECTION)?;
+        let mut records = Vec::new();
+        for entry in write.open_table(TABLE_COLLECTION)?.iter()? {
+            records.push(bson::from_slice::<CollectionRecord>(
+                entry?.1.value().as_slice(),
+            )?);
+        }
+        let mut names = records
+            .iter()
+            .map(|r| r.name.clone())
+            .collect::<HashSet<_>>();
+        for entry in index.iter()? {
+            let name = entry?.0.value().to_string();
+            if names.insert(name.clone()) {
+                records.push(CollectionRecord {
+                    name,
+                    ..Default::default()
+                });
+            }
+        }
+
+        for mut record in records {
+            let mut ids = Vec::new();
+            for id in index.get(record.name.as_str())? {
+                ids.push(id?.value().to_string());
+            }
+            let before = record.clone();
+            record.members.retain(|m| ids.contains(m));
+            for id in ids {
+                if !record.members.contains(&id) {
+                    record.members.push(id);
+                }
+            }
+            if record != before {
+                info!("Collection '{}' members updated from index", record.name);
+                collection_put(&write, &record)?;
+            }
+        }
+    }
+    write.commit()?;
+    Ok(())
+}
 
 pub(super) fn collection_cache_sync(metadata: &Metadata, data: State<'_, DataState>) -> Result<()> {
     if let Some(name) = &metadata.collection {
@@ -22,29 +172,31 @@ pub(super) fn collection_cache_sync_all(data: State<'_, DataState>) -> Result<()
         .map_err(|e| anyhow!("Failed to lock collection temp set: {}", e))?;
 
     let read = db.begin_read()?;
-    let table = read.open_table(TABLE_METADATA)?;
-
-    for (k, v) in table.iter()?.flatten() {
-        if let Ok(data) = bson::from_slice::<Metadata>(v.value().as_slice()) {
-            if let Some(collection) = data.collection {
-                collections.insert(collection);
-            }
-        } else {
-            warn!("Failed to deserialize metadata for key '{}'", k.value());
-        }
+    collections.clear();
+    for entry in read.open_table(TABLE_COLLECTION)?.iter()? {
+        collections.insert(entry?.0.value().to_string());
     }
 
     Ok(())
 }
 
+/// Drops the collection of a removed or moved entry from the cache, unless
+/// the collection is still stored.
 pub(super) fn collection_cache_remove(
     metadata: &Metadata,
     data: State<'_, DataState>,
 ) -> Result<()> {
     if let Some(name) = &metadata.collection {
-        data.collection_cache_edit(|c| {
-            c.remove(name);
-        })?;
+        let read = data.database().begin_read()?;
+        if read
+            .open_table(TABLE_COLLECTION)?
+            .get(name.as_str())?
+            .is_none()
+        {
+            data.collection_cache_edit(|c| {
+                c.remove(name);
+            })?;
+        }
     }
     Ok(())
 }
@@ -58,3 +210,218 @@ pub fn collection_cache_get(data: State<'_, DataState>) -> Result<Vec<String>> {
         .cloned()
         .collect())
 }
+
+/// Lists all collections by name.
+pub async fn collection_list(data: State<'_, DataState>) -> Result<Vec<CollectionRecord>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let mut records = Vec::new();
+        for entry in read.open_table(TABLE_COLLECTION)?.iter()? {
+            records.push(bson::from_slice::<CollectionRecord>(
+                entry?.1.value().as_slice(),
+            )?);
+        }
+        Ok(records)
+    })
+    .await?
+}
+
+/// Lists the members of a collection in series order.
+pub async fn collection_members(
+    name: String,
+    data: State<'_, DataState>,
+) -> Result<Vec<MetadataSummary>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let record = collection_read(&read.open_table(TABLE_COLLECTION)?, &name)?
+            .ok_or_else(|| anyhow!("Collection '{}' not found", name))?;
+        Ok(
+            metadata_get_many(&read, record.members.iter().map(String::as_str))?
+                .iter()
+                .map(MetadataSummary::from)
+                .collect(),
+        )
+    })
+    .await?
+}
+
+/// Creates a collection or updates its description and cover. An empty
+/// collection without both is removed.
+pub async fn collection_save(
+    arg: CollectionArg,
+    data: State<'_, DataState>,
+) -> Result<CollectionRecord> {
+    let db = data.database();
+    let record = async_runtime::spawn_blocking(move || {
+        if arg.name.trim().is_empty() {
+            return Err(anyhow!("Collection name is empty"));
+        }
+        let write = db.begin_write()?;
+        let existing = collection_read(&write.open_table(TABLE_COLLECTION)?, &arg.name)?;
+        let record = CollectionRecord {
+            name: arg.name,
+            description: arg.description,
+            cover: arg.cover,
+            members: existing.map(|r| r.members).unwrap_or_default(),
+        };
+        collection_put(&write, &record)?;
+        write.commit()?;
+        Ok::<CollectionRecord, anyhow::Error>(record)
+    })
+    .await??;
+
+    collection_cache_sync_all(data)?;
+    Ok(record)
+}
+
+/// Renames a collection and updates all of its members, keeping their order.
+/// Returns the count of changed entries.
+pub async fn collection_rename(
+    from: String,
+    to: String,
+    data: State<'_, DataState>,
+) -> Result<u32> {
+    let db = data.database();
+    let count = async_runtime::spawn_blocking(move || {
+        if to.trim().is_empty() {
+            return Err(anyhow!("Collection name is empty"));
+        }
+        let write = db.begin_write()?;
+        let record = {
+            let table = write.open_table(TABLE_COLLECTION)?;
+            if collection_read(&table, &to)?.is_some() {
+                return Err(anyhow!("Collection '{}' already exists", to));
+            }
+            collection_read(&table, &from)?
+                .ok_or_else(|| anyhow!("Collection '{}' not found", from))?
+        };
+        write.open_table(TABLE_COLLECTION)?.insert(
+            to.as_str(),
+            bson::to_vec(&CollectionRecord {
+                name: to.clone(),
+                members: Vec::new(),
+                ..record.clone()
+            })?,
+        )?;
+
+        let members = {
+            let read = write.open_table(TABLE_METADATA)?;
+            let mut members = Vec::new();
+            for id in &record.members {
+                if let Some(value) = read.get(id.as_str())? {
+                    members.push(bson::from_slice::<Metadata>(value.value().as_slice())?);
+                }
+            }
+            members
+        };
+        let mut count = 0;
+        for mut metadata in members {
+            metadata.collection = Some(to.clone());
+            metadata.mark_update();
+            metadata_write(&write, &metadata.id.to_string(), Some(&metadata))?;
+            count += 1;
+        }
+        write.open_table(TABLE_COLLECTION)?.remove(from.as_str())?;
+        write.commit()?;
+
+        info!(
+            "Renamed collection '{}' to '{}' on {} entries",
+            from, to, count
+        );
+        Ok::<u32, anyhow::Error>(count)
+    })
+    .await??;
+
+    collection_cache_sync_all(data)?;
+    Ok(count)
+}
+
+/// Sets the series order, `ids` must contain every member exactly once.
+pub async fn collection_reorder(
+    name: String,
+    ids: Vec<String>,
+    data: State<'_, DataState>,
+) -> Result<()> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let mut record = collection_read(&write.open_table(TABLE_COLLECTION)?, &name)?
+            .ok_or_else(|| anyhow!("Collection '{}' not found", name))?;
+        let expected = record.members.iter().collect::<HashSet<_>>();
+        if ids.len() != record.members.len() || ids.iter().collect::<HashSet<_>>() != expected {
+            return Err(anyhow!(
+                "New order of collection '{}' must contain each member once",
+                name
+            ));
+        }
+        record.members = ids;
+        collection_put(&write, &record)?;
+        write.commit()?;
+        Ok(())
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use redb::backends::InMemoryBackend;
+
+    use super::*;
+
+    fn metadata(collection: Option<&str>) -> Metadata {
+        Metadata {
+            collection: collection.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collection_member_sync() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let a = metadata(Some("Series"));
+        let b = metadata(Some("Series"));
+        let write = db.begin_write().unwrap();
+        metadata_write(&write, &a.id.to_string(), Some(&a)).unwrap();
+        metadata_write(&write, &b.id.to_string(), Some(&b)).unwrap();
+
+        let get = |write: &WriteTransaction| {
+            collection_read(&write.open_table(TABLE_COLLECTION).unwrap(), "Series").unwrap()
+        };
+        assert_eq!(
+            get(&write).unwrap().members,
+            vec![a.id.to_string(), b.id.to_string()]
+        );
+
+        // One member leaving keeps the collection
+        metadata_write(&write, &a.id.to_string(), None).unwrap();
+        assert_eq!(get(&write).unwrap().members, vec![b.id.to_string()]);
+
+        // A described collection outlives its last member
+        let mut record = get(&write).unwrap();
+        record.description = Some(String::from("Trilogy"));
+        collection_put(&write, &record).unwrap();
+        let moved = metadata(None);
+        metadata_write(
+            &write,
+            &b.id.to_string(),
+            Some(&Metadata { id: b.id, ..moved }),
+        )
+        .unwrap();
+        let record = get(&write).unwrap();
+        assert!(record.members.is_empty());
+
+        collection_put(
+            &write,
+            &CollectionRecord {
+                description: None,
+                ..record
+            },
+        )
+        .unwrap();
+        assert!(get(&write).is_none());
+    }
+}
@@ -2,27 +2,100 @@ use std::path::Path;
 
 use anyhow::{Result, anyhow};
 use log::{debug, error, info, warn};
-use redb::{ReadableTable, ReadableTableMetadata};
+use redb::{ReadableTable, ReadableTableMetadata, WriteTransaction};
 use tauri::{AppHandle, State, async_runtime};
 use tokio::fs as tfs;
 
 use crate::{
-    cmd::append::DeployArg,
+    cmd::append::{BulkEditArg, DeployArg, QueryArg},
     core::{
         AppStateExt,
         data::{
             library::{
                 TABLE_METADATA,
-                collection::{collection_cache_remove, collection_cache_sync},
+                archive::archive_files_remove,
+                collection::{
+                    collection_cache_remove, collection_cache_sync, collection_cache_sync_all,
+                    collection_member_sync,
+                },
                 deployment::{deployment_cache_remove, deployment_cache_sync},
+                history::{HistoryOp, history_record},
+                index::index_sync,
+                relation::relation_remove_all,
+                save::{SaveTrigger, snapshot_take},
+                tag::tag_canonical,
+                trash::{trash_put, trash_remove},
             },
-            metadata::{Metadata, MetadataOption},
+            metadata::{BulkEditResult, Metadata, MetadataOption},
+            query::{Query, QueryPage},
             state::DataState,
         },
         get_handle_ref,
     },
 };
 
+/// Writes `value` under `key`, or removes the entry when `value` is `None`, and
+/// keeps the index tables and history in sync within the same transaction.
+/// Tags are written with their canonical names from the tag registry.
+///
+/// Returns the previous value, if any.
+pub(super) fn metadata_write(
+    write: &WriteTransaction,
+    key: &str,
+    value: Option<&Metadata>,
+) -> Result<Option<Metadata>> {
+    metadata_write_as(write, key, value, None, None)
+}
+
+/// Same as [metadata_write], but records the history with an explicit
+/// operation instead of inferring it.
+pub(super) fn metadata_write_as(
+    write: &WriteTransaction,
+    key: &str,
+    value: Option<&Metadata>,
+    op: Option<HistoryOp>,
+    undo_of: Option<u64>,
+) -> Result<Option<Metadata>> {
+    let canonical = match value {
+        Some(value) => tag_canonical(write, &value.tags)?.map(|tags| Metadata {
+            tags,
+            ..value.clone()
+        }),
+        None => None,
+    };
+    let value = canonical.as_ref().or(value);
+    let previous = {
+        let mut table = write.open_table(TABLE_METADATA)?;
+        let previous = match value {
+            Some(value) => {
+                let raw = bson::to_vec(value)
+                    .map_err(|e| anyhow!("Failed to serialize value for key '{}': {}", key, e))?;
+                table.insert(key, raw)?
+            },
+            None => table.remove(key)?,
+        };
+        previous.and_then(|raw| {
+            bson::from_slice::<Metadata>(raw.value().as_slice())
+                .inspect_err(|e| {
+                    warn!(
+                        "Failed to deserialize previous value for key '{}': {}",
+                        key, e
+                    )
+                })
+                .ok()
+        })
+    };
+    index_sync(write, previous.as_ref(), value)?;
+    collection_member_sync(write, previous.as_ref(), value)?;
+    if value.is_some() {
+        trash_remove(write, key)?;
+    } else {
+        relation_remove_all(write, key)?;
+    }
+    history_record(write, key, previous.as_ref(), value, op, undo_of)?;
+    Ok(previous)
+}
+
 async fn metadata_get_internal(
     key: String,
     data: State<'_, DataState>,
@@ -60,11 +133,7 @@ async fn metadata_set_internal(
 
     async_runtime::spawn_blocking(move || {
         let write = db.begin_write()?;
-        {
-            let mut table = write.open_table(TABLE_METADATA)?;
-            let raw = bson::to_vec(&value)?;
-            table.insert(&*key, raw)?;
-        }
+        metadata_write(&write, &key, Some(&value))?;
         write.commit()?;
         Ok(())
     })
@@ -85,12 +154,7 @@ async fn metadata_create_internal(
     let db = data.database();
     async_runtime::spawn_blocking(move || {
         let write = db.begin_write()?;
-        {
-            let mut table = write.open_table(TABLE_METADATA)?;
-            let value = bson::to_vec(&new)
-                .map_err(|e| anyhow!("Failed to serialize value for id '{}': {}", new_id, e))?;
-            table.insert(new_id.as_str(), value)?;
-        }
+        metadata_write(&write, &new_id, Some(&new))?;
         write.commit()?;
         Ok::<(), anyhow::Error>(())
     })
@@ -107,30 +171,31 @@ async fn metadata_patch_internal(
 ) -> Result<()> {
     let db = data.database();
     if let Some(mut metadata) = metadata_get_internal(key.clone(), data.clone()).await? {
-        let exist_archive_info = metadata.archive_info.clone();
-        metadata.patch(opt).await?;
+        let exist_archives = metadata.archives.clone();
+        let pruned = metadata.patch(opt).await?;
 
-        if exist_archive_info.eq(&metadata.archive_info) {
-            debug!("No changes detected in archive info for key '{}'", key);
-        } else {
+        for version in &mut metadata.archives {
+            if exist_archives.iter().any(|v| v.archive == version.archive) {
+                continue;
+            }
             debug!(
-                "Archive info changed for key '{}': {:?}",
-                key, metadata.archive_info
+                "Archive of version '{}' changed for key '{}': {:?}",
+                version.version, key, version.archive
             );
-            metadata.archive_info.update_size().await?;
+            version.archive.update_size().await?;
         }
 
         collection_cache_sync(&metadata, data.clone())?;
-        deployment_cache_sync(&metadata, data)?;
+        deployment_cache_sync(&metadata, data.clone())?;
 
         let write = db.begin_write()?;
-        {
-            let mut table = write.open_table(TABLE_METADATA)?;
-            let value = bson::to_vec(&metadata)
-                .map_err(|e| anyhow!("Failed to serialize value for key '{}': {}", key, e))?;
-            table.insert(&*key, value)?;
-        }
+        let previous = metadata_write(&write, &key, Some(&metadata))?;
         write.commit()?;
+        if let Some(previous) = previous {
+            collection_cache_remove(&previous, data.clone())?;
+        }
+        let pruned = pruned.into_iter().map(|v| v.archive).collect::<Vec<_>>();
+        archive_files_remove(&db, &key, &pruned);
         info!("Patched library entry with id '{}'", key);
         Ok(())
     } else {
@@ -143,25 +208,21 @@ async fn metadata_delete_internal(key: String, data: State<'_, DataState>) -> Re
 
     async_runtime::spawn_blocking(move || {
         let write = db.begin_write()?;
-        let removed = {
-            let mut table = write.open_table(TABLE_METADATA)?;
-            if let Some(removed) = table.remove(&*key)? {
-                let removed = bson::from_slice::<Metadata>(removed.value().as_slice())?;
-                info!("Deleted library entry with id '{}'", key);
-                Some(removed)
-            } else {
-                warn!("Key '{}' not found in library", key);
-                None
-            }
-        };
+        let removed = metadata_write(&write, &key, None)?;
+        if let Some(removed) = &removed {
+            trash_put(&write, removed)?;
+        }
         write.commit()?;
 
         if let Some(removed) = removed {
+            info!("Moved library entry with id '{}' to trash", key);
             let data = get_handle_ref().state_data();
             let _ = collection_cache_remove(&removed, data.clone())
                 .inspect_err(|e| error!("Failed to remove collection from cache: {}", e));
             let _ = deployment_cache_remove(&removed, data)
                 .inspect_err(|e| error!("Failed to remove deployment from cache: {}", e));
+        } else {
+            warn!("Key '{}' not found in library", key);
         }
 
         Ok::<(), anyhow::Error>(())
@@ -215,6 +276,102 @@ pub async fn metadata_get_all(data: State<'_, DataState>) -> Result<Vec<Metadata
     .await?
 }
 
+/// Filters the library with a query string, then sorts and paginates the
+/// result.
+pub async fn metadata_query(arg: QueryArg, data: State<'_, DataState>) -> Result<QueryPage> {
+    let query = Query::parse(&arg.query)
+        .map_err(|e| anyhow!("Failed to parse query '{}': {}", arg.query, e))?;
+
+    let mut items = metadata_get_all(data)
+        .await?
+        .into_iter()
+        .filter(|m| query.matches(m))
+        .collect::<Vec<_>>();
+    items.sort_by(|a, b| {
+        let ordering = arg.sort.compare(a, b);
+        if arg.desc {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    let total = items.len() as u32;
+    let items = items
+        .into_iter()
+        .skip(arg.offset as usize)
+        .take(arg.limit.map_or(usize::MAX, |l| l as usize))
+        .collect();
+    debug!("Query '{}' matched {} entries", arg.query, total);
+
+    Ok(QueryPage { total, items })
+}
+
+/// Applies one change to many entries in a single transaction. Entries that
+/// are missing or can not take the change are reported and left untouched.
+pub async fn metadata_bulk_edit(
+    arg: BulkEditArg,
+    data: State<'_, DataState>,
+) -> Result<Vec<BulkEditResult>> {
+    let db = data.database();
+    let results = async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let mut results = Vec::with_capacity(arg.ids.len());
+        for id in arg.ids {
+            let existing = {
+                let table = write.open_table(TABLE_METADATA)?;
+                let value = table.get(id.as_str())?;
+                // An unreadable entry is reported like a missing one
+                match value.map(|v| bson::from_slice::<Metadata>(v.value().as_slice())) {
+                    Some(Ok(metadata)) => Ok(metadata),
+                    Some(Err(e)) => Err(format!("Entry '{}' can not be read: {}", id, e)),
+                    None => Err(format!("Key '{}' not found in library", id)),
+                }
+            };
+            let mut metadata = match existing {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    results.push(BulkEditResult {
+                        id,
+                        changed: false,
+                        error: Some(error),
+                    });
+                    continue;
+                },
+            };
+            match metadata.bulk_edit(&arg.edit) {
+                Ok(changed) => {
+                    if changed {
+                        metadata_write(&write, &id, Some(&metadata))?;
+                    }
+                    results.push(BulkEditResult {
+                        id,
+                        changed,
+                        error: None,
+                    });
+                },
+                Err(e) => results.push(BulkEditResult {
+                    id,
+                    changed: false,
+                    error: Some(e.to_string()),
+                }),
+            }
+        }
+        write.commit()?;
+        Ok::<Vec<BulkEditResult>, anyhow::Error>(results)
+    })
+    .await??;
+
+    collection_cache_sync_all(data)?;
+    info!(
+        "Bulk edited {} of {} library entries",
+        results.iter().filter(|r| r.changed).count(),
+        results.len()
+    );
+
+    Ok(results)
+}
+
 pub async fn metadata_get(key: String, data: State<'_, DataState>) -> Result<Option<Metadata>> {
     metadata_get_internal(key, data).await
 }
@@ -236,10 +393,11 @@ pub async fn metadata_deploy(key: String, arg: DeployArg, app: AppHandle) -> Res
         async fn deploy_process(
             mut metadata: Metadata,
             deploy_path: impl AsRef<Path>,
+            version: Option<&str>,
             app: &AppHandle,
         ) -> Result<()> {
             let id = metadata.id.to_string();
-            if metadata.deploy(deploy_path, app).await? {
+            if metadata.deploy(deploy_path, version, app).await? {
                 info!("Successfully deployed metadata with id '{id}'");
 
                 let data = app.state_data();
@@ -268,7 +426,7 @@ pub async fn metadata_deploy(key: String, arg: DeployArg, app: AppHandle) -> Res
                 tfs::create_dir_all(&deploy_path).await?;
             }
 
-            deploy_process(metadata, deploy_path, &app).await
+            deploy_process(metadata, deploy_path, arg.version.as_deref(), &app).await
         } else if arg.target_dir.is_some() {
             // Deploy to custom dir
             let deploy_path = arg.target_dir.expect("Why?");
@@ -279,7 +437,7 @@ pub async fn metadata_deploy(key: String, arg: DeployArg, app: AppHandle) -> Res
                 tfs::create_dir_all(&deploy_path).await?;
             }
 
-            deploy_process(metadata, deploy_path, &app).await
+            deploy_process(metadata, deploy_path, arg.version.as_deref(), &app).await
         } else {
             error!("Provided deploy argument not valid: {}, {:?}", key, arg);
             Err(anyhow!("Failed to deploy metadata with key '{}'", key))
@@ -289,9 +447,38 @@ pub async fn metadata_deploy(key: String, arg: DeployArg, app: AppHandle) -> Res
     }
 }
 
-pub async fn metadata_deploy_off(key: String, data: State<'_, DataState>) -> Result<()> {
+/// Clears the deployment of `key` after a snapshot of its saves. A failed
+/// snapshot keeps the entry deployed unless `force` is set.
+pub async fn metadata_deploy_off(
+    key: String,
+    force: bool,
+    data: State<'_, DataState>,
+) -> Result<()> {
     let existing_data = metadata_get_internal(key.clone(), data.clone()).await?;
     if let Some(mut metadata) = existing_data {
+        // Saves inside the deployment are gone once it is cleared
+        let config = get_handle_ref().state_config().get();
+        let snapshot = metadata.clone();
+        let taken = async_runtime::spawn_blocking(move || {
+            snapshot_take(&config, &snapshot, SaveTrigger::DeployOff)
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|taken| taken);
+        match taken {
+            Ok(_) => {},
+            Err(e) if force => warn!(
+                "Undeploying '{}' without a snapshot of its saves: {}",
+                key, e
+            ),
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to snapshot saves of '{}', it stays deployed: {}",
+                    key,
+                    e
+                ));
+            },
+        }
         if metadata.deploy_off().await? {
             metadata_set_internal(key.clone(), metadata.clone(), data.clone()).await?;
             let _ = deployment_cache_remove(&metadata, data).inspect_err(|e| {
@@ -1,9 +1,9 @@
 use anyhow::{Result, anyhow};
-use log::{info, warn};
-use redb::ReadableTable;
+use log::info;
 use tauri::State;
 
 use crate::core::data::{
+    library::index::index_deployed,
     metadata::{DeployInfo, Metadata},
     state::DataState,
 };
@@ -25,17 +25,8 @@ pub(super) fn deployment_cache_sync_all(data: State<'_, DataState>) -> Result<()
         .map_err(|e| anyhow!("Failed to lock deployment temp set: {}", e))?;
 
     let read = db.begin_read()?;
-    let table = read.open_table(crate::core::data::library::TABLE_METADATA)?;
-
-    for (k, v) in table.iter()?.flatten() {
-        if let Ok(data) = bson::from_slice::<Metadata>(v.value().as_slice()) {
-            if data.deploy_info != DeployInfo::None {
-                deployments.insert(data.id.to_string());
-            }
-        } else {
-            warn!("Failed to deserialize metadata for key '{}'", k.value());
-        }
-    }
+    deployments.clear();
+    deployments.extend(index_deployed(&read)?);
 
     Ok(())
 }
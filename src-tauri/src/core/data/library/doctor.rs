@@ -0,0 +1,498 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use log::{info, warn};
+use redb::{ReadTransaction, ReadableTable};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, async_runtime};
+use ts_rs::TS;
+
+use crate::core::{
+    AppStateExt,
+    data::{
+        library::{
+            TABLE_METADATA,
+            backup::{BackupTrigger, backup_online},
+            collection::collection_cache_sync_all,
+            delegate::metadata_write,
+            deployment::deployment_cache_sync_all,
+            history::history_archives_all,
+            index::index_rebuild,
+            trash::{TABLE_TRASH, TrashEntry},
+        },
+        metadata::{ArchiveInfo, DeployInfo, Metadata},
+    },
+    util::path_ext::PathExt,
+};
+
+/// A problem found by [doctor_check], each kind has one repair action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum DoctorIssueKind {
+    /// The archive path of a version does not exist, repaired by removing the
+    /// version
+    ArchiveMissing,
+    /// A file in the archive directory no entry uses, repaired by deleting it
+    ArchiveOrphan,
+    /// The deployed path does not exist, repaired by clearing the deploy info
+    DeployMissing,
+    /// The image file does not exist, repaired by clearing the image
+    ImageMissing,
+    /// The stored size of a version is outdated, repaired by updating it
+    SizeStale,
+    /// The record can not be deserialized, repaired by removing it after a
+    /// backup
+    RecordUnreadable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct DoctorIssue {
+    pub kind: DoctorIssueKind,
+    /// Entry id, `None` for orphaned archives
+    pub id: Option<String>,
+    pub path: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct DoctorReport {
+    pub entries: u32,
+    pub issues: Vec<DoctorIssue>,
+}
+
+impl DoctorIssue {
+    fn entry(kind: DoctorIssueKind, id: &str, path: &Path, detail: String) -> Self {
+        Self {
+            kind,
+            id: Some(id.to_string()),
+            path: Some(path.display().to_string()),
+            detail,
+        }
+    }
+}
+
+/// Checks the paths of one entry.
+fn doctor_entry(metadata: &Metadata, dir_archive: &Path, dir_image: &Path) -> Vec<DoctorIssue> {
+    let id = metadata.id.to_string();
+    let mut issues = Vec::new();
+
+    for version in &metadata.archives {
+        let Some(path) = version.archive.path_in(dir_archive) else {
+            continue;
+        };
+        let exists = match &version.archive {
+            ArchiveInfo::Directory { .. } => path.is_dir(),
+            _ => path.is_file(),
+        };
+        if !exists {
+            issues.push(DoctorIssue::entry(
+                DoctorIssueKind::ArchiveMissing,
+                &id,
+                &path,
+                format!(
+                    "Archive of '{}' version '{}' not found",
+                    metadata.title, version.version
+                ),
+            ));
+        } else {
+            let size = path.calculate_size();
+            if size != version.archive.size() {
+                issues.push(DoctorIssue::entry(
+                    DoctorIssueKind::SizeStale,
+                    &id,
+                    &path,
+                    format!(
+                        "Archive size of '{}' version '{}' is {} bytes, stored {}",
+                        metadata.title,
+                        version.version,
+                        size,
+                        version.archive.size()
+                    ),
+                ));
+            }
+        }
+    }
+    if let Some(path) = metadata.deploy_info.path()
+        && !path.exists()
+    {
+        issues.push(DoctorIssue::entry(
+            DoctorIssueKind::DeployMissing,
+            &id,
+            path,
+            format!("Deployment of '{}' not found", metadata.title),
+        ));
+    }
+    for image in &metadata.images {
+        let path = dir_image.join(format!("{}.png", image.hash));
+        if !path.is_file() {
+            issues.push(DoctorIssue::entry(
+                DoctorIssueKind::ImageMissing,
+                &id,
+                &path,
+                format!("{:?} image of '{}' not found", image.role, metadata.title),
+            ));
+        }
+    }
+    issues
+}
+
+/// Archive paths of every version of an entry
+fn doctor_archive_paths<'a>(
+    metadata: &'a Metadata,
+    dir_archive: &'a Path,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    metadata
+        .archives
+        .iter()
+        .filter_map(|v| v.archive.path_in(dir_archive))
+        .filter(|p| !p.as_os_str().is_empty())
+}
+
+/// Whether `path` is a used archive or inside a used archive directory
+fn doctor_is_used(used: &HashSet<PathBuf>, path: &Path) -> bool {
+    used.iter().any(|u| path.starts_with(u))
+}
+
+/// Files in the archive directory that are not in `used`
+fn doctor_orphans(dir_archive: &Path, used: &HashSet<PathBuf>) -> Vec<DoctorIssue> {
+    walkdir::WalkDir::new(dir_archive)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && !doctor_is_used(used, entry.path()))
+        .map(|entry| DoctorIssue {
+            kind: DoctorIssueKind::ArchiveOrphan,
+            id: None,
+            path: Some(entry.path().display().to_string()),
+            detail: String::from("Archive file not used by any entry"),
+        })
+        .collect()
+}
+
+/// Archive paths of trashed entries and of history records, they are needed on
+/// restore and undo
+fn doctor_recoverable_used(read: &ReadTransaction, dir_archive: &Path) -> Result<HashSet<PathBuf>> {
+    let mut used = history_archives_all(read)?
+        .iter()
+        .filter_map(|a| a.path_in(dir_archive))
+        .filter(|p| !p.as_os_str().is_empty())
+        .collect::<HashSet<_>>();
+    for entry in read.open_table(TABLE_TRASH)?.iter()? {
+        if let Ok(trashed) = bson::from_slice::<TrashEntry>(entry?.1.value().as_slice()) {
+            used.extend(doctor_archive_paths(&trashed.metadata, dir_archive));
+        }
+    }
+    Ok(used)
+}
+
+/// Removes the files of [DoctorIssueKind::ArchiveOrphan] issues that are still
+/// not used, returns the count of removed files
+fn doctor_remove_orphans(
+    issues: &[DoctorIssue],
+    dir_archive: &Path,
+    used: &HashSet<PathBuf>,
+) -> Result<u32> {
+    let mut count = 0;
+    for issue in issues
+        .iter()
+        .filter(|i| i.kind == DoctorIssueKind::ArchiveOrphan)
+    {
+        let Some(path) = issue.path.as_deref().map(PathBuf::from) else {
+            continue;
+        };
+        if !path.starts_with(dir_archive) || path.components().any(|c| c.as_os_str() == "..") {
+            return Err(anyhow!(
+                "Refusing to remove '{}' outside of the archive directory",
+                path.display()
+            ));
+        }
+        if path.is_file() && !doctor_is_used(used, &path) {
+            fs::remove_file(&path)?;
+            info!("Removed orphaned archive: {}", path.display());
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Walks every entry and the archive directory and reports what is broken.
+/// Nothing is changed, see [doctor_repair].
+pub async fn doctor_check(app: AppHandle) -> Result<DoctorReport> {
+    let config = app.state_config().get();
+    let db = app.state_data().database();
+    async_runtime::spawn_blocking(move || {
+        let (dir_archive, dir_image) = (config.dir_archive(), config.dir_image());
+        let read = db.begin_read()?;
+        let mut report = DoctorReport {
+            entries: 0,
+            issues: Vec::new(),
+        };
+        let mut used = HashSet::new();
+
+        for entry in read.open_table(TABLE_METADATA)?.iter()? {
+            let (k, v) = entry?;
+            report.entries += 1;
+            match bson::from_slice::<Metadata>(v.value().as_slice()) {
+                Ok(metadata) => {
+                    used.extend(doctor_archive_paths(&metadata, &dir_archive));
+                    report
+                        .issues
+                        .extend(doctor_entry(&metadata, &dir_archive, &dir_image));
+                },
+                Err(e) => report.issues.push(DoctorIssue {
+                    kind: DoctorIssueKind::RecordUnreadable,
+                    id: Some(k.value().to_string()),
+                    path: None,
+                    detail: e.to_string(),
+                }),
+            }
+        }
+        used.extend(doctor_recoverable_used(&read, &dir_archive)?);
+        report.issues.extend(doctor_orphans(&dir_archive, &used));
+
+        info!(
+            "Library check found {} issues in {} entries",
+            report.issues.len(),
+            report.entries
+        );
+        Ok(report)
+    })
+    .await?
+}
+
+/// Applies the repair action of each issue, returns the count of repaired
+/// issues. Issues that are no longer present are skipped.
+pub async fn doctor_repair(issues: Vec<DoctorIssue>, app: AppHandle) -> Result<u32> {
+    let config = app.state_config().get();
+    let db = app.state_data().database();
+    let count = async_runtime::spawn_blocking(move || {
+        let dir_archive = config.dir_archive();
+        let used = {
+            let read = db.begin_read()?;
+            let mut used = doctor_recoverable_used(&read, &dir_archive)?;
+            for entry in read.open_table(TABLE_METADATA)?.iter()? {
+                if let Ok(metadata) = bson::from_slice::<Metadata>(entry?.1.value().as_slice()) {
+                    used.extend(doctor_archive_paths(&metadata, &dir_archive));
+                }
+            }
+            used
+        };
+
+        let mut count = doctor_remove_orphans(&issues, &dir_archive, &used)?;
+
+        if issues
+            .iter()
+            .any(|i| i.kind == DoctorIssueKind::RecordUnreadable)
+        {
+            backup_online(&db, &config, BackupTrigger::Repair)?;
+        }
+        let write = db.begin_write()?;
+        let mut removed = false;
+        for issue in &issues {
+            let Some(id) = issue.id.as_deref() else {
+                continue;
+            };
+            if issue.kind == DoctorIssueKind::RecordUnreadable {
+                let mut table = write.open_table(TABLE_METADATA)?;
+                let unreadable = table
+                    .get(id)?
+                    .is_some_and(|v| bson::from_slice::<Metadata>(v.value().as_slice()).is_err());
+                if unreadable {
+                    table.remove(id)?;
+                    warn!("Removed unreadable library entry '{}'", id);
+                    removed = true;
+                    count += 1;
+                }
+                continue;
+            }
+
+            let existing = {
+                let table = write.open_table(TABLE_METADATA)?;
+                let value = table.get(id)?;
+                value.and_then(|v| bson::from_slice::<Metadata>(v.value().as_slice()).ok())
+            };
+            let Some(mut metadata) = existing else {
+                continue;
+            };
+            let issue_path = issue.path.as_deref().map(PathBuf::from);
+            let repaired = match issue.kind {
+                DoctorIssueKind::ArchiveMissing => {
+                    let before = metadata.archives.len();
+                    metadata
+                        .archives
+                        .retain(|v| v.archive.path_in(&dir_archive) != issue_path);
+                    metadata.archives.len() != before
+                },
+                DoctorIssueKind::DeployMissing => {
+                    metadata.deploy_info = DeployInfo::None;
+                    true
+                },
+                DoctorIssueKind::ImageMissing => {
+                    let missing = issue
+                        .path
+                        .as_deref()
+                        .and_then(|p| Path::new(p).file_stem())
+                        .map(|s| s.to_string_lossy().to_string());
+                    let before = metadata.images.len();
+                    metadata
+                        .images
+                        .retain(|i| Some(&i.hash) != missing.as_ref());
+                    metadata.images.len() != before
+                },
+                DoctorIssueKind::SizeStale => {
+                    let mut changed = false;
+                    for version in &mut metadata.archives {
+                        if version.archive.path_in(&dir_archive) == issue_path {
+                            let before = version.archive.size();
+                            version.archive.update_size_blocking()?;
+                            changed |= version.archive.size() != before;
+                        }
+                    }
+                    changed
+                },
+                DoctorIssueKind::ArchiveOrphan | DoctorIssueKind::RecordUnreadable => false,
+            };
+            if repaired {
+                metadata.mark_update();
+                metadata_write(&write, id, Some(&metadata))?;
+                count += 1;
+            }
+        }
+        if removed {
+            index_rebuild(&write)?;
+        }
+        write.commit()?;
+        Ok::<u32, anyhow::Error>(count)
+    })
+    .await??;
+
+    collection_cache_sync_all(app.state_data())?;
+    deployment_cache_sync_all(app.state_data())?;
+    info!("Repaired {} library issues", count);
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::core::data::metadata::{ArchiveVersion, ImageRole, MetadataImage};
+
+    #[test]
+    fn test_doctor_entry() {
+        let dir = std::env::temp_dir().join(format!("doctor_{}", Uuid::new_v4()));
+        let (dir_archive, dir_image) = (dir.join("archive"), dir.join("image"));
+        fs::create_dir_all(dir_archive.join("game")).unwrap();
+        fs::create_dir_all(&dir_image).unwrap();
+        fs::write(dir_archive.join("game/a.7z"), [0u8; 16]).unwrap();
+        fs::write(dir_archive.join("game/orphan.7z"), [0u8; 4]).unwrap();
+
+        let mut metadata = Metadata {
+            title: String::from("Sample"),
+            images: vec![MetadataImage {
+                hash: String::from("missing"),
+                role: ImageRole::Cover,
+            }],
+            archives: vec![ArchiveVersion {
+                version: String::from("1.0"),
+                archive: ArchiveInfo::ArchiveFile {
+                    size: 8,
+                    path: String::from("game/a.7z"),
+                    password: None,
+                },
+                add_time: Utc::now(),
+                notes: None,
+                pinned: false,
+            }],
+            deploy_info: DeployInfo::Directory {
+                path: dir.join("deployed"),
+                version: None,
+            },
+            ..Default::default()
+        };
+        let kinds = |m: &Metadata| {
+            doctor_entry(m, &dir_archive, &dir_image)
+                .into_iter()
+                .map(|i| i.kind)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(&metadata),
+            vec![
+                DoctorIssueKind::SizeStale,
+                DoctorIssueKind::DeployMissing,
+                DoctorIssueKind::ImageMissing
+            ]
+        );
+
+        let used = HashSet::from([dir_archive.join("game/a.7z")]);
+        let orphans = doctor_orphans(&dir_archive, &used);
+        assert_eq!(orphans.len(), 1);
+        assert!(orphans[0].path.as_ref().unwrap().ends_with("orphan.7z"));
+
+        metadata.archives[0].archive = ArchiveInfo::ArchiveFile {
+            size: 16,
+            path: String::from("game/gone.7z"),
+            password: None,
+        };
+        assert_eq!(kinds(&metadata)[0], DoctorIssueKind::ArchiveMissing);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_doctor_directory_archive() {
+        let dir = std::env::temp_dir().join(format!("doctor_{}", Uuid::new_v4()));
+        let dir_archive = dir.join("archive");
+        let game = dir_archive.join("game/dir");
+        fs::create_dir_all(game.join("data")).unwrap();
+        fs::write(game.join("game.exe"), [0u8; 4]).unwrap();
+        fs::write(game.join("data/save.dat"), [0u8; 4]).unwrap();
+        fs::write(dir_archive.join("game/orphan.7z"), [0u8; 4]).unwrap();
+
+        let metadata = Metadata {
+            archives: vec![ArchiveVersion {
+                version: String::from("1.0"),
+                archive: ArchiveInfo::Directory {
+                    size: 8,
+                    path: game.display().to_string(),
+                },
+                add_time: Utc::now(),
+                notes: None,
+                pinned: false,
+            }],
+            ..Default::default()
+        };
+        let used = doctor_archive_paths(&metadata, &dir_archive).collect::<HashSet<_>>();
+        let orphans = doctor_orphans(&dir_archive, &used);
+        assert_eq!(orphans.len(), 1);
+        assert!(orphans[0].path.as_ref().unwrap().ends_with("orphan.7z"));
+
+        // Issues from an older check still name files inside the directory
+        let stale = [game.join("game.exe"), game.join("data/save.dat")]
+            .into_iter()
+            .map(|path| DoctorIssue {
+                kind: DoctorIssueKind::ArchiveOrphan,
+                id: None,
+                path: Some(path.display().to_string()),
+                detail: String::new(),
+            });
+        let issues = orphans.into_iter().chain(stale).collect::<Vec<_>>();
+        assert_eq!(
+            doctor_remove_orphans(&issues, &dir_archive, &used).unwrap(),
+            1
+        );
+        assert!(game.join("game.exe").is_file());
+        assert!(game.join("data/save.dat").is_file());
+        assert!(!dir_archive.join("game/orphan.7z").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{Result, anyhow};
+use log::info;
+use redb::ReadableTable;
+use serde::{Deserialize, Serialize};
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::{
+    cmd::append::DuplicateMergeArg,
+    core::{
+        data::{
+            library::{
+                TABLE_METADATA, collection::collection_cache_sync_all, delegate::metadata_write,
+                launcher::play_session_move, relation::relation_move, trash::trash_put,
+            },
+            metadata::{ContentInfo, DeployInfo, GameDistribution, Metadata, MetadataSummary},
+            state::DataState,
+        },
+        util::{img::image_name_hash, text::normalize},
+    },
+};
+
+/// Groups below this score are not reported unless asked for
+const DEFAULT_MIN_SCORE: u32 = 40;
+/// Maximum perceptual hash distance of covers counted as similar
+const COVER_DISTANCE: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum DuplicateReason {
+    /// Same store and id
+    Distribution,
+    /// Same title or alias after normalization
+    Title,
+    /// Same non-zero archive size
+    ArchiveSize,
+    /// Similar cover image
+    Cover,
+}
+
+impl DuplicateReason {
+    fn score(self) -> u32 {
+        match self {
+            Self::Distribution => 100,
+            Self::Title => 60,
+            Self::Cover => 40,
+            Self::ArchiveSize => 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct DuplicateGroup {
+    /// Highest pair score in the group, up to 100
+    pub score: u32,
+    pub reasons: Vec<DuplicateReason>,
+    pub items: Vec<MetadataSummary>,
+}
+
+/// Which archive versions the merged entry keeps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum MergeArchive {
+    #[default]
+    Keep,
+    Remove,
+    /// Versions of both ordered by add time, `keep` wins on equal labels
+    Both,
+}
+
+/// Lowercased, width folded title without spaces and punctuation
+fn title_key(title: &str) -> Option<String> {
+    let key = normalize(title)
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>();
+    (!key.is_empty()).then_some(key)
+}
+
+/// Bucket keys of an entry, entries sharing a key are duplicate candidates
+fn duplicate_keys(metadata: &Metadata) -> Vec<(DuplicateReason, String)> {
+    let mut keys = Vec::new();
+    if let Some(distribution) = metadata.content_info.distribution() {
+        let key = match distribution {
+            GameDistribution::Unknown => None,
+            GameDistribution::Steam(d) => Some(format!("steam:{}", d.app_id)),
+            GameDistribution::DLSite(d) => Some(format!("dlsite:{}", d.id.to_uppercase())),
+            GameDistribution::Other(d) => Some(format!("{}:{}", normalize(&d.name), d.id)),
+        };
+        keys.extend(key.map(|k| (DuplicateReason::Distribution, k)));
+    }
+    for title in std::iter::once(&metadata.title).chain(&metadata.alias) {
+        if let Some(key) = title_key(title) {
+            keys.push((DuplicateReason::Title, key));
+        }
+    }
+    let size = metadata.archive().size();
+    if size > 0 {
+        keys.push((DuplicateReason::ArchiveSize, size.to_string()));
+    }
+    keys
+}
+
+/// Scores every candidate pair and joins them into groups of entry indexes,
+/// highest score first.
+fn duplicate_groups(
+    entries: &[Metadata],
+    min_score: u32,
+) -> Vec<(u32, Vec<DuplicateReason>, Vec<usize>)> {
+    let mut pairs = HashMap::<(usize, usize), BTreeSet<DuplicateReason>>::new();
+
+    let mut buckets = HashMap::<(DuplicateReason, String), Vec<usize>>::new();
+    for (i, metadata) in entries.iter().enumerate() {
+        for key in duplicate_keys(metadata) {
+            let bucket = buckets.entry(key).or_default();
+            if !bucket.contains(&i) {
+                bucket.push(i);
+            }
+        }
+    }
+    for ((reason, _), bucket) in &buckets {
+        for (n, &a) in bucket.iter().enumerate() {
+            for &b in &bucket[n + 1..] {
+                pairs.entry((a, b)).or_default().insert(*reason);
+            }
+        }
+    }
+
+    let covers = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, m)| m.cover().and_then(image_name_hash).map(|h| (i, h)))
+        .collect::<Vec<_>>();
+    for (n, (a, hash_a)) in covers.iter().enumerate() {
+        for (b, hash_b) in &covers[n + 1..] {
+            if hash_a.as_bytes().len() == hash_b.as_bytes().len()
+                && hash_a.dist(hash_b) <= COVER_DISTANCE
+            {
+                pairs
+                    .entry((*a, *b))
+                    .or_default()
+                    .insert(DuplicateReason::Cover);
+            }
+        }
+    }
+
+    let mut parent = (0..entries.len()).collect::<Vec<_>>();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut scored = Vec::new();
+    for ((a, b), reasons) in pairs {
+        let score = reasons.iter().map(|r| r.score()).sum::<u32>().min(100);
+        if score >= min_score {
+            let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+            parent[ra] = rb;
+            scored.push((a, score, reasons));
+        }
+    }
+
+    let mut groups = HashMap::<usize, (u32, BTreeSet<DuplicateReason>, Vec<usize>)>::new();
+    for (a, score, reasons) in scored {
+        let group = groups.entry(root(&mut parent, a)).or_default();
+        group.0 = group.0.max(score);
+        group.1.extend(reasons);
+    }
+    for i in 0..entries.len() {
+        if let Some(group) = groups.get_mut(&root(&mut parent, i)) {
+            group.2.push(i);
+        }
+    }
+
+    let mut groups = groups
+        .into_values()
+        .map(|(score, reasons, items)| (score, reasons.into_iter().collect(), items))
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+    groups
+}
+
+/// Folds `remove` into `keep`: tags and aliases are combined, the title of
+/// `remove` becomes an alias and empty fields are filled in.
+fn duplicate_merge_into(keep: &Metadata, remove: &Metadata, archive: MergeArchive) -> Metadata {
+    let mut merged = keep.clone();
+    for tag in &remove.tags {
+        if !merged.tags.contains(tag) {
+            merged.tags.push(tag.clone());
+        }
+    }
+    for alias in std::iter::once(&remove.title).chain(&remove.alias) {
+        if !alias.is_empty() && *alias != merged.title && !merged.alias.contains(alias) {
+            merged.alias.push(alias.clone());
+        }
+    }
+    merged.collection = keep.collection.clone().or(remove.collection.clone());
+    merged.description = keep.description.clone().or(remove.description.clone());
+    if merged.cover().is_none()
+        && let Some(cover) = remove.cover()
+    {
+        merged.cover_set(Some(cover.to_string()));
+    }
+    merged.images_add(remove.images.iter().cloned());
+    merged.rating = keep.rating.max(remove.rating);
+    for (name, value) in &remove.custom {
+        merged
+            .custom
+            .entry(name.clone())
+            .or_insert_with(|| value.clone());
+    }
+    if merged.content_info == ContentInfo::default() {
+        merged.content_info = remove.content_info.clone();
+    }
+    match archive {
+        MergeArchive::Keep => {},
+        MergeArchive::Remove => merged.archives = remove.archives.clone(),
+        MergeArchive::Both => {
+            for version in &remove.archives {
+                if !merged.archives.iter().any(|v| v.version == version.version) {
+                    merged.archives.push(version.clone());
+                }
+            }
+            merged.archives.sort_by_key(|v| v.add_time);
+        },
+    }
+    merged.play_info = keep.play_info.merge(&remove.play_info);
+    if merged.save_info.locations.is_empty() {
+        merged.save_info = remove.save_info.clone();
+    }
+    merged.create_time = keep.create_time.min(remove.create_time);
+    merged.mark_update();
+    merged
+}
+
+/// Finds groups of likely duplicates, `min_score` defaults to
+/// [DEFAULT_MIN_SCORE].
+pub async fn duplicate_find(
+    min_score: Option<u32>,
+    data: State<'_, DataState>,
+) -> Result<Vec<DuplicateGroup>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let table = read.open_table(TABLE_METADATA)?;
+        let mut entries = Vec::new();
+        for entry in table.iter()? {
+            if let Ok(metadata) = bson::from_slice::<Metadata>(entry?.1.value().as_slice()) {
+                entries.push(metadata);
+            }
+        }
+
+        Ok(
+            duplicate_groups(&entries, min_score.unwrap_or(DEFAULT_MIN_SCORE))
+                .into_iter()
+                .map(|(score, reasons, items)| DuplicateGroup {
+                    score,
+                    reasons,
+                    items: items
+                        .into_iter()
+                        .map(|i| MetadataSummary::from(&entries[i]))
+                        .collect(),
+                })
+                .collect(),
+        )
+    })
+    .await?
+}
+
+/// Merges `arg.remove` into `arg.keep` and moves it to the trash, returns the
+/// merged entry. Deployed entries have to be undeployed first.
+pub async fn duplicate_merge(
+    arg: DuplicateMergeArg,
+    data: State<'_, DataState>,
+) -> Result<Metadata> {
+    let db = data.database();
+    let merged = async_runtime::spawn_blocking(move || {
+        if arg.keep == arg.remove {
+            return Err(anyhow!("Can not merge an entry into itself"));
+        }
+        let write = db.begin_write()?;
+        let (keep, remove) = {
+            let table = write.open_table(TABLE_METADATA)?;
+            let get = |key: &str| {
+                table
+                    .get(key)?
+                    .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+                    .transpose()?
+                    .ok_or_else(|| anyhow!("Key '{}' not found in library", key))
+            };
+            (get(&arg.keep)?, get(&arg.remove)?)
+        };
+        if keep.deploy_info != DeployInfo::None || remove.deploy_info != DeployInfo::None {
+            return Err(anyhow!("Undeploy both entries before merging them"));
+        }
+
+        let merged = duplicate_merge_into(&keep, &remove, arg.archive);
+        metadata_write(&write, &arg.keep, Some(&merged))?;
+        relation_move(&write, &arg.remove, &arg.keep)?;
+        play_session_move(&write, &arg.remove, &arg.keep)?;
+        metadata_write(&write, &arg.remove, None)?;
+        trash_put(&write, &remove)?;
+        write.commit()?;
+
+        info!("Merged library entry '{}' into '{}'", arg.remove, arg.keep);
+        Ok::<Metadata, anyhow::Error>(merged)
+    })
+    .await??;
+
+    collection_cache_sync_all(data)?;
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::{
+        api::dl_site::DLContentType,
+        core::data::metadata::{ArchiveInfo, DLSiteDistributionData, GameData},
+    };
+
+    fn metadata(title: &str, size: u64) -> Metadata {
+        let mut metadata = Metadata {
+            title: title.to_string(),
+            ..Default::default()
+        };
+        if size > 0 {
+            let archive = ArchiveInfo::Directory {
+                size,
+                path: String::from(title),
+            };
+            metadata.archive_set(archive, Some(title.to_string()));
+        }
+        metadata
+    }
+
+    fn dlsite(id: &str) -> ContentInfo {
+        ContentInfo::Game(GameData {
+            version: String::from("1.0"),
+            game_type: Default::default(),
+            developer: None,
+            publisher: None,
+            sys_platform: Vec::new(),
+            distribution: GameDistribution::DLSite(DLSiteDistributionData {
+                id: id.to_string(),
+                content_type: DLContentType::DoujinR18,
+            }),
+        })
+    }
+
+    #[test]
+    fn test_duplicate_groups() {
+        let mut a = metadata("Sample Quest", 100);
+        a.content_info = dlsite("RJ01000000");
+        let mut b = metadata("ｓａｍｐｌｅ　ｑｕｅｓｔ！", 0);
+        b.content_info = dlsite("rj01000000");
+        let c = metadata("Other", 100);
+        let d = metadata("Unrelated", 200);
+        let entries = vec![a, b, c, d];
+
+        let groups = duplicate_groups(&entries, 40);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, 100);
+        assert_eq!(
+            groups[0].1,
+            vec![DuplicateReason::Distribution, DuplicateReason::Title]
+        );
+        assert_eq!(groups[0].2, vec![0, 1]);
+
+        // Same archive size alone is a weak hint
+        let groups = duplicate_groups(&entries, 30);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].2, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_duplicate_merge_into() {
+        let mut keep = metadata("Sample Quest", 100);
+        keep.tags = vec![String::from("RPG")];
+        let mut remove = metadata("Sample Quest Deluxe", 200);
+        remove.tags = vec![String::from("RPG"), String::from("Fantasy")];
+        remove.rating = 4;
+        remove.create_time = keep.create_time - Duration::days(1);
+
+        let merged = duplicate_merge_into(&keep, &remove, MergeArchive::Remove);
+        assert_eq!(merged.id, keep.id);
+        assert_eq!(merged.tags, vec!["RPG", "Fantasy"]);
+        assert_eq!(merged.alias, vec!["Sample Quest Deluxe"]);
+        assert_eq!(merged.rating, 4);
+        assert_eq!(merged.archive_size(), 200);
+
+        let merged = duplicate_merge_into(&keep, &remove, MergeArchive::Both);
+        assert_eq!(merged.archives.len(), 2);
+        assert_eq!(merged.archive_size(), 300);
+        assert_eq!(merged.create_time, remove.create_time);
+    }
+}
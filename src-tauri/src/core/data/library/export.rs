@@ -0,0 +1,471 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::PathBuf,
+};
+
+use anyhow::{Result, anyhow};
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::AppHandle;
+use tokio::fs as tfs;
+use ts_rs::TS;
+
+use crate::{
+    cmd::append::ExportArg,
+    core::{
+        AppStateExt,
+        data::{
+            library::{LIB_FILE_EXPORT_NAME, LIB_FILE_STEM, metadata_get_all},
+            metadata::{
+                ArchiveInfo, ContentInfo, DeployInfo, GameData, GameDistribution, Metadata,
+            },
+            query::Query,
+        },
+        util::compress::{CompressFormat, compress_as},
+    },
+};
+
+/// Name of the metadata file inside a bundle
+const BUNDLE_FILE_METADATA: &str = LIB_FILE_EXPORT_NAME;
+/// Name of the image directory inside a bundle
+const BUNDLE_DIR_IMAGE: &str = "image";
+const IMAGE_EXT: &str = "png";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum ExportFormat {
+    /// One JSON array, the format read by import
+    #[default]
+    Json,
+    /// One JSON object per line
+    JsonLines,
+    /// Flattened columns, see [CSV_COLUMNS]
+    Csv,
+    /// Zip with the JSON export and the referenced images
+    Bundle,
+}
+
+impl ExportFormat {
+    fn ext(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::JsonLines => "jsonl",
+            Self::Csv => "csv",
+            Self::Bundle => "zip",
+        }
+    }
+}
+
+/// A CSV column as (source field, column name, value)
+type Column = (&'static str, &'static str, fn(&Metadata) -> String);
+
+fn game(metadata: &Metadata) -> Option<&GameData> {
+    match &metadata.content_info {
+        ContentInfo::Game(data) => Some(data),
+        _ => None,
+    }
+}
+
+fn game_field(metadata: &Metadata, f: impl FnOnce(&GameData) -> String) -> String {
+    game(metadata).map(f).unwrap_or_default()
+}
+
+/// Columns of the CSV export. Selecting a source field such as `content_info`
+/// selects all of its columns.
+const CSV_COLUMNS: &[Column] = &[
+    ("id", "id", |m| m.id.to_string()),
+    ("title", "title", |m| m.title.clone()),
+    ("alias", "alias", |m| m.alias.join("; ")),
+    ("tags", "tags", |m| m.tags.join("; ")),
+    ("collection", "collection", |m| {
+        m.collection.clone().unwrap_or_default()
+    }),
+    ("description", "description", |m| {
+        m.description.clone().unwrap_or_default()
+    }),
+    ("images", "cover", |m| {
+        m.cover().unwrap_or_default().to_string()
+    }),
+    ("images", "images", |m| {
+        m.images
+            .iter()
+            .map(|i| i.hash.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }),
+    ("rating", "rating", |m| m.rating.to_string()),
+    ("content_info", "content_type", |m| {
+        m.content_info.type_name().to_string()
+    }),
+    ("content_info", "version", |m| {
+        m.content_info.version().unwrap_or_default().to_string()
+    }),
+    ("content_info", "game_type", |m| {
+        game_field(m, |g| format!("{:?}", g.game_type))
+    }),
+    ("content_info", "developer", |m| {
+        game_field(m, |g| g.developer.clone().unwrap_or_default())
+    }),
+    ("content_info", "publisher", |m| {
+        game_field(m, |g| g.publisher.clone().unwrap_or_default())
+    }),
+    ("content_info", "platform", |m| {
+        game_field(m, |g| {
+            g.sys_platform
+                .iter()
+                .map(|p| format!("{:?}", p))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }),
+    ("content_info", "distribution", |m| {
+        match m.content_info.distribution() {
+            None | Some(GameDistribution::Unknown) => String::new(),
+            Some(GameDistribution::Steam(_)) => String::from("Steam"),
+            Some(GameDistribution::DLSite(_)) => String::from("DLSite"),
+            Some(GameDistribution::Other(data)) => data.name.clone(),
+        }
+    }),
+    ("content_info", "distribution_id", |m| {
+        match m.content_info.distribution() {
+            None | Some(GameDistribution::Unknown) => String::new(),
+            Some(GameDistribution::Steam(data)) => data.app_id.clone(),
+            Some(GameDistribution::DLSite(data)) => data.full_id(),
+            Some(GameDistribution::Other(data)) => data.id.clone(),
+        }
+    }),
+    ("archives", "archive_type", |m| {
+        match m.archive() {
+            ArchiveInfo::None => "",
+            ArchiveInfo::ArchiveFile { .. } => "ArchiveFile",
+            ArchiveInfo::CommonFile { .. } => "CommonFile",
+            ArchiveInfo::Directory { .. } => "Directory",
+        }
+        .to_string()
+    }),
+    ("archives", "archive_size", |m| {
+        m.archive().size().to_string()
+    }),
+    ("archives", "archive_path", |m| match m.archive() {
+        ArchiveInfo::None => String::new(),
+        ArchiveInfo::ArchiveFile { path, .. }
+        | ArchiveInfo::CommonFile { path, .. }
+        | ArchiveInfo::Directory { path, .. } => path.clone(),
+    }),
+    ("archives", "archive_version", |m| {
+        m.archives
+            .last()
+            .map(|v| v.version.clone())
+            .unwrap_or_default()
+    }),
+    ("archives", "archive_versions", |m| {
+        m.archives
+            .iter()
+            .map(|v| v.version.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }),
+    ("deploy_info", "deploy_path", |m| match &m.deploy_info {
+        DeployInfo::None => String::new(),
+        DeployInfo::File { path, .. } | DeployInfo::Directory { path, .. } => {
+            path.display().to_string()
+        },
+    }),
+    ("deploy_info", "deploy_version", |m| {
+        m.deploy_info.version().unwrap_or_default().to_string()
+    }),
+    ("create_time", "create_time", |m| m.create_time.to_rfc3339()),
+    ("update_time", "update_time", |m| m.update_time.to_rfc3339()),
+];
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Prefix of the columns holding custom field values
+const CSV_CUSTOM_PREFIX: &str = "field.";
+
+/// Writes the selected columns, with a BOM so spreadsheet applications detect
+/// UTF-8. Every custom field used by an entry gets a `field.<name>` column,
+/// selected by its column name or by `custom`.
+fn export_csv(entries: &[Metadata], fields: Option<&[String]>) -> Result<String> {
+    let custom_names = entries
+        .iter()
+        .flat_map(|m| m.custom.keys())
+        .collect::<BTreeSet<_>>();
+    let is_custom = |f: &str| {
+        f == "custom"
+            || f.strip_prefix(CSV_CUSTOM_PREFIX)
+                .is_some_and(|name| custom_names.iter().any(|n| *n == name))
+    };
+    if let Some(fields) = fields {
+        let unknown = fields
+            .iter()
+            .filter(|f| !is_custom(f) && !CSV_COLUMNS.iter().any(|(s, c, _)| s == f || c == f))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            return Err(anyhow!("Unknown export fields: {}", unknown.join(", ")));
+        }
+    }
+    let columns = CSV_COLUMNS
+        .iter()
+        .filter(|(source, column, _)| {
+            fields.is_none_or(|f| f.iter().any(|f| f == source || f == column))
+        })
+        .collect::<Vec<_>>();
+    let custom_columns = custom_names
+        .into_iter()
+        .filter(|name| {
+            fields.is_none_or(|f| {
+                f.iter().any(|f| {
+                    f == "custom" || f.strip_prefix(CSV_CUSTOM_PREFIX) == Some(name.as_str())
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut csv = String::from('\u{FEFF}');
+    let header = columns
+        .iter()
+        .map(|(_, c, _)| c.to_string())
+        .chain(
+            custom_columns
+                .iter()
+                .map(|name| format!("{}{}", CSV_CUSTOM_PREFIX, name)),
+        )
+        .map(|c| csv_escape(&c))
+        .collect::<Vec<_>>();
+    csv.push_str(&header.join(","));
+    csv.push_str("\r\n");
+    for metadata in entries {
+        let row = columns
+            .iter()
+            .map(|(_, _, value)| value(metadata))
+            .chain(custom_columns.iter().map(|name| {
+                metadata
+                    .custom
+                    .get(*name)
+                    .map(|v| v.text())
+                    .unwrap_or_default()
+            }))
+            .map(|v| csv_escape(&v))
+            .collect::<Vec<_>>();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    Ok(csv)
+}
+
+/// Serializes an entry keeping only the selected top level fields.
+fn export_value(metadata: &Metadata, fields: Option<&[String]>) -> Result<Value> {
+    let mut value = serde_json::to_value(metadata)?;
+    if let (Some(fields), Value::Object(map)) = (fields, &mut value) {
+        let unknown = fields
+            .iter()
+            .filter(|f| !map.contains_key(f.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            return Err(anyhow!("Unknown export fields: {}", unknown.join(", ")));
+        }
+        map.retain(|k, _| fields.contains(k));
+    }
+    Ok(value)
+}
+
+fn export_json(entries: &[Metadata], fields: Option<&[String]>) -> Result<String> {
+    let values = entries
+        .iter()
+        .map(|m| export_value(m, fields))
+        .collect::<Result<Vec<_>>>()?;
+    serde_json::to_string(&values)
+        .map_err(|e| anyhow!("Failed to serialize metadata to JSON: {}", e))
+}
+
+fn export_json_lines(entries: &[Metadata], fields: Option<&[String]>) -> Result<String> {
+    let mut lines = String::new();
+    for metadata in entries {
+        lines.push_str(&serde_json::to_string(&export_value(metadata, fields)?)?);
+        lines.push('\n');
+    }
+    Ok(lines)
+}
+
+/// Picks the entries in `ids` and matching `query`, all entries if neither is
+/// given.
+async fn export_select(arg: &ExportArg, app: &AppHandle) -> Result<Vec<Metadata>> {
+    let mut entries = metadata_get_all(app.state_data()).await?;
+    if let Some(ids) = &arg.ids {
+        let ids = ids.iter().map(String::as_str).collect::<HashSet<_>>();
+        entries.retain(|m| ids.contains(m.id.to_string().as_str()));
+    }
+    if let Some(query) = &arg.query {
+        let query = Query::parse(query)?;
+        entries.retain(|m| query.matches(m));
+    }
+    Ok(entries)
+}
+
+/// A bundle is a full copy of the selected entries next to their images, a
+/// projection could drop the `images` the bundled files belong to. Import does
+/// not open the zip, its metadata file is a plain JSON export once extracted.
+fn export_check(arg: &ExportArg) -> Result<()> {
+    if arg.format == ExportFormat::Bundle && arg.fields.is_some() {
+        return Err(anyhow!(
+            "Bundles always contain whole entries, fields can not be selected"
+        ));
+    }
+    Ok(())
+}
+
+async fn export_bundle(entries: &[Metadata], path: &PathBuf, app: &AppHandle) -> Result<()> {
+    let dir_image = app.state_config().get().dir_image();
+    let staging = std::env::temp_dir().join(format!(
+        "{}_bundle_{}",
+        LIB_FILE_STEM,
+        chrono::Local::now().format("%Y%m%d_%H%M%S%3f")
+    ));
+    let staging_image = staging.join(BUNDLE_DIR_IMAGE);
+    tfs::create_dir_all(&staging_image).await?;
+
+    let result = async {
+        tfs::write(
+            staging.join(BUNDLE_FILE_METADATA),
+            export_json(entries, None)?,
+        )
+        .await?;
+        let images = entries
+            .iter()
+            .flat_map(|m| m.images.iter().map(|i| i.hash.as_str()))
+            .collect::<BTreeSet<_>>();
+        for image in images {
+            let file_name = format!("{}.{}", image, IMAGE_EXT);
+            let source = dir_image.join(&file_name);
+            if let Err(e) = tfs::copy(&source, staging_image.join(&file_name)).await {
+                warn!(
+                    "Failed to add image '{}' to bundle: {}",
+                    source.display(),
+                    e
+                );
+            }
+        }
+        compress_as(app, &staging, path, None, CompressFormat::Zip).await
+    }
+    .await;
+
+    let _ = tfs::remove_dir_all(&staging)
+        .await
+        .inspect_err(|e| warn!("Failed to remove '{}': {}", staging.display(), e));
+    result
+}
+
+/// Exports the selected entries, returns the written path. The path defaults to
+/// `lib.<ext>` in the data directory.
+pub async fn export_library(arg: ExportArg, app: AppHandle) -> Result<String> {
+    export_check(&arg)?;
+    let path = match &arg.path {
+        Some(path) => PathBuf::from(path),
+        None => app.state_config().get().path_data().join(format!(
+            "{}.{}",
+            LIB_FILE_STEM,
+            arg.format.ext()
+        )),
+    };
+    let entries = export_select(&arg, &app).await?;
+    let fields = arg.fields.as_deref();
+
+    match arg.format {
+        ExportFormat::Json => tfs::write(&path, export_json(&entries, fields)?).await?,
+        ExportFormat::JsonLines => tfs::write(&path, export_json_lines(&entries, fields)?).await?,
+        ExportFormat::Csv => tfs::write(&path, export_csv(&entries, fields)?).await?,
+        ExportFormat::Bundle => export_bundle(&entries, &path, &app).await?,
+    }
+
+    info!(
+        "Exported {} library entries as {:?} to {}",
+        entries.len(),
+        arg.format,
+        path.display()
+    );
+
+    Ok(path.display().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::core::data::metadata::CustomValue;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            id: Uuid::nil(),
+            title: String::from("Title, \"quoted\""),
+            tags: vec![String::from("RPG"), String::from("Fantasy")],
+            rating: 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let fields = [
+            String::from("title"),
+            String::from("tags"),
+            String::from("rating"),
+        ];
+        let csv = export_csv(&[metadata()], Some(&fields)).unwrap();
+        assert_eq!(
+            csv,
+            "\u{FEFF}title,tags,rating\r\n\"Title, \"\"quoted\"\"\",RPG; Fantasy,4\r\n"
+        );
+
+        let csv = export_csv(&[metadata()], Some(&[String::from("archives")])).unwrap();
+        assert!(csv.starts_with(
+            "\u{FEFF}archive_type,archive_size,archive_path,archive_version,archive_versions\r\n"
+        ));
+
+        assert!(export_csv(&[metadata()], Some(&[String::from("unknown")])).is_err());
+
+        let mut custom = metadata();
+        custom.custom.insert(
+            String::from("translated_by"),
+            CustomValue::String(String::from("Team")),
+        );
+        let csv = export_csv(&[metadata(), custom], Some(&[String::from("custom")])).unwrap();
+        assert_eq!(csv, "\u{FEFF}field.translated_by\r\n\r\nTeam\r\n");
+    }
+
+    #[test]
+    fn test_export_json_lines() {
+        let fields = [String::from("id"), String::from("rating")];
+        let lines = export_json_lines(&[metadata(), metadata()], Some(&fields)).unwrap();
+        assert_eq!(
+            lines,
+            "{\"id\":\"00000000-0000-0000-0000-000000000000\",\"rating\":4}\n".repeat(2)
+        );
+    }
+
+    #[test]
+    fn test_export_check() {
+        let mut arg = ExportArg {
+            path: None,
+            format: ExportFormat::Bundle,
+            ids: None,
+            query: None,
+            fields: None,
+        };
+        assert!(export_check(&arg).is_ok());
+        arg.fields = Some(vec![String::from("title")]);
+        assert!(export_check(&arg).is_err());
+        arg.format = ExportFormat::Json;
+        assert!(export_check(&arg).is_ok());
+    }
+}
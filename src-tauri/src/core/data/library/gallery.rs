@@ -0,0 +1,237 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use log::{info, warn};
+use redb::ReadableTable;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, async_runtime};
+use tokio::fs as tfs;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    cmd::append::ImageImportArg,
+    core::{
+        AppStateExt,
+        data::{
+            library::{LIB_FILE_STEM, TABLE_METADATA, delegate::metadata_write, metadata_get},
+            metadata::{ArchiveInfo, Metadata, MetadataImage},
+        },
+        util::{
+            compress::decompress,
+            img::{process_image_file, process_image_web},
+        },
+    },
+};
+
+/// Extensions of the image formats that can be decoded
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+/// Images found in an entry smaller than this on either side are skipped, most
+/// of them are icons or sprites
+const MIN_ENTRY_IMAGE_SIZE: u32 = 256;
+/// Default of [ImageImportArg::limit]
+const DEFAULT_IMPORT_LIMIT: u32 = 50;
+
+/// Where [image_import] takes images from
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(tag = "type", content = "value")]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum ImageSource {
+    /// Image files in a folder and its subfolders
+    Folder(String),
+    /// Image URLs, for example the sample images of a DLSite work
+    Urls(Vec<String>),
+    /// Image files in the deployment, archive directory or archive file of the
+    /// entry
+    Entry,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ImageImportReport {
+    /// Images appended to the entry, already present ones are left out
+    pub added: Vec<MetadataImage>,
+    pub failed: Vec<ImageImportFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ImageImportFailure {
+    /// Path or URL of the image
+    pub source: String,
+    pub error: String,
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Image files below `dir` sorted by path, `min_size` skips small images by
+/// reading only their headers.
+fn image_files(dir: &Path, min_size: Option<u32>) -> Vec<PathBuf> {
+    let mut files = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && is_image(e.path()))
+        .map(|e| e.into_path())
+        .filter(|p| {
+            min_size.is_none_or(|min| {
+                image::image_dimensions(p).is_ok_and(|(w, h)| w >= min && h >= min)
+            })
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Image files of an entry, preferring the deployment over the archive. An
+/// archive file is extracted to `staging` first.
+async fn image_files_entry(
+    metadata: &Metadata,
+    staging: &Path,
+    app: &AppHandle,
+) -> Result<Vec<PathBuf>> {
+    let min_size = Some(MIN_ENTRY_IMAGE_SIZE);
+    if let Some(path) = metadata.deploy_info.path()
+        && path.is_dir()
+    {
+        let path = path.clone();
+        return Ok(async_runtime::spawn_blocking(move || image_files(&path, min_size)).await?);
+    }
+
+    let dir_archive = app.state_config().get().dir_archive();
+    let Some(path) = metadata.archive().path_in(&dir_archive) else {
+        return Err(anyhow!("Entry '{}' has no archive", metadata.id));
+    };
+    match metadata.archive() {
+        ArchiveInfo::ArchiveFile { password, .. } => {
+            tfs::create_dir_all(staging).await?;
+            decompress(app, &path, staging, password.as_deref()).await?;
+            let staging = staging.to_owned();
+            Ok(async_runtime::spawn_blocking(move || image_files(&staging, min_size)).await?)
+        },
+        ArchiveInfo::Directory { .. } => {
+            Ok(async_runtime::spawn_blocking(move || image_files(&path, min_size)).await?)
+        },
+        _ => Ok(is_image(&path).then_some(path).into_iter().collect()),
+    }
+}
+
+/// Processes the images of `arg.source` like a pasted image and appends them
+/// to the entry with `arg.role`.
+pub async fn image_import(arg: ImageImportArg, app: AppHandle) -> Result<ImageImportReport> {
+    let metadata = metadata_get(arg.id.clone(), app.state_data())
+        .await?
+        .ok_or_else(|| anyhow!("Key '{}' not found in library", arg.id))?;
+    let limit = arg.limit.unwrap_or(DEFAULT_IMPORT_LIMIT) as usize;
+    let staging = std::env::temp_dir().join(format!("{}_images_{}", LIB_FILE_STEM, Uuid::new_v4()));
+
+    let mut report = ImageImportReport::default();
+    let mut hashes = Vec::new();
+    let result = async {
+        match &arg.source {
+            ImageSource::Urls(urls) => {
+                for url in urls.iter().take(limit) {
+                    match process_image_web(url, app.clone()).await {
+                        Ok(hash) => hashes.push(hash),
+                        Err(e) => report.failed.push(ImageImportFailure {
+                            source: url.clone(),
+                            error: e.to_string(),
+                        }),
+                    }
+                }
+            },
+            ImageSource::Folder(_) | ImageSource::Entry => {
+                let files = match &arg.source {
+                    ImageSource::Folder(dir) => {
+                        let dir = PathBuf::from(dir);
+                        if !dir.is_dir() {
+                            return Err(anyhow!("Not a directory: {}", dir.display()));
+                        }
+                        async_runtime::spawn_blocking(move || image_files(&dir, None)).await?
+                    },
+                    _ => image_files_entry(&metadata, &staging, &app).await?,
+                };
+                for file in files.into_iter().take(limit) {
+                    match process_image_file(&file, app.clone()).await {
+                        Ok(hash) => hashes.push(hash),
+                        Err(e) => report.failed.push(ImageImportFailure {
+                            source: file.display().to_string(),
+                            error: e.to_string(),
+                        }),
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+    .await;
+    if tfs::try_exists(&staging).await.unwrap_or(false) {
+        let _ = tfs::remove_dir_all(&staging)
+            .await
+            .inspect_err(|e| warn!("Failed to remove '{}': {}", staging.display(), e));
+    }
+    result?;
+
+    // Read the entry again, it may have been edited while processing
+    let db = app.state_data().database();
+    let id = arg.id.clone();
+    let role = arg.role;
+    report.added = async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let existing = {
+            let table = write.open_table(TABLE_METADATA)?;
+            let value = table.get(id.as_str())?;
+            value
+                .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+                .transpose()?
+        };
+        let mut metadata = existing.ok_or_else(|| anyhow!("Key '{}' not found in library", id))?;
+        let added =
+            metadata.images_add(hashes.into_iter().map(|hash| MetadataImage { hash, role }));
+        if !added.is_empty() {
+            metadata.mark_update();
+            metadata_write(&write, &id, Some(&metadata))?;
+        }
+        write.commit()?;
+        Ok::<_, anyhow::Error>(added)
+    })
+    .await??;
+
+    info!(
+        "Imported {} images to '{}', {} failed",
+        report.added.len(),
+        arg.id,
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_image_files() {
+        let dir = std::env::temp_dir().join(format!("gallery_{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        image::RgbImage::new(300, 300)
+            .save(dir.join("sub/b.png"))
+            .unwrap();
+        image::RgbImage::new(16, 16)
+            .save(dir.join("a.PNG"))
+            .unwrap();
+        fs::write(dir.join("c.txt"), "text").unwrap();
+
+        let all = image_files(&dir, None);
+        assert_eq!(all, vec![dir.join("a.PNG"), dir.join("sub/b.png")]);
+        let large = image_files(&dir, Some(MIN_ENTRY_IMAGE_SIZE));
+        assert_eq!(large, vec![dir.join("sub/b.png")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
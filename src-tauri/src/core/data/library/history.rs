@@ -0,0 +1,580 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use redb::{
+    Database, MultimapTableDefinition, ReadTransaction, ReadableMultimapTable, ReadableTable,
+    ReadableTableMetadata, TableDefinition, WriteTransaction,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::core::{
+    AppStateExt,
+    data::{
+        library::{
+            TABLE_METADATA,
+            collection::{collection_cache_remove, collection_cache_sync},
+            delegate::metadata_write_as,
+            deployment::{deployment_cache_remove, deployment_cache_sync},
+            trash::trash_put,
+        },
+        metadata::{ArchiveInfo, ArchiveVersion, DeployInfo, Metadata},
+        state::DataState,
+    },
+    get_handle_ref,
+};
+
+/// Sequence number to a JSON encoded [HistoryRecord], append only
+pub(super) const TABLE_HISTORY: TableDefinition<u64, Vec<u8>> = TableDefinition::new("history");
+/// Entry id to the sequence numbers of its records
+pub(super) const TABLE_HISTORY_ENTRY: MultimapTableDefinition<&str, u64> =
+    MultimapTableDefinition::new("history_entry");
+
+/// The field whose presence in a diff marks a created or deleted entry
+const FIELD_ID: &str = "id";
+const FIELD_ARCHIVES: &str = "archives";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum HistoryOp {
+    Create,
+    Patch,
+    Delete,
+    Deploy,
+    DeployOff,
+    Import,
+    Revert,
+    Undo,
+    Restore,
+    /// A finished play session added to the play time
+    Play,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct FieldChange {
+    pub field: String,
+    #[ts(type = "unknown")]
+    pub before: Value,
+    #[ts(type = "unknown")]
+    pub after: Value,
+}
+
+/// One recorded operation on a library entry
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct HistoryRecord {
+    #[ts(type = "number")]
+    pub seq: u64,
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub op: HistoryOp,
+    /// The record reverted by this one, set for [HistoryOp::Undo]
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub undo_of: Option<u64>,
+    pub changes: Vec<FieldChange>,
+}
+
+impl HistoryRecord {
+    fn is_create(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|c| c.field == FIELD_ID && c.before.is_null())
+    }
+
+    fn is_delete(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|c| c.field == FIELD_ID && c.after.is_null())
+    }
+
+    /// Archives of the versions on either side of the change
+    fn archives(&self) -> impl Iterator<Item = ArchiveInfo> + '_ {
+        self.changes
+            .iter()
+            .filter(|c| c.field == FIELD_ARCHIVES)
+            .flat_map(|c| [&c.before, &c.after])
+            .filter_map(|v| serde_json::from_value::<Vec<ArchiveVersion>>(v.clone()).ok())
+            .flatten()
+            .map(|v| v.archive)
+    }
+}
+
+fn to_fields(metadata: Option<&Metadata>) -> Result<Map<String, Value>> {
+    match metadata.map(serde_json::to_value).transpose()? {
+        Some(Value::Object(map)) => Ok(map),
+        Some(_) => Err(anyhow!("Metadata is not serialized as an object")),
+        None => Ok(Map::new()),
+    }
+}
+
+/// Field by field difference between two states, `None` means the entry does
+/// not exist.
+pub(super) fn diff(
+    before: Option<&Metadata>,
+    after: Option<&Metadata>,
+) -> Result<Vec<FieldChange>> {
+    let before = to_fields(before)?;
+    let after = to_fields(after)?;
+    let mut fields = before.keys().chain(after.keys()).collect::<Vec<_>>();
+    fields.sort();
+    fields.dedup();
+
+    Ok(fields
+        .into_iter()
+        .filter_map(|field| {
+            let b = before.get(field).cloned().unwrap_or(Value::Null);
+            let a = after.get(field).cloned().unwrap_or(Value::Null);
+            (b != a).then(|| FieldChange {
+                field: field.clone(),
+                before: b,
+                after: a,
+            })
+        })
+        .collect())
+}
+
+/// Applies the `before` side of `record` onto `current`, returning the state of
+/// the entry as if the record never happened.
+fn apply_inverse(current: Option<&Metadata>, record: &HistoryRecord) -> Result<Option<Metadata>> {
+    if record.is_create() {
+        return Ok(None);
+    }
+    let mut fields = if record.is_delete() {
+        Map::new()
+    } else {
+        match current {
+            Some(current) => to_fields(Some(current))?,
+            None => {
+                return Err(anyhow!(
+                    "Entry '{}' no longer exists, can not revert record {}",
+                    record.id,
+                    record.seq
+                ));
+            },
+        }
+    };
+    for change in &record.changes {
+        fields.insert(change.field.clone(), change.before.clone());
+    }
+    Ok(Some(serde_json::from_value(Value::Object(fields))?))
+}
+
+/// Appends a record for a write of `key` from `before` to `after`, the
+/// operation is inferred if `op` is not given. Nothing is recorded if there is
+/// no change.
+pub(super) fn history_record(
+    write: &WriteTransaction,
+    key: &str,
+    before: Option<&Metadata>,
+    after: Option<&Metadata>,
+    op: Option<HistoryOp>,
+    undo_of: Option<u64>,
+) -> Result<()> {
+    let changes = diff(before, after)?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let op = op.unwrap_or(match (before, after) {
+        (None, _) => HistoryOp::Create,
+        (Some(_), None) => HistoryOp::Delete,
+        (Some(b), Some(a)) => match (&b.deploy_info, &a.deploy_info) {
+            (DeployInfo::None, DeployInfo::None) => HistoryOp::Patch,
+            (DeployInfo::None, _) => HistoryOp::Deploy,
+            (_, DeployInfo::None) => HistoryOp::DeployOff,
+            _ => HistoryOp::Patch,
+        },
+    });
+
+    let mut table = write.open_table(TABLE_HISTORY)?;
+    let seq = table.last()?.map_or(0, |(k, _)| k.value() + 1);
+    let record = HistoryRecord {
+        seq,
+        id: key.to_string(),
+        time: Utc::now(),
+        op,
+        undo_of,
+        changes,
+    };
+    table.insert(seq, serde_json::to_vec(&record)?)?;
+    write
+        .open_multimap_table(TABLE_HISTORY_ENTRY)?
+        .insert(key, seq)?;
+    Ok(())
+}
+
+/// Drops the oldest records so at most `limit` are kept, `0` keeps all.
+pub(super) fn history_prune(db: &Database, limit: u32) -> Result<u32> {
+    if limit == 0 {
+        return Ok(0);
+    }
+    let write = db.begin_write()?;
+    let count = {
+        let mut table = write.open_table(TABLE_HISTORY)?;
+        let excess = table.len()?.saturating_sub(limit as u64);
+        let mut entry = write.open_multimap_table(TABLE_HISTORY_ENTRY)?;
+        for _ in 0..excess {
+            let Some((seq, value)) = table.pop_first()? else {
+                break;
+            };
+            match serde_json::from_slice::<HistoryRecord>(value.value().as_slice()) {
+                Ok(record) => {
+                    entry.remove(record.id.as_str(), seq.value())?;
+                },
+                Err(e) => error!(
+                    "Failed to deserialize history record {}: {}",
+                    seq.value(),
+                    e
+                ),
+            }
+        }
+        excess as u32
+    };
+    write.commit()?;
+
+    if count > 0 {
+        info!(
+            "Pruned {} history records over the limit of {}",
+            count, limit
+        );
+    }
+    Ok(count)
+}
+
+/// Writes the result of a revert or undo, an entry that ends up removed goes to
+/// the trash like a deleted one.
+fn history_apply(
+    write: &WriteTransaction,
+    key: &str,
+    previous: Option<&Metadata>,
+    current: Option<&Metadata>,
+    op: HistoryOp,
+    undo_of: Option<u64>,
+) -> Result<()> {
+    metadata_write_as(write, key, current, Some(op), undo_of)?;
+    if let (Some(previous), None) = (previous, current) {
+        trash_put(write, previous)?;
+    }
+    Ok(())
+}
+
+/// Brings the caches in line with a write done by revert or undo.
+fn history_cache_sync(previous: Option<&Metadata>, current: Option<&Metadata>) {
+    let data = get_handle_ref().state_data();
+    if let Some(previous) = previous {
+        let _ = collection_cache_remove(previous, data.clone())
+            .inspect_err(|e| error!("Failed to remove collection from cache: {}", e));
+        let _ = deployment_cache_remove(previous, data.clone())
+            .inspect_err(|e| error!("Failed to remove deployment from cache: {}", e));
+    }
+    if let Some(current) = current {
+        let _ = collection_cache_sync(current, data.clone())
+            .inspect_err(|e| error!("Failed to sync collection cache: {}", e));
+        let _ = deployment_cache_sync(current, data)
+            .inspect_err(|e| error!("Failed to sync deployment cache: {}", e));
+    }
+}
+
+fn metadata_read(write: &WriteTransaction, key: &str) -> Result<Option<Metadata>> {
+    let table = write.open_table(TABLE_METADATA)?;
+    let value = table.get(key)?;
+    Ok(value
+        .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+        .transpose()?)
+}
+
+fn history_get(write: &WriteTransaction, seq: u64) -> Result<HistoryRecord> {
+    let table = write.open_table(TABLE_HISTORY)?;
+    let value = table
+        .get(seq)?
+        .ok_or_else(|| anyhow!("History record {} not found", seq))?;
+    Ok(serde_json::from_slice(value.value().as_slice())?)
+}
+
+/// Returns the records of an entry, newest first.
+pub async fn history_list(key: String, data: State<'_, DataState>) -> Result<Vec<HistoryRecord>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let entry = read.open_multimap_table(TABLE_HISTORY_ENTRY)?;
+        let table = read.open_table(TABLE_HISTORY)?;
+        let mut records = Vec::new();
+        for seq in entry.get(key.as_str())? {
+            if let Some(value) = table.get(seq?.value())? {
+                records.push(serde_json::from_slice::<HistoryRecord>(
+                    value.value().as_slice(),
+                )?);
+            }
+        }
+        records.reverse();
+        Ok(records)
+    })
+    .await?
+}
+
+/// Archives named in the records of entry `key`. Undoing those records brings
+/// the versions back, so their files must be kept.
+pub(super) fn history_archives(read: &ReadTransaction, key: &str) -> Result<Vec<ArchiveInfo>> {
+    let entry = read.open_multimap_table(TABLE_HISTORY_ENTRY)?;
+    let table = read.open_table(TABLE_HISTORY)?;
+    let mut archives = Vec::new();
+    for seq in entry.get(key)? {
+        if let Some(value) = table.get(seq?.value())? {
+            let record = serde_json::from_slice::<HistoryRecord>(value.value().as_slice())?;
+            archives.extend(record.archives());
+        }
+    }
+    Ok(archives)
+}
+
+/// Archives named in any record, see [history_archives]
+pub(super) fn history_archives_all(read: &ReadTransaction) -> Result<Vec<ArchiveInfo>> {
+    let mut archives = Vec::new();
+    for entry in read.open_table(TABLE_HISTORY)?.iter()? {
+        if let Ok(record) = serde_json::from_slice::<HistoryRecord>(entry?.1.value().as_slice()) {
+            archives.extend(record.archives());
+        }
+    }
+    Ok(archives)
+}
+
+/// Reverts an entry to its state right after record `seq`, by undoing all
+/// later records of the entry. The revert itself is recorded.
+pub async fn history_revert(key: String, seq: u64, data: State<'_, DataState>) -> Result<()> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let (previous, current) = {
+            let later = {
+                let entry = write.open_multimap_table(TABLE_HISTORY_ENTRY)?;
+                let mut later = Vec::new();
+                let mut found = false;
+                for s in entry.get(key.as_str())? {
+                    let s = s?.value();
+                    if s == seq {
+                        found = true;
+                    } else if s > seq {
+                        later.push(s);
+                    }
+                }
+                if !found {
+                    return Err(anyhow!("Record {} does not belong to entry '{}'", seq, key));
+                }
+                later
+            };
+
+            let previous = metadata_read(&write, &key)?;
+            let mut current = previous.clone();
+            for s in later.into_iter().rev() {
+                current = apply_inverse(current.as_ref(), &history_get(&write, s)?)?;
+            }
+            if let Some(current) = current.as_mut() {
+                current.mark_update();
+            }
+            history_apply(
+                &write,
+                &key,
+                previous.as_ref(),
+                current.as_ref(),
+                HistoryOp::Revert,
+                None,
+            )?;
+            (previous, current)
+        };
+        write.commit()?;
+
+        history_cache_sync(previous.as_ref(), current.as_ref());
+        info!("Reverted library entry '{}' to record {}", key, seq);
+        Ok(())
+    })
+    .await?
+}
+
+/// Undoes the last `count` operations across the library, operations that are
+/// undos themselves or already undone are skipped. Returns the undone records.
+pub async fn history_undo(count: u32, data: State<'_, DataState>) -> Result<Vec<HistoryRecord>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let mut targets = Vec::new();
+        {
+            let table = write.open_table(TABLE_HISTORY)?;
+            let mut undone = HashSet::new();
+            for entry in table.iter()?.rev() {
+                if targets.len() >= count as usize {
+                    break;
+                }
+                let record = serde_json::from_slice::<HistoryRecord>(entry?.1.value().as_slice())?;
+                if let Some(undo_of) = record.undo_of {
+                    undone.insert(undo_of);
+                    continue;
+                }
+                if !undone.contains(&record.seq) {
+                    targets.push(record);
+                }
+            }
+        }
+
+        let mut changed = Vec::new();
+        for record in &targets {
+            let previous = metadata_read(&write, &record.id)?;
+            let mut current = apply_inverse(previous.as_ref(), record)?;
+            if let Some(current) = current.as_mut() {
+                current.mark_update();
+            }
+            history_apply(
+                &write,
+                &record.id,
+                previous.as_ref(),
+                current.as_ref(),
+                HistoryOp::Undo,
+                Some(record.seq),
+            )?;
+            changed.push((previous, current));
+        }
+        write.commit()?;
+
+        for (previous, current) in &changed {
+            history_cache_sync(previous.as_ref(), current.as_ref());
+        }
+        info!("Undone {} operations", targets.len());
+        Ok(targets)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use redb::backends::InMemoryBackend;
+
+    use super::*;
+    use crate::core::data::library::trash::TABLE_TRASH;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            title: String::from("Title"),
+            tags: vec![String::from("RPG")],
+            description: Some(String::from("Description")),
+            rating: 3,
+            ..Default::default()
+        }
+    }
+
+    fn record(changes: Vec<FieldChange>) -> HistoryRecord {
+        HistoryRecord {
+            seq: 0,
+            id: String::new(),
+            time: Utc::now(),
+            op: HistoryOp::Patch,
+            undo_of: None,
+            changes,
+        }
+    }
+
+    #[test]
+    fn test_diff_inverse() {
+        let before = metadata();
+        let mut after = before.clone();
+        after.tags.push(String::from("Fantasy"));
+        after.description = None;
+
+        let changes = diff(Some(&before), Some(&after)).unwrap();
+        assert_eq!(
+            changes.iter().map(|c| c.field.as_str()).collect::<Vec<_>>(),
+            vec!["description", "tags"]
+        );
+        let reverted = apply_inverse(Some(&after), &record(changes)).unwrap();
+        assert_eq!(reverted, Some(before.clone()));
+
+        let created = record(diff(None, Some(&before)).unwrap());
+        assert!(created.is_create());
+        assert_eq!(apply_inverse(Some(&before), &created).unwrap(), None);
+
+        let deleted = record(diff(Some(&before), None).unwrap());
+        assert!(deleted.is_delete());
+        assert_eq!(apply_inverse(None, &deleted).unwrap(), Some(before));
+    }
+
+    #[test]
+    fn test_undo_create_trash() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let created = metadata();
+        let key = created.id.to_string();
+
+        let write = db.begin_write().unwrap();
+        metadata_write_as(&write, &key, Some(&created), None, None).unwrap();
+        let record = history_get(&write, 0).unwrap();
+        let current = apply_inverse(Some(&created), &record).unwrap();
+        history_apply(
+            &write,
+            &key,
+            Some(&created),
+            current.as_ref(),
+            HistoryOp::Undo,
+            Some(record.seq),
+        )
+        .unwrap();
+        write.commit().unwrap();
+
+        let read = db.begin_read().unwrap();
+        assert!(
+            read.open_table(TABLE_METADATA)
+                .unwrap()
+                .get(key.as_str())
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            read.open_table(TABLE_TRASH)
+                .unwrap()
+                .get(key.as_str())
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_history_prune() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let first = metadata();
+        let second = metadata();
+        let write = db.begin_write().unwrap();
+        for entry in [&first, &second, &first] {
+            let key = entry.id.to_string();
+            let before = metadata_read(&write, &key).unwrap();
+            let mut after = entry.clone();
+            after.rating = before.as_ref().map_or(0, |m| m.rating + 1);
+            history_record(&write, &key, before.as_ref(), Some(&after), None, None).unwrap();
+            write
+                .open_table(TABLE_METADATA)
+                .unwrap()
+                .insert(key.as_str(), bson::to_vec(&after).unwrap())
+                .unwrap();
+        }
+        write.commit().unwrap();
+
+        assert_eq!(history_prune(&db, 0).unwrap(), 0);
+        assert_eq!(history_prune(&db, 2).unwrap(), 1);
+        assert_eq!(history_prune(&db, 2).unwrap(), 0);
+
+        let read = db.begin_read().unwrap();
+        let table = read.open_table(TABLE_HISTORY).unwrap();
+        assert_eq!(table.first().unwrap().unwrap().0.value(), 1);
+        let entry = read.open_multimap_table(TABLE_HISTORY_ENTRY).unwrap();
+        let seqs = entry
+            .get(first.id.to_string().as_str())
+            .unwrap()
+            .map(|s| s.unwrap().value())
+            .collect::<Vec<_>>();
+        assert_eq!(seqs, vec![2]);
+    }
+}
@@ -0,0 +1,368 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use log::{info, warn};
+use redb::ReadableTable;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, async_runtime};
+use tokio::fs as tfs;
+use ts_rs::TS;
+
+use crate::{
+    cmd::append::ImportArg,
+    core::{
+        AppStateExt,
+        data::{
+            library::{
+                LIB_FILE_EXPORT_NAME, TABLE_METADATA,
+                backup::{BackupTrigger, backup_online},
+                collection::collection_cache_sync_all,
+                delegate::metadata_write_as,
+                deployment::deployment_cache_sync_all,
+                history::{HistoryOp, diff},
+                migration::migration_upgrade,
+            },
+            metadata::{Metadata, PlayInfo},
+        },
+    },
+};
+
+/// How an imported entry is resolved against an existing one with the same id
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum ImportMode {
+    /// Replace the existing entry, its deployment on this machine is kept
+    #[default]
+    Overwrite,
+    /// Take the entry with the later `update_time`
+    KeepNewer,
+    /// Never touch existing entries, only add new ones
+    KeepExisting,
+    /// Combine both field by field, see [import_merge]
+    Merge,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ImportReport {
+    /// Ids not in the library yet
+    pub added: Vec<String>,
+    /// Ids of existing entries that are written
+    pub changed: Vec<String>,
+    pub unchanged: u32,
+    /// Existing entries that differ from the imported ones
+    pub conflicts: Vec<ImportConflict>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ImportConflict {
+    pub id: String,
+    pub title: String,
+    /// Names of the differing fields
+    pub fields: Vec<String>,
+}
+
+/// Combines two versions of an entry, preferring the later edited one for
+/// single values and custom fields and taking the union of tags and aliases.
+/// Deployment and executable stay as they are in the library, they only make
+/// sense on the local machine.
+fn import_merge(existing: &Metadata, incoming: &Metadata) -> Metadata {
+    let (newer, older) = if incoming.update_time > existing.update_time {
+        (incoming, existing)
+    } else {
+        (existing, incoming)
+    };
+    let union = |a: &[String], b: &[String]| {
+        let mut result = a.to_vec();
+        for value in b {
+            if !result.contains(value) {
+                result.push(value.clone());
+            }
+        }
+        result
+    };
+
+    Metadata {
+        id: existing.id,
+        title: if newer.title.is_empty() {
+            older.title.clone()
+        } else {
+            newer.title.clone()
+        },
+        alias: union(&existing.alias, &incoming.alias),
+        tags: union(&existing.tags, &incoming.tags),
+        collection: newer.collection.clone().or(older.collection.clone()),
+        description: newer.description.clone().or(older.description.clone()),
+        images: if newer.images.is_empty() {
+            older.images.clone()
+        } else {
+            newer.images.clone()
+        },
+        rating: if newer.rating == 0 {
+            older.rating
+        } else {
+            newer.rating
+        },
+        custom: {
+            let mut custom = older.custom.clone();
+            custom.extend(newer.custom.clone());
+            custom
+        },
+        content_info: if newer.content_info == Default::default() {
+            older.content_info.clone()
+        } else {
+            newer.content_info.clone()
+        },
+        archives: {
+            let mut archives = older.archives.clone();
+            for version in &newer.archives {
+                match archives.iter_mut().find(|v| v.version == version.version) {
+                    Some(existing) => *existing = version.clone(),
+                    None => archives.push(version.clone()),
+                }
+            }
+            archives.sort_by_key(|v| v.add_time);
+            archives
+        },
+        deploy_info: existing.deploy_info.clone(),
+        play_info: PlayInfo {
+            executable: existing.play_info.executable.clone(),
+            playtime: existing.play_info.playtime.max(incoming.play_info.playtime),
+            last_played: existing
+                .play_info
+                .last_played
+                .max(incoming.play_info.last_played),
+        },
+        save_info: if newer.save_info == Default::default() {
+            older.save_info.clone()
+        } else {
+            newer.save_info.clone()
+        },
+        create_time: existing.create_time.min(incoming.create_time),
+        update_time: newer.update_time,
+    }
+}
+
+/// Resolves every imported entry against the library, returns the report and
+/// the entries to write.
+fn import_plan(
+    table: &impl ReadableTable<&'static str, Vec<u8>>,
+    entries: Vec<Metadata>,
+    mode: ImportMode,
+) -> Result<(ImportReport, Vec<Metadata>)> {
+    let mut report = ImportReport::default();
+    let mut writes = Vec::new();
+
+    for incoming in entries {
+        let id = incoming.id.to_string();
+        let existing = table
+            .get(id.as_str())?
+            .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+            .transpose()
+            .unwrap_or_else(|e| {
+                warn!("Existing entry '{}' is unreadable, replacing: {}", id, e);
+                None
+            });
+        let Some(existing) = existing else {
+            report.added.push(id);
+            writes.push(incoming);
+            continue;
+        };
+        if existing == incoming {
+            report.unchanged += 1;
+            continue;
+        }
+
+        report.conflicts.push(ImportConflict {
+            id: id.clone(),
+            title: incoming.title.clone(),
+            fields: diff(Some(&existing), Some(&incoming))?
+                .into_iter()
+                .map(|c| c.field)
+                .collect(),
+        });
+        let resolved = match mode {
+            ImportMode::Overwrite => Metadata {
+                deploy_info: existing.deploy_info.clone(),
+                ..incoming
+            },
+            ImportMode::KeepNewer if incoming.update_time > existing.update_time => Metadata {
+                deploy_info: existing.deploy_info.clone(),
+                ..incoming
+            },
+            ImportMode::KeepNewer | ImportMode::KeepExisting => existing.clone(),
+            ImportMode::Merge => import_merge(&existing, &incoming),
+        };
+        if resolved == existing {
+            report.unchanged += 1;
+        } else {
+            report.changed.push(id);
+            writes.push(resolved);
+        }
+    }
+
+    Ok((report, writes))
+}
+
+async fn import_read(arg: &ImportArg, app: &AppHandle) -> Result<Vec<Metadata>> {
+    let path = match &arg.path {
+        Some(path) => PathBuf::from(path),
+        None => app
+            .state_config()
+            .get()
+            .path_data()
+            .join(LIB_FILE_EXPORT_NAME),
+    };
+    if !path.exists() {
+        return Err(anyhow!("Library export file not found: {}", path.display()));
+    }
+
+    let json = tfs::read_to_string(&path)
+        .await
+        .map_err(|e| anyhow!("Failed to read library export file: {}", e))?;
+    let entries = serde_json::from_str::<Vec<serde_json::Value>>(&json)
+        .map_err(|e| anyhow!("Failed to deserialize JSON: {}", e))?
+        .into_iter()
+        .map(migration_upgrade)
+        .collect::<Result<Vec<_>>>()
+        .map_err(|e| anyhow!("Failed to read exported entry: {}", e))?;
+    if entries.is_empty() {
+        warn!(
+            "No entries found in the library export file: {}",
+            path.display()
+        );
+    }
+    Ok(entries)
+}
+
+/// Reports what [import_library] would do without writing anything.
+pub async fn import_preview(arg: ImportArg, app: AppHandle) -> Result<ImportReport> {
+    let entries = import_read(&arg, &app).await?;
+    let db = app.state_data().database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let table = read.open_table(TABLE_METADATA)?;
+        import_plan(&table, entries, arg.mode).map(|(report, _)| report)
+    })
+    .await?
+}
+
+pub async fn import_library(arg: ImportArg, app: AppHandle) -> Result<ImportReport> {
+    let entries = import_read(&arg, &app).await?;
+    let config = app.state_config().get();
+    let db = app.state_data().database();
+    let report = async_runtime::spawn_blocking(move || {
+        if config.backup().before_import {
+            backup_online(&db, &config, BackupTrigger::Import)?;
+        }
+        let write = db.begin_write()?;
+        let (report, writes) = {
+            let table = write.open_table(TABLE_METADATA)?;
+            import_plan(&table, entries, arg.mode)?
+        };
+        for entry in &writes {
+            metadata_write_as(
+                &write,
+                &entry.id.to_string(),
+                Some(entry),
+                Some(HistoryOp::Import),
+                None,
+            )?;
+        }
+        write.commit()?;
+        Ok::<ImportReport, anyhow::Error>(report)
+    })
+    .await??;
+
+    collection_cache_sync_all(app.state_data())?;
+    deployment_cache_sync_all(app.state_data())?;
+
+    info!(
+        "Library imported with mode {:?}: {} added, {} changed, {} unchanged",
+        arg.mode,
+        report.added.len(),
+        report.changed.len(),
+        report.unchanged
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+    use crate::core::data::metadata::DeployInfo;
+
+    fn metadata(title: &str, tags: &[&str]) -> Metadata {
+        Metadata {
+            title: title.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_import_plan() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let mut existing = metadata("Local", &["RPG"]);
+        existing.rating = 4;
+        let write = db.begin_write().unwrap();
+        write
+            .open_table(TABLE_METADATA)
+            .unwrap()
+            .insert(
+                existing.id.to_string().as_str(),
+                bson::to_vec(&existing).unwrap(),
+            )
+            .unwrap();
+        write.commit().unwrap();
+
+        let mut incoming = existing.clone();
+        incoming.title = String::from("Remote");
+        incoming.tags = vec![String::from("Fantasy")];
+        incoming.rating = 0;
+        incoming.update_time = existing.update_time + Duration::seconds(1);
+        let added = metadata("New", &[]);
+        let entries = vec![incoming.clone(), added.clone()];
+
+        let read = db.begin_read().unwrap();
+        let table = read.open_table(TABLE_METADATA).unwrap();
+
+        let (report, writes) = import_plan(&table, entries.clone(), ImportMode::Merge).unwrap();
+        assert_eq!(report.added, vec![added.id.to_string()]);
+        assert_eq!(report.changed, vec![existing.id.to_string()]);
+        assert_eq!(
+            report.conflicts[0].fields,
+            vec!["rating", "tags", "title", "update_time"]
+        );
+        let merged = &writes[0];
+        assert_eq!(merged.title, "Remote");
+        assert_eq!(merged.tags, vec!["RPG", "Fantasy"]);
+        assert_eq!(merged.rating, 4);
+
+        let (report, writes) =
+            import_plan(&table, entries.clone(), ImportMode::KeepExisting).unwrap();
+        assert!(report.changed.is_empty());
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(writes.len(), 1);
+
+        let (report, writes) = import_plan(&table, entries, ImportMode::KeepNewer).unwrap();
+        assert_eq!(report.changed.len(), 1);
+        assert_eq!(writes[0].title, "Remote");
+
+        // Deploy paths of the exporting machine are never taken over
+        incoming.deploy_info = DeployInfo::File {
+            path: PathBuf::from("remote.7z"),
+            version: None,
+        };
+        let (_, writes) = import_plan(&table, vec![incoming], ImportMode::Overwrite).unwrap();
+        assert_eq!(writes[0].title, "Remote");
+        assert_eq!(writes[0].deploy_info, existing.deploy_info);
+    }
+}
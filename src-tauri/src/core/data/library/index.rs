@@ -0,0 +1,454 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, anyhow};
+use log::{info, warn};
+use redb::{
+    Database, MultimapTableDefinition, ReadTransaction, ReadableMultimapTable, ReadableTable,
+    ReadableTableMetadata, TableDefinition, WriteTransaction,
+};
+use tauri::{State, async_runtime};
+
+use crate::{
+    cmd::append::{IndexQueryArg, ListArg},
+    core::data::{
+        library::{
+            TABLE_META, TABLE_METADATA,
+            search::{TABLE_SEARCH_DOC, TABLE_SEARCH_POSTING, search_clear, search_sync},
+            stats::{TABLE_INDEX_STATS, stats_sync},
+        },
+        metadata::{DeployInfo, Metadata, MetadataSummary, SummaryPage},
+        query::SortKey,
+        state::DataState,
+    },
+};
+
+/// Bump this whenever an index table is added or its layout changes, the
+/// indexes will be rebuilt from [TABLE_METADATA] on next startup.
+const INDEX_VERSION: u64 = 3;
+const META_INDEX_VERSION: &str = "index_version";
+
+pub(super) const TABLE_INDEX_TAG: MultimapTableDefinition<&str, &str> =
+    MultimapTableDefinition::new("index_tag");
+pub(super) const TABLE_INDEX_COLLECTION: MultimapTableDefinition<&str, &str> =
+    MultimapTableDefinition::new("index_collection");
+pub(super) const TABLE_INDEX_CONTENT_TYPE: MultimapTableDefinition<&str, &str> =
+    MultimapTableDefinition::new("index_content_type");
+pub(super) const TABLE_INDEX_RATING: MultimapTableDefinition<u8, &str> =
+    MultimapTableDefinition::new("index_rating");
+/// Update time in milliseconds to ids
+pub(super) const TABLE_INDEX_UPDATE_TIME: MultimapTableDefinition<i64, &str> =
+    MultimapTableDefinition::new("index_update_time");
+pub(super) const TABLE_INDEX_DEPLOYED: TableDefinition<&str, ()> =
+    TableDefinition::new("index_deployed");
+
+/// Opens every index table once so that read transactions never fail on a
+/// missing table, then rebuilds the indexes if they are outdated.
+pub(super) fn index_check(db: &Database) -> Result<()> {
+    let write = db.begin_write()?;
+    let version = write
+        .open_table(TABLE_META)?
+        .get(META_INDEX_VERSION)?
+        .map(|v| v.value());
+    if version == Some(INDEX_VERSION) {
+        write.open_multimap_table(TABLE_INDEX_TAG)?;
+        write.open_multimap_table(TABLE_INDEX_COLLECTION)?;
+        write.open_multimap_table(TABLE_INDEX_CONTENT_TYPE)?;
+        write.open_multimap_table(TABLE_INDEX_RATING)?;
+        write.open_multimap_table(TABLE_INDEX_UPDATE_TIME)?;
+        write.open_table(TABLE_INDEX_DEPLOYED)?;
+        write.open_table(TABLE_INDEX_STATS)?;
+        write.open_table(TABLE_SEARCH_POSTING)?;
+        write.open_table(TABLE_SEARCH_DOC)?;
+    } else {
+        info!(
+            "Index version mismatch ({:?} != {}), rebuilding indexes",
+            version, INDEX_VERSION
+        );
+        let count = index_rebuild(&write)?;
+        write
+            .open_table(TABLE_META)?
+            .insert(META_INDEX_VERSION, INDEX_VERSION)?;
+        info!("Rebuilt indexes for {} entries", count);
+    }
+    write.commit()?;
+    Ok(())
+}
+
+/// Drops all index tables and fills them again from [TABLE_METADATA].
+pub(super) fn index_rebuild(write: &WriteTransaction) -> Result<u64> {
+    write.delete_multimap_table(TABLE_INDEX_TAG)?;
+    write.delete_multimap_table(TABLE_INDEX_COLLECTION)?;
+    write.delete_multimap_table(TABLE_INDEX_CONTENT_TYPE)?;
+    write.delete_multimap_table(TABLE_INDEX_RATING)?;
+    write.delete_multimap_table(TABLE_INDEX_UPDATE_TIME)?;
+    write.delete_table(TABLE_INDEX_DEPLOYED)?;
+    write.delete_table(TABLE_INDEX_STATS)?;
+    search_clear(write)?;
+
+    let table = write.open_table(TABLE_METADATA)?;
+    let mut count = 0;
+    for (k, v) in table.iter()?.flatten() {
+        match bson::from_slice::<Metadata>(v.value().as_slice()) {
+            Ok(metadata) => {
+                index_sync(write, None, Some(&metadata))?;
+                count += 1;
+            },
+            Err(e) => warn!(
+                "Failed to deserialize metadata for key '{}', not indexed: {}",
+                k.value(),
+                e
+            ),
+        }
+    }
+    Ok(count)
+}
+
+/// Removes the index entries of `old` and adds the ones of `new`, must be
+/// called in the same transaction that writes [TABLE_METADATA].
+pub(super) fn index_sync(
+    write: &WriteTransaction,
+    old: Option<&Metadata>,
+    new: Option<&Metadata>,
+) -> Result<()> {
+    let mut tag = write.open_multimap_table(TABLE_INDEX_TAG)?;
+    let mut collection = write.open_multimap_table(TABLE_INDEX_COLLECTION)?;
+    let mut content_type = write.open_multimap_table(TABLE_INDEX_CONTENT_TYPE)?;
+    let mut rating = write.open_multimap_table(TABLE_INDEX_RATING)?;
+    let mut update_time = write.open_multimap_table(TABLE_INDEX_UPDATE_TIME)?;
+    let mut deployed = write.open_table(TABLE_INDEX_DEPLOYED)?;
+
+    if let Some(old) = old {
+        let id = old.id.to_string();
+        for t in &old.tags {
+            tag.remove(t.as_str(), id.as_str())?;
+        }
+        if let Some(c) = &old.collection {
+            collection.remove(c.as_str(), id.as_str())?;
+        }
+        content_type.remove(old.content_info.type_name(), id.as_str())?;
+        rating.remove(old.rating, id.as_str())?;
+        update_time.remove(old.update_time.timestamp_millis(), id.as_str())?;
+        deployed.remove(id.as_str())?;
+    }
+
+    if let Some(new) = new {
+        let id = new.id.to_string();
+        for t in &new.tags {
+            tag.insert(t.as_str(), id.as_str())?;
+        }
+        if let Some(c) = &new.collection {
+            collection.insert(c.as_str(), id.as_str())?;
+        }
+        content_type.insert(new.content_info.type_name(), id.as_str())?;
+        rating.insert(new.rating, id.as_str())?;
+        update_time.insert(new.update_time.timestamp_millis(), id.as_str())?;
+        if new.deploy_info != DeployInfo::None {
+            deployed.insert(id.as_str(), ())?;
+        }
+    }
+
+    stats_sync(write, old, new)?;
+    search_sync(write, old, new)
+}
+
+fn index_ids<K: redb::Key + 'static>(
+    read: &ReadTransaction,
+    def: MultimapTableDefinition<K, &str>,
+    key: K::SelfType<'_>,
+) -> Result<HashSet<String>> {
+    let table = read.open_multimap_table(def)?;
+    let mut ids = HashSet::new();
+    for id in table.get(key)? {
+        ids.insert(id?.value().to_string());
+    }
+    Ok(ids)
+}
+
+fn index_ids_rating(read: &ReadTransaction, min: u8, max: u8) -> Result<HashSet<String>> {
+    let table = read.open_multimap_table(TABLE_INDEX_RATING)?;
+    let mut ids = HashSet::new();
+    for entry in table.range(min..=max)? {
+        for id in entry?.1 {
+            ids.insert(id?.value().to_string());
+        }
+    }
+    Ok(ids)
+}
+
+/// Returns all keys of an index table whose value set is not empty.
+pub(super) fn index_keys(
+    read: &ReadTransaction,
+    def: MultimapTableDefinition<&str, &str>,
+) -> Result<Vec<String>> {
+    let table = read.open_multimap_table(def)?;
+    let mut keys = Vec::new();
+    for entry in table.iter()? {
+        keys.push(entry?.0.value().to_string());
+    }
+    Ok(keys)
+}
+
+pub(super) fn index_deployed(read: &ReadTransaction) -> Result<Vec<String>> {
+    let table = read.open_table(TABLE_INDEX_DEPLOYED)?;
+    let mut ids = Vec::new();
+    for entry in table.iter()? {
+        ids.push(entry?.0.value().to_string());
+    }
+    Ok(ids)
+}
+
+/// Reads the given ids from [TABLE_METADATA], missing or broken entries are
+/// skipped with a warning.
+pub(super) fn metadata_get_many<'a>(
+    read: &ReadTransaction,
+    ids: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<Metadata>> {
+    let table = read.open_table(TABLE_METADATA)?;
+    let mut result = Vec::new();
+    for id in ids {
+        match table.get(id)? {
+            Some(value) => match bson::from_slice::<Metadata>(value.value().as_slice()) {
+                Ok(metadata) => result.push(metadata),
+                Err(e) => warn!("Failed to deserialize value for key '{}': {}", id, e),
+            },
+            None => warn!("Indexed key '{}' not found in library", id),
+        }
+    }
+    Ok(result)
+}
+
+fn index_query_ids(read: &ReadTransaction, arg: &IndexQueryArg) -> Result<Option<HashSet<String>>> {
+    fn intersect(acc: Option<HashSet<String>>, next: HashSet<String>) -> Option<HashSet<String>> {
+        Some(match acc {
+            Some(acc) => acc.intersection(&next).cloned().collect(),
+            None => next,
+        })
+    }
+
+    let mut ids = None;
+    for tag in &arg.tags {
+        ids = intersect(ids, index_ids(read, TABLE_INDEX_TAG, tag.as_str())?);
+    }
+    if let Some(collection) = &arg.collection {
+        ids = intersect(
+            ids,
+            index_ids(read, TABLE_INDEX_COLLECTION, collection.as_str())?,
+        );
+    }
+    if let Some(content_type) = &arg.content_type {
+        ids = intersect(
+            ids,
+            index_ids(read, TABLE_INDEX_CONTENT_TYPE, content_type.as_str())?,
+        );
+    }
+    if arg.rating_min.is_some() || arg.rating_max.is_some() {
+        let min = arg.rating_min.unwrap_or(u8::MIN);
+        let max = arg.rating_max.unwrap_or(u8::MAX);
+        if min > max {
+            return Err(anyhow!("Invalid rating range: {} > {}", min, max));
+        }
+        ids = intersect(ids, index_ids_rating(read, min, max)?);
+    }
+    Ok(ids)
+}
+
+/// Looks up entries matching all conditions in `arg` through the index tables,
+/// ordered by update time, newest first. An empty query returns everything.
+pub async fn metadata_index_query(
+    arg: IndexQueryArg,
+    data: State<'_, DataState>,
+) -> Result<Vec<Metadata>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let ids = index_query_ids(&read, &arg)?;
+
+        let table = read.open_multimap_table(TABLE_INDEX_UPDATE_TIME)?;
+        let mut ordered = Vec::new();
+        for entry in table.iter()?.rev() {
+            for id in entry?.1 {
+                let id = id?.value().to_string();
+                if ids.as_ref().is_none_or(|ids| ids.contains(&id)) {
+                    ordered.push(id);
+                }
+            }
+        }
+
+        metadata_get_many(&read, ordered.iter().map(String::as_str))
+    })
+    .await?
+}
+
+/// Returns the most recently updated entries.
+pub async fn metadata_recent(limit: u32, data: State<'_, DataState>) -> Result<Vec<Metadata>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let table = read.open_multimap_table(TABLE_INDEX_UPDATE_TIME)?;
+        let mut ids = Vec::new();
+        'outer: for entry in table.iter()?.rev() {
+            for id in entry?.1 {
+                if ids.len() >= limit as usize {
+                    break 'outer;
+                }
+                ids.push(id?.value().to_string());
+            }
+        }
+        metadata_get_many(&read, ids.iter().map(String::as_str))
+    })
+    .await?
+}
+
+/// Lists summaries of one sorted page. Sorting by update time walks
+/// [TABLE_INDEX_UPDATE_TIME] and only reads the entries of the page.
+pub async fn metadata_list(arg: ListArg, data: State<'_, DataState>) -> Result<SummaryPage> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let table = read.open_table(TABLE_METADATA)?;
+        let total = table.len()? as u32;
+        let offset = arg.offset as usize;
+        let limit = arg.limit.map_or(usize::MAX, |l| l as usize);
+
+        let items = if let SortKey::UpdateTime = arg.sort {
+            let index = read.open_multimap_table(TABLE_INDEX_UPDATE_TIME)?;
+            let mut ids = Vec::new();
+            let mut skipped = 0;
+            let entries: Box<dyn Iterator<Item = _>> = if arg.desc {
+                Box::new(index.iter()?.rev())
+            } else {
+                Box::new(index.iter()?)
+            };
+            'outer: for entry in entries {
+                let mut group = entry?
+                    .1
+                    .map(|id| id.map(|id| id.value().to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if arg.desc {
+                    group.reverse();
+                }
+                for id in group {
+                    if ids.len() >= limit {
+                        break 'outer;
+                    }
+                    if skipped < offset {
+                        skipped += 1;
+                    } else {
+                        ids.push(id);
+                    }
+                }
+            }
+            metadata_get_many(&read, ids.iter().map(String::as_str))?
+                .iter()
+                .map(MetadataSummary::from)
+                .collect()
+        } else {
+            let mut all = Vec::with_capacity(total as usize);
+            for entry in table.iter()? {
+                let (k, v) = entry?;
+                match bson::from_slice::<Metadata>(v.value().as_slice()) {
+                    Ok(metadata) => all.push(metadata),
+                    Err(e) => warn!("Failed to deserialize entry '{}': {}", k.value(), e),
+                }
+            }
+            all.sort_by(|a, b| {
+                let ordering = arg.sort.compare(a, b);
+                if arg.desc {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+            all.iter()
+                .skip(offset)
+                .take(limit)
+                .map(MetadataSummary::from)
+                .collect()
+        };
+
+        Ok(SummaryPage { total, items })
+    })
+    .await?
+}
+
+/// Returns every tag in use together with the number of entries using it.
+pub async fn index_tag_list(data: State<'_, DataState>) -> Result<Vec<(String, u64)>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let table = read.open_multimap_table(TABLE_INDEX_TAG)?;
+        let mut tags = Vec::new();
+        for entry in table.iter()? {
+            let (tag, ids) = entry?;
+            tags.push((tag.value().to_string(), ids.len()));
+        }
+        Ok(tags)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use redb::backends::InMemoryBackend;
+
+    use super::*;
+
+    fn metadata(tags: &[&str], collection: Option<&str>, rating: u8) -> Metadata {
+        Metadata {
+            title: String::from("Test"),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            collection: collection.map(str::to_string),
+            rating,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_index_sync() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let a = metadata(&["RPG", "Fantasy"], Some("Series A"), 4);
+        let b = metadata(&["RPG"], None, 2);
+
+        let write = db.begin_write().unwrap();
+        index_sync(&write, None, Some(&a)).unwrap();
+        index_sync(&write, None, Some(&b)).unwrap();
+        write.commit().unwrap();
+
+        let read = db.begin_read().unwrap();
+        let query = |tags: &[&str], rating_min: Option<u8>| {
+            let arg = IndexQueryArg {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                collection: None,
+                content_type: None,
+                rating_min,
+                rating_max: None,
+            };
+            index_query_ids(&read, &arg).unwrap().unwrap()
+        };
+        assert_eq!(query(&["RPG"], None).len(), 2);
+        assert_eq!(query(&["RPG", "Fantasy"], None).len(), 1);
+        assert_eq!(query(&["RPG"], Some(3)).len(), 1);
+        drop(read);
+
+        let mut a_new = a.clone();
+        a_new.tags = vec![String::from("SLG")];
+        a_new.collection = None;
+        let write = db.begin_write().unwrap();
+        index_sync(&write, Some(&a), Some(&a_new)).unwrap();
+        write.commit().unwrap();
+
+        let read = db.begin_read().unwrap();
+        assert_eq!(index_ids(&read, TABLE_INDEX_TAG, "RPG").unwrap().len(), 1);
+        assert_eq!(
+            index_ids(&read, TABLE_INDEX_TAG, "Fantasy").unwrap().len(),
+            0
+        );
+        assert!(
+            index_keys(&read, TABLE_INDEX_COLLECTION)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, TimeZone, Utc};
+use log::{error, info};
+use redb::{ReadableTable, TableDefinition, WriteTransaction};
+use serde::Serialize;
+use tauri::{AppHandle, State, async_runtime};
+use tauri_plugin_shell::{ShellExt, process::CommandEvent};
+use ts_rs::TS;
+
+use crate::core::{
+    AppStateExt,
+    data::{
+        library::{
+            TABLE_METADATA, delegate::metadata_write_as, history::HistoryOp, metadata_get,
+            trash::TABLE_TRASH,
+        },
+        metadata::{DeployInfo, Metadata},
+        state::DataState,
+    },
+};
+
+/// (entry id, start in milliseconds) to end in milliseconds
+pub(super) const TABLE_PLAY_SESSION: TableDefinition<(&str, i64), i64> =
+    TableDefinition::new("play_session");
+
+/// Extensions considered launchable
+const EXECUTABLE_EXTENSIONS: &[&str] = &["exe", "bat", "cmd", "sh", "appimage", "x86_64"];
+/// Name parts of executables that are not the game itself
+const EXECUTABLE_IGNORED: &[&str] = &[
+    "unins", "setup", "install", "crash", "config", "update", "redist", "vc_", "dxweb", "helper",
+];
+/// Directories below the deployment searched for executables
+const EXECUTABLE_MAX_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct PlaySession {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Seconds between start and end
+    #[ts(type = "number")]
+    pub duration: u64,
+}
+
+impl PlaySession {
+    fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            end,
+            duration: (end - start).num_seconds().max(0) as u64,
+        }
+    }
+}
+
+/// Launchable files below `dir` relative to it, most likely the game first:
+/// not an installer or helper, closest to `dir`, then largest.
+fn launch_candidates(dir: &Path) -> Vec<String> {
+    let mut candidates = walkdir::WalkDir::new(dir)
+        .max_depth(EXECUTABLE_MAX_DEPTH)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    EXECUTABLE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+                })
+        })
+        .map(|e| {
+            let name = e.file_name().to_string_lossy().to_ascii_lowercase();
+            let ignored = EXECUTABLE_IGNORED.iter().any(|part| name.contains(part));
+            let size = e.metadata().map(|m| m.len()).unwrap_or_default();
+            (ignored, e.depth(), std::cmp::Reverse(size), e.into_path())
+        })
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .filter_map(|(.., path)| {
+            path.strip_prefix(dir)
+                .ok()
+                .map(|p| p.to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+fn deploy_dir(metadata: &Metadata) -> Result<PathBuf> {
+    match &metadata.deploy_info {
+        DeployInfo::Directory { path, .. } if path.is_dir() => Ok(path.clone()),
+        _ => Err(anyhow!(
+            "Entry '{}' is not deployed to a directory",
+            metadata.id
+        )),
+    }
+}
+
+/// Stores a finished session and adds it to the play time of the entry. Fails
+/// if the entry is no longer in the library.
+fn play_record(
+    write: &WriteTransaction,
+    id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<()> {
+    let existing = {
+        let table = write.open_table(TABLE_METADATA)?;
+        let value = table.get(id)?;
+        value
+            .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+            .transpose()?
+    };
+    let mut metadata = existing.ok_or_else(|| anyhow!("Key '{}' not found in library", id))?;
+    write
+        .open_table(TABLE_PLAY_SESSION)?
+        .insert((id, start.timestamp_millis()), end.timestamp_millis())?;
+    metadata.play_info.record(start, end);
+    metadata_write_as(write, id, Some(&metadata), Some(HistoryOp::Play), None)?;
+    Ok(())
+}
+
+/// Checks that an executable stays inside the deployment it is relative to
+fn executable_check(executable: &str) -> Result<()> {
+    let inside = Path::new(executable)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return Err(anyhow!(
+            "Executable '{}' must be relative to the deployment",
+            executable
+        ));
+    }
+    Ok(())
+}
+
+/// Removes the sessions of entries that are neither in the library nor in the
+/// trash, called after purging the trash.
+pub(super) fn play_session_prune(write: &WriteTransaction) -> Result<u32> {
+    let metadata = write.open_table(TABLE_METADATA)?;
+    let trash = write.open_table(TABLE_TRASH)?;
+    let mut table = write.open_table(TABLE_PLAY_SESSION)?;
+    let mut orphaned = Vec::new();
+    for entry in table.iter()? {
+        let (key, _) = entry?;
+        let (id, start) = key.value();
+        if metadata.get(id)?.is_none() && trash.get(id)?.is_none() {
+            orphaned.push((id.to_string(), start));
+        }
+    }
+    for (id, start) in &orphaned {
+        table.remove((id.as_str(), *start))?;
+    }
+    Ok(orphaned.len() as u32)
+}
+
+/// Moves the sessions of `from` to `to`, used when merging entries.
+pub(super) fn play_session_move(write: &WriteTransaction, from: &str, to: &str) -> Result<()> {
+    let mut table = write.open_table(TABLE_PLAY_SESSION)?;
+    let sessions = table
+        .range((from, i64::MIN)..=(from, i64::MAX))?
+        .map(|entry| entry.map(|(k, v)| (k.value().1, v.value())))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (start, end) in sessions {
+        table.remove((from, start))?;
+        table.insert((to, start), end)?;
+    }
+    Ok(())
+}
+
+/// Launchable files of a deployed entry, the first one is used when no
+/// executable is set.
+pub async fn launch_candidates_get(key: String, data: State<'_, DataState>) -> Result<Vec<String>> {
+    let metadata = metadata_get(key.clone(), data)
+        .await?
+        .ok_or_else(|| anyhow!("Key '{}' not found in library", key))?;
+    let dir = deploy_dir(&metadata)?;
+    Ok(async_runtime::spawn_blocking(move || launch_candidates(&dir)).await?)
+}
+
+/// Sets the executable used by [launch], or clears it to detect it again.
+pub async fn launch_set(
+    key: String,
+    executable: Option<String>,
+    data: State<'_, DataState>,
+) -> Result<()> {
+    if let Some(executable) = &executable {
+        executable_check(executable)?;
+    }
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let existing = {
+            let table = write.open_table(TABLE_METADATA)?;
+            let value = table.get(key.as_str())?;
+            value
+                .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+                .transpose()?
+        };
+        let mut metadata = existing.ok_or_else(|| anyhow!("Key '{}' not found in library", key))?;
+        metadata.play_info.executable = executable;
+        metadata.mark_update();
+        metadata_write_as(&write, &key, Some(&metadata), Some(HistoryOp::Patch), None)?;
+        write.commit()?;
+        Ok(())
+    })
+    .await?
+}
+
+/// Starts the executable of a deployed entry and records a session once the
+/// process exits. Fails if the entry is already running.
+pub async fn launch(key: String, app: AppHandle) -> Result<()> {
+    let data = app.state_data();
+    let metadata = metadata_get(key.clone(), data.clone())
+        .await?
+        .ok_or_else(|| anyhow!("Key '{}' not found in library", key))?;
+    let dir = deploy_dir(&metadata)?;
+    let executable = match &metadata.play_info.executable {
+        Some(executable) => executable.clone(),
+        None => {
+            let dir = dir.clone();
+            async_runtime::spawn_blocking(move || launch_candidates(&dir))
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("No executable found in '{}'", metadata.title))?
+        },
+    };
+    executable_check(&executable)?;
+    let program = dir.join(&executable);
+    if !program.is_file() {
+        return Err(anyhow!("Executable not found: {}", program.display()));
+    }
+
+    let mut started = false;
+    data.launch_cache_edit(|running| started = running.insert(key.clone()))?;
+    if !started {
+        return Err(anyhow!("'{}' is already running", metadata.title));
+    }
+    let spawned = app
+        .shell()
+        .command(program.to_string_lossy().to_string())
+        .current_dir(&dir)
+        .spawn();
+    let (mut rx, child) = match spawned {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            data.launch_cache_edit(|running| {
+                running.remove(&key);
+            })?;
+            return Err(anyhow!("Failed to start '{}': {}", program.display(), e));
+        },
+    };
+    let start = Utc::now();
+    info!("Launched '{}' as {}", program.display(), child.pid());
+
+    async_runtime::spawn(async move {
+        let _child = child;
+        while let Some(event) = rx.recv().await {
+            if let CommandEvent::Terminated(payload) = event {
+                info!("'{}' exited with {:?}", key, payload.code);
+                break;
+            }
+        }
+        let end = Utc::now();
+        let data = app.state_data();
+        let _ = data.launch_cache_edit(|running| {
+            running.remove(&key);
+        });
+        let db = data.database();
+        let result = async_runtime::spawn_blocking(move || {
+            let write = db.begin_write()?;
+            play_record(&write, &key, start, end)?;
+            write.commit()?;
+            Ok::<(), anyhow::Error>(())
+        })
+        .await;
+        match result {
+            Ok(Err(e)) => error!("Failed to record play session: {}", e),
+            Err(e) => error!("Failed to record play session: {}", e),
+            Ok(Ok(())) => {},
+        }
+    });
+    Ok(())
+}
+
+/// Ids of the entries started by [launch] that are still running
+pub fn launch_running(data: State<'_, DataState>) -> Result<Vec<String>> {
+    Ok(data
+        .launch_cache()
+        .lock()
+        .map_err(|e| anyhow!("Failed to read launch temp set: {}", e))?
+        .iter()
+        .cloned()
+        .collect())
+}
+
+/// Sessions of an entry, latest first.
+pub async fn play_sessions(key: String, data: State<'_, DataState>) -> Result<Vec<PlaySession>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let table = read.open_table(TABLE_PLAY_SESSION)?;
+        let mut sessions = Vec::new();
+        for entry in table.range((key.as_str(), i64::MIN)..=(key.as_str(), i64::MAX))? {
+            let (k, v) = entry?;
+            if let (Some(start), Some(end)) = (
+                Utc.timestamp_millis_opt(k.value().1).single(),
+                Utc.timestamp_millis_opt(v.value()).single(),
+            ) {
+                sessions.push(PlaySession::new(start, end));
+            }
+        }
+        sessions.reverse();
+        Ok(sessions)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use chrono::Duration;
+    use redb::{Database, ReadableTableMetadata, backends::InMemoryBackend};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::core::data::library::delegate::metadata_write;
+
+    #[test]
+    fn test_launch_candidates() {
+        let dir = std::env::temp_dir().join(format!("launch_{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        fs::write(dir.join("unins000.exe"), [0u8; 64]).unwrap();
+        fs::write(dir.join("bin/Game.exe"), [0u8; 64]).unwrap();
+        fs::write(dir.join("Config.exe"), [0u8; 8]).unwrap();
+        fs::write(dir.join("Launcher.EXE"), [0u8; 8]).unwrap();
+        fs::write(dir.join("readme.txt"), [0u8; 8]).unwrap();
+
+        let candidates = launch_candidates(&dir);
+        assert_eq!(candidates.len(), 4);
+        assert_eq!(candidates[0], "Launcher.EXE");
+        assert_eq!(Path::new(&candidates[1]), Path::new("bin").join("Game.exe"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_executable_check() {
+        assert!(executable_check("game.exe").is_ok());
+        assert!(executable_check("./bin/game.exe").is_ok());
+        assert!(executable_check("../game.exe").is_err());
+        assert!(executable_check("bin/../../game.exe").is_err());
+        assert!(executable_check("/usr/bin/game").is_err());
+    }
+
+    #[test]
+    fn test_play_record() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "create_time": "2024-01-01T00:00:00Z",
+            "update_time": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let id = metadata.id.to_string();
+        let start = Utc::now();
+
+        let write = db.begin_write().unwrap();
+        write.open_table(TABLE_TRASH).unwrap();
+        metadata_write(&write, &id, Some(&metadata)).unwrap();
+        play_record(&write, &id, start, start + Duration::minutes(30)).unwrap();
+        play_record(
+            &write,
+            &id,
+            start + Duration::hours(2),
+            start + Duration::hours(3),
+        )
+        .unwrap();
+        assert!(play_record(&write, "gone", start, start + Duration::minutes(5)).is_err());
+        write
+            .open_table(TABLE_PLAY_SESSION)
+            .unwrap()
+            .insert(("gone", 0), 1)
+            .unwrap();
+        assert_eq!(play_session_prune(&write).unwrap(), 1);
+
+        let value = write
+            .open_table(TABLE_METADATA)
+            .unwrap()
+            .get(id.as_str())
+            .unwrap()
+            .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()).unwrap())
+            .unwrap();
+        assert_eq!(value.play_info.playtime, 90 * 60);
+        assert_eq!(
+            value.play_info.last_played,
+            Some(start + Duration::hours(3))
+        );
+        assert_eq!(
+            write.open_table(TABLE_PLAY_SESSION).unwrap().len().unwrap(),
+            2
+        );
+    }
+}
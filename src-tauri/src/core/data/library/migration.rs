@@ -0,0 +1,794 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Result, anyhow};
+use bson::{Bson, Document, doc};
+use chrono::DateTime;
+use log::{info, warn};
+use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition, WriteTransaction};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::core::data::{
+    library::{
+        TABLE_META, TABLE_METADATA,
+        history::{FieldChange, HistoryRecord, TABLE_HISTORY},
+        index::index_rebuild,
+        trash::{TABLE_TRASH, TrashEntry},
+    },
+    metadata::Metadata,
+    state::DataState,
+};
+
+/// Version of the current [Metadata] layout, records written before
+/// versioning was introduced are treated as version 1.
+pub(super) const SCHEMA_VERSION: u64 = 3;
+const META_SCHEMA_VERSION: &str = "schema_version";
+
+/// One step upgrading a raw record to `version`, returns whether the record
+/// was changed.
+pub(super) struct Migration {
+    pub version: u64,
+    pub description: &'static str,
+    pub migrate: fn(&mut Document) -> Result<bool>,
+}
+
+/// Registered steps, ordered by version. Add a step here whenever a change to
+/// [Metadata] can not be covered by `#[serde(default)]`. Steps must leave
+/// records already in the new layout untouched, see [migration_upgrade].
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        description: "Move the single image into the image list as cover",
+        migrate: migrate_image_list,
+    },
+    Migration {
+        version: 3,
+        description: "Move the single archive into the version list",
+        migrate: migrate_archive_versions,
+    },
+];
+
+fn migrate_image_list(doc: &mut Document) -> Result<bool> {
+    let Some(image) = doc.remove("image") else {
+        return Ok(false);
+    };
+    if let Bson::String(hash) = image
+        && !doc.contains_key("images")
+    {
+        doc.insert("images", vec![doc! { "hash": hash, "role": "Cover" }]);
+    }
+    Ok(true)
+}
+
+/// The archive becomes the first version, labeled with the game version or
+/// the creation time like a version added without a label.
+fn migrate_archive_versions(doc: &mut Document) -> Result<bool> {
+    let Some(archive) = doc.remove("archive_info") else {
+        return Ok(false);
+    };
+    let Bson::Document(archive) = archive else {
+        return Ok(true);
+    };
+    if archive.get_str("type").is_ok_and(|t| t != "None") && !doc.contains_key("archives") {
+        let add_time = doc.get("create_time").cloned().unwrap_or(Bson::Null);
+        let game_version = doc
+            .get_document("content_info")
+            .ok()
+            .filter(|c| c.get_str("type") == Ok("Game"))
+            .and_then(|c| c.get_document("data").ok())
+            .and_then(|d| d.get_str("version").ok())
+            .filter(|v| !v.is_empty());
+        let version = match game_version {
+            Some(version) => version.to_string(),
+            None => doc
+                .get_str("create_time")
+                .ok()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.format("%Y%m%d%H%M%S").to_string())
+                .ok_or_else(|| anyhow!("Invalid create_time"))?,
+        };
+        doc.insert(
+            "archives",
+            vec![doc! { "version": version, "archive": archive, "add_time": add_time }],
+        );
+    }
+    Ok(true)
+}
+
+/// Reads an entry written by any earlier version, used for imported exports
+/// which are not covered by the library migration.
+pub(super) fn migration_upgrade(value: serde_json::Value) -> Result<Metadata> {
+    let mut doc = bson::to_document(&value)?;
+    for step in MIGRATIONS {
+        (step.migrate)(&mut doc)?;
+    }
+    Ok(bson::from_document(doc)?)
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct MigrationReport {
+    #[ts(type = "number")]
+    pub from_version: u64,
+    #[ts(type = "number")]
+    pub to_version: u64,
+    pub total: u32,
+    pub steps: Vec<MigrationStepReport>,
+    /// Records that can not be read after all steps are applied
+    pub failed: Vec<MigrationFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct MigrationStepReport {
+    #[ts(type = "number")]
+    pub version: u64,
+    pub description: String,
+    pub changed: u32,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct MigrationFailure {
+    pub id: String,
+    /// The record is a trashed entry
+    pub trashed: bool,
+    pub error: String,
+}
+
+/// Tables holding [Metadata] records, migrated by the same steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordKind {
+    Metadata,
+    /// A [TrashEntry], the record is under `metadata`
+    Trash,
+}
+
+impl RecordKind {
+    fn table(&self) -> TableDefinition<'static, &'static str, Vec<u8>> {
+        match self {
+            Self::Metadata => TABLE_METADATA,
+            Self::Trash => TABLE_TRASH,
+        }
+    }
+
+    fn metadata<'a>(&self, doc: &'a mut Document) -> Result<&'a mut Document> {
+        match self {
+            Self::Metadata => Ok(doc),
+            Self::Trash => Ok(doc.get_document_mut("metadata")?),
+        }
+    }
+
+    /// Checks with the same raw deserializer used for reading the table
+    fn check(&self, raw: &[u8]) -> Result<()> {
+        match self {
+            Self::Metadata => bson::from_slice::<Metadata>(raw).map(|_| ()),
+            Self::Trash => bson::from_slice::<TrashEntry>(raw).map(|_| ()),
+        }?;
+        Ok(())
+    }
+
+    fn failure(&self, id: String, error: String) -> MigrationFailure {
+        MigrationFailure {
+            id,
+            trashed: *self == Self::Trash,
+            error,
+        }
+    }
+}
+
+impl MigrationReport {
+    /// Adds the counts of `other`, which ran the same steps on another table
+    fn merge(&mut self, other: MigrationReport) {
+        self.total += other.total;
+        for (step, other) in self.steps.iter_mut().zip(other.steps) {
+            step.changed += other.changed;
+        }
+        self.failed.extend(other.failed);
+    }
+}
+
+/// The schema version of a library, one without a stored version is at
+/// [SCHEMA_VERSION] if empty or version 1 otherwise.
+fn schema_version_of(stored: Option<u64>, metadata: &impl ReadableTableMetadata) -> Result<u64> {
+    match stored {
+        Some(version) => Ok(version),
+        None if metadata.is_empty()? => Ok(SCHEMA_VERSION),
+        None => Ok(1),
+    }
+}
+
+/// Reads the schema version, see [schema_version_of], and stamps it if it was
+/// not stored yet.
+fn schema_version(db: &Database) -> Result<u64> {
+    let write = db.begin_write()?;
+    let version = {
+        let mut meta = write.open_table(TABLE_META)?;
+        let stored = meta.get(META_SCHEMA_VERSION)?.map(|v| v.value());
+        let version = schema_version_of(stored, &write.open_table(TABLE_METADATA)?)?;
+        if stored.is_none() {
+            meta.insert(META_SCHEMA_VERSION, version)?;
+        }
+        version
+    };
+    write.commit()?;
+    Ok(version)
+}
+
+/// Returns the stored version if the library needs to be migrated, fails if it
+/// was written by a newer version of the application.
+pub(super) fn migration_pending(db: &Database) -> Result<Option<u64>> {
+    let version = schema_version(db)?;
+    if version > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Library schema version {} is newer than supported version {}",
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+    Ok((version < SCHEMA_VERSION).then_some(version))
+}
+
+/// Changed records as id and raw value
+type ChangedRecords = Vec<(String, Vec<u8>)>;
+
+/// Applies `steps` newer than `from` to every record of a `kind` table,
+/// returns the report and the changed records.
+fn migrate_records(
+    table: &impl ReadableTable<&'static str, Vec<u8>>,
+    kind: RecordKind,
+    steps: &[Migration],
+    from: u64,
+    to: u64,
+) -> Result<(MigrationReport, ChangedRecords)> {
+    let steps = steps
+        .iter()
+        .filter(|s| s.version > from && s.version <= to)
+        .collect::<Vec<_>>();
+    let mut report = MigrationReport {
+        from_version: from,
+        to_version: to,
+        total: 0,
+        steps: steps
+            .iter()
+            .map(|s| MigrationStepReport {
+                version: s.version,
+                description: s.description.to_string(),
+                changed: 0,
+            })
+            .collect(),
+        failed: Vec::new(),
+    };
+    let mut changed = Vec::new();
+
+    for entry in table.iter()? {
+        let (k, v) = entry?;
+        let id = k.value().to_string();
+        report.total += 1;
+
+        let mut doc = match bson::from_slice::<Document>(v.value().as_slice()) {
+            Ok(doc) => doc,
+            Err(e) => {
+                report.failed.push(kind.failure(id, e.to_string()));
+                continue;
+            },
+        };
+        let metadata = match kind.metadata(&mut doc) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                report.failed.push(kind.failure(id, e.to_string()));
+                continue;
+            },
+        };
+
+        let mut dirty = false;
+        let mut error = None;
+        for (i, step) in steps.iter().enumerate() {
+            match (step.migrate)(metadata) {
+                Ok(true) => {
+                    report.steps[i].changed += 1;
+                    dirty = true;
+                },
+                Ok(false) => {},
+                Err(e) => {
+                    error = Some(format!("Step {} failed: {}", step.version, e));
+                    break;
+                },
+            }
+        }
+        if let Some(error) = error {
+            report.failed.push(kind.failure(id, error));
+            continue;
+        }
+
+        let raw = bson::to_vec(&doc)?;
+        if let Err(e) = kind.check(&raw) {
+            report.failed.push(kind.failure(id, e.to_string()));
+            continue;
+        }
+        if dirty {
+            changed.push((id, raw));
+        }
+    }
+
+    Ok((report, changed))
+}
+
+/// Migrates the records of a `kind` table from version `from` in place
+fn migrate_table(write: &WriteTransaction, kind: RecordKind, from: u64) -> Result<MigrationReport> {
+    let mut table = write.open_table(kind.table())?;
+    let (report, changed) = migrate_records(&table, kind, MIGRATIONS, from, SCHEMA_VERSION)?;
+    for (id, raw) in changed {
+        table.insert(id.as_str(), raw)?;
+    }
+    Ok(report)
+}
+
+/// Fields some steps read from the whole entry, see [migrate_archive_versions]
+const CONTEXT_FIELDS: [&str; 2] = ["create_time", "content_info"];
+
+/// Runs `steps` over one side of a history record, `context` fills in the
+/// fields a step reads but the record does not hold. Returns whether the side
+/// was changed.
+fn migrate_side(
+    side: &mut Map<String, Value>,
+    context: &Document,
+    steps: &[&Migration],
+) -> Result<bool> {
+    let mut doc = bson::to_document(&*side)?;
+    let mut added = Vec::new();
+    for field in CONTEXT_FIELDS {
+        if !doc.contains_key(field)
+            && let Some(value) = context.get(field)
+        {
+            doc.insert(field, value.clone());
+            added.push(field);
+        }
+    }
+    let mut dirty = false;
+    for step in steps {
+        dirty |= (step.migrate)(&mut doc)?;
+    }
+    if dirty {
+        for field in added {
+            doc.remove(field);
+        }
+        let Value::Object(migrated) = Bson::Document(doc).into_relaxed_extjson() else {
+            return Err(anyhow!("Migrated record is not an object"));
+        };
+        *side = migrated;
+    }
+    Ok(dirty)
+}
+
+/// Value of a field missing on one side of a migrated change, nothing for a
+/// created or deleted entry and an empty list for the list fields the steps
+/// introduce.
+fn migrate_missing(whole: bool, other: &Value) -> Value {
+    match other {
+        Value::Array(_) if !whole => Value::Array(Vec::new()),
+        _ => Value::Null,
+    }
+}
+
+/// Applies `steps` newer than `from` to both sides of the history records, so
+/// reverting a record written before a migration restores the current layout.
+/// Returns the number of changed records.
+fn migrate_history(write: &WriteTransaction, steps: &[Migration], from: u64) -> Result<u32> {
+    let steps = steps
+        .iter()
+        .filter(|s| s.version > from)
+        .collect::<Vec<_>>();
+    if steps.is_empty() {
+        return Ok(0);
+    }
+    let metadata = write.open_table(TABLE_METADATA)?;
+    let trash = write.open_table(TABLE_TRASH)?;
+    let mut table = write.open_table(TABLE_HISTORY)?;
+
+    let mut changed = Vec::new();
+    for entry in table.iter()? {
+        let (k, v) = entry?;
+        let seq = k.value();
+        let mut record = match serde_json::from_slice::<HistoryRecord>(v.value().as_slice()) {
+            Ok(record) => record,
+            Err(e) => {
+                warn!("Failed to read history record {}: {}", seq, e);
+                continue;
+            },
+        };
+
+        let context = match metadata.get(record.id.as_str())? {
+            Some(raw) => bson::from_slice::<Document>(raw.value().as_slice()).ok(),
+            None => trash.get(record.id.as_str())?.and_then(|raw| {
+                bson::from_slice::<Document>(raw.value().as_slice())
+                    .ok()
+                    .and_then(|d| d.get_document("metadata").ok().cloned())
+            }),
+        }
+        .unwrap_or_else(|| doc! { "create_time": record.time.to_rfc3339() });
+
+        let (mut before, mut after) = (Map::new(), Map::new());
+        for change in &record.changes {
+            before.insert(change.field.clone(), change.before.clone());
+            after.insert(change.field.clone(), change.after.clone());
+        }
+        let whole_before = before.get("id").is_some_and(Value::is_null);
+        let whole_after = after.get("id").is_some_and(Value::is_null);
+        let dirty = match migrate_side(&mut before, &context, &steps)
+            .and_then(|b| Ok(migrate_side(&mut after, &context, &steps)? | b))
+        {
+            Ok(dirty) => dirty,
+            Err(e) => {
+                warn!("Failed to migrate history record {}: {}", seq, e);
+                continue;
+            },
+        };
+        if !dirty {
+            continue;
+        }
+
+        let fields = before
+            .keys()
+            .chain(after.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        record.changes = fields
+            .into_iter()
+            .filter_map(|field| {
+                let (b, a) = match (before.remove(&field), after.remove(&field)) {
+                    (Some(b), Some(a)) => (b, a),
+                    (Some(b), None) => (b.clone(), migrate_missing(whole_after, &b)),
+                    (None, Some(a)) => (migrate_missing(whole_before, &a), a),
+                    (None, None) => return None,
+                };
+                (b != a).then_some(FieldChange {
+                    field,
+                    before: b,
+                    after: a,
+                })
+            })
+            .collect();
+        changed.push((seq, serde_json::to_vec(&record)?));
+    }
+
+    let count = changed.len() as u32;
+    for (seq, raw) in changed {
+        table.insert(seq, raw)?;
+    }
+    Ok(count)
+}
+
+/// Migrates all records of the library and the trash to [SCHEMA_VERSION] in
+/// one transaction and rebuilds the indexes. The history is migrated along so
+/// its records can still be reverted. Records failing to migrate are
+/// left untouched, and the stored version stays at the one they are in so
+/// they are tried again on the next start.
+pub(super) fn migration_run(db: &Database) -> Result<MigrationReport> {
+    let from = schema_version(db)?;
+    let write = db.begin_write()?;
+    let mut report = migrate_table(&write, RecordKind::Metadata, from)?;
+    report.merge(migrate_table(&write, RecordKind::Trash, from)?);
+    let history = migrate_history(&write, MIGRATIONS, from)?;
+    index_rebuild(&write)?;
+    if report.failed.is_empty() {
+        write
+            .open_table(TABLE_META)?
+            .insert(META_SCHEMA_VERSION, SCHEMA_VERSION)?;
+    } else {
+        warn!(
+            "Keeping schema version {} for {} entries that failed to migrate",
+            from,
+            report.failed.len()
+        );
+    }
+    write.commit()?;
+
+    info!(
+        "Migrated library from schema version {} to {}: {:?}, {} history records",
+        report.from_version, report.to_version, report.steps, history
+    );
+    for failure in &report.failed {
+        warn!(
+            "Failed to migrate {}entry '{}': {}",
+            if failure.trashed { "trashed " } else { "" },
+            failure.id,
+            failure.error
+        );
+    }
+    Ok(report)
+}
+
+/// Reports what a migration to [SCHEMA_VERSION] would change without writing
+/// anything. Also lists the records that can not be read.
+pub async fn migration_dry_run(data: State<'_, DataState>) -> Result<MigrationReport> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let stored = read
+            .open_table(TABLE_META)?
+            .get(META_SCHEMA_VERSION)?
+            .map(|v| v.value());
+        let from = schema_version_of(stored, &read.open_table(TABLE_METADATA)?)?;
+        let migrate = |kind: RecordKind| {
+            let table = read.open_table(kind.table())?;
+            migrate_records(&table, kind, MIGRATIONS, from, SCHEMA_VERSION)
+                .map(|(report, _)| report)
+        };
+        let mut report = migrate(RecordKind::Metadata)?;
+        report.merge(migrate(RecordKind::Trash)?);
+        Ok(report)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use redb::backends::InMemoryBackend;
+
+    use super::*;
+    use crate::core::data::library::{
+        delegate::metadata_write, history::HistoryOp, trash::trash_remove,
+    };
+
+    fn rename_name_to_title(doc: &mut Document) -> Result<bool> {
+        match doc.remove("name") {
+            Some(name) => {
+                doc.insert("title", name);
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    #[test]
+    fn test_migrate_records() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let metadata = Metadata {
+            ..Default::default()
+        };
+        let mut legacy = bson::from_slice::<Document>(&bson::to_vec(&metadata).unwrap()).unwrap();
+        legacy.remove("title");
+        legacy.insert("name", "Legacy");
+        let broken = doc! { "id": 42 };
+
+        let write = db.begin_write().unwrap();
+        {
+            let mut table = write.open_table(TABLE_METADATA).unwrap();
+            table.insert("a", bson::to_vec(&legacy).unwrap()).unwrap();
+            table.insert("b", bson::to_vec(&broken).unwrap()).unwrap();
+        }
+        write.commit().unwrap();
+
+        let steps = [Migration {
+            version: 2,
+            description: "Rename name to title",
+            migrate: rename_name_to_title,
+        }];
+        let read = db.begin_read().unwrap();
+        let table = read.open_table(TABLE_METADATA).unwrap();
+        let (report, changed) =
+            migrate_records(&table, RecordKind::Metadata, &steps, 1, 2).unwrap();
+
+        assert_eq!(report.total, 2);
+        assert_eq!(report.steps[0].changed, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].id, "b");
+        assert_eq!(changed.len(), 1);
+        let migrated = bson::from_slice::<Metadata>(&changed[0].1).unwrap();
+        assert_eq!(migrated.title, "Legacy");
+
+        let (report, changed) =
+            migrate_records(&table, RecordKind::Metadata, &steps, 2, 2).unwrap();
+        assert!(report.steps.is_empty());
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn test_migration_failed_version() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        assert_eq!(schema_version(&db).unwrap(), SCHEMA_VERSION);
+
+        let write = db.begin_write().unwrap();
+        write
+            .open_table(TABLE_METADATA)
+            .unwrap()
+            .insert("broken", bson::to_vec(&doc! { "id": 42 }).unwrap())
+            .unwrap();
+        write
+            .open_table(TABLE_META)
+            .unwrap()
+            .insert(META_SCHEMA_VERSION, 1)
+            .unwrap();
+        write.commit().unwrap();
+
+        let report = migration_run(&db).unwrap();
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(migration_pending(&db).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn test_migrate_trash() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let entry = TrashEntry {
+            metadata: Metadata {
+                title: String::from("Trashed"),
+                ..Default::default()
+            },
+            delete_time: Utc::now(),
+        };
+        let id = entry.metadata.id.to_string();
+        let id = id.as_str();
+        let mut trashed = bson::from_slice::<Document>(&bson::to_vec(&entry).unwrap()).unwrap();
+        let legacy = trashed.get_document_mut("metadata").unwrap();
+        legacy.remove("images");
+        legacy.insert("image", "hash");
+        let write = db.begin_write().unwrap();
+        write
+            .open_table(TABLE_TRASH)
+            .unwrap()
+            .insert(id, bson::to_vec(&trashed).unwrap())
+            .unwrap();
+        write
+            .open_table(TABLE_META)
+            .unwrap()
+            .insert(META_SCHEMA_VERSION, 1)
+            .unwrap();
+        write.commit().unwrap();
+
+        let report = migration_run(&db).unwrap();
+        assert_eq!(report.total, 1);
+        assert_eq!(report.steps[0].changed, 1);
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+
+        // Restoring reads the record like trash_restore does
+        let write = db.begin_write().unwrap();
+        let entry = trash_remove(&write, id).unwrap().unwrap();
+        metadata_write(&write, id, Some(&entry.metadata)).unwrap();
+        write.commit().unwrap();
+        assert_eq!(entry.metadata.title, "Trashed");
+        assert_eq!(entry.metadata.cover(), Some("hash"));
+    }
+
+    #[test]
+    fn test_migrate_trash_archives() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let entry = TrashEntry {
+            metadata: Default::default(),
+            delete_time: Utc::now(),
+        };
+        let id = entry.metadata.id.to_string();
+        let mut trashed = bson::from_slice::<Document>(&bson::to_vec(&entry).unwrap()).unwrap();
+        let legacy = trashed.get_document_mut("metadata").unwrap();
+        legacy.remove("archives");
+        legacy.insert(
+            "archive_info",
+            doc! { "type": "Directory", "data": { "size": 4, "path": "dir" } },
+        );
+        let write = db.begin_write().unwrap();
+        write
+            .open_table(TABLE_TRASH)
+            .unwrap()
+            .insert(id.as_str(), bson::to_vec(&trashed).unwrap())
+            .unwrap();
+        write
+            .open_table(TABLE_META)
+            .unwrap()
+            .insert(META_SCHEMA_VERSION, 2)
+            .unwrap();
+        write.commit().unwrap();
+
+        let report = migration_run(&db).unwrap();
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].changed, 1);
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+
+        let write = db.begin_write().unwrap();
+        let entry = trash_remove(&write, &id).unwrap().unwrap();
+        assert_eq!(entry.metadata.archives.len(), 1);
+        assert_eq!(entry.metadata.archive_size(), 4);
+    }
+
+    #[test]
+    fn test_migrate_history() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let entry = Metadata::default();
+        let id = entry.id.to_string();
+        let directory =
+            serde_json::json!({ "type": "Directory", "data": { "size": 4, "path": "dir" } });
+        let record = HistoryRecord {
+            seq: 0,
+            id: id.clone(),
+            time: Utc::now(),
+            op: HistoryOp::Patch,
+            undo_of: None,
+            changes: vec![
+                FieldChange {
+                    field: String::from("archive_info"),
+                    before: serde_json::json!({ "type": "None" }),
+                    after: directory,
+                },
+                FieldChange {
+                    field: String::from("image"),
+                    before: Value::from("a"),
+                    after: Value::from("b"),
+                },
+            ],
+        };
+        let write = db.begin_write().unwrap();
+        metadata_write(&write, &id, Some(&entry)).unwrap();
+        write
+            .open_table(TABLE_HISTORY)
+            .unwrap()
+            .insert(0, serde_json::to_vec(&record).unwrap())
+            .unwrap();
+
+        assert_eq!(migrate_history(&write, MIGRATIONS, 1).unwrap(), 1);
+        // Already migrated records are left alone
+        assert_eq!(migrate_history(&write, MIGRATIONS, 1).unwrap(), 0);
+        let raw = write
+            .open_table(TABLE_HISTORY)
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .unwrap()
+            .value();
+        let record = serde_json::from_slice::<HistoryRecord>(&raw).unwrap();
+        assert_eq!(
+            record
+                .changes
+                .iter()
+                .map(|c| c.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["archives", "images"]
+        );
+
+        let mut fields = serde_json::to_value(&entry).unwrap();
+        for change in &record.changes {
+            fields[&change.field] = change.after.clone();
+        }
+        let after = serde_json::from_value::<Metadata>(fields.clone()).unwrap();
+        assert_eq!(after.archive_size(), 4);
+        assert_eq!(after.images[0].hash, "b");
+        for change in &record.changes {
+            fields[&change.field] = change.before.clone();
+        }
+        let before = serde_json::from_value::<Metadata>(fields).unwrap();
+        assert!(before.archives.is_empty());
+        assert_eq!(before.images[0].hash, "a");
+    }
+
+    #[test]
+    fn test_migration_upgrade() {
+        let legacy = serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000000",
+            "image": "hash",
+            "content_info": { "type": "Game", "data": { "version": "1.2" } },
+            "archive_info": { "type": "Directory", "data": { "size": 4, "path": "dir" } },
+            "create_time": "2024-01-01T00:00:00Z",
+            "update_time": "2024-01-01T00:00:00Z",
+        });
+        let metadata = migration_upgrade(legacy).unwrap();
+        assert_eq!(metadata.cover(), Some("hash"));
+        assert_eq!(metadata.archives.len(), 1);
+        assert_eq!(metadata.archives[0].version, "1.2");
+        assert_eq!(metadata.archive_size(), 4);
+        assert_eq!(metadata.archives[0].add_time, metadata.create_time);
+
+        let current = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(migration_upgrade(current).unwrap(), metadata);
+    }
+}
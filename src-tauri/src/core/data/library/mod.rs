@@ -1,21 +1,83 @@
+mod archive;
+mod backup;
 mod collection;
 mod delegate;
 mod deployment;
+mod doctor;
+mod duplicate;
+mod export;
+mod gallery;
+mod history;
+mod import;
+mod index;
+mod launcher;
+mod migration;
+mod relation;
+mod save;
+mod search;
+mod stats;
+mod tag;
+mod trash;
 mod util;
 
-use std::{fs, path::Path};
+use std::path::Path;
 
 use anyhow::{Result, anyhow};
 use const_format::formatc;
-use log::{info, warn};
+use log::{error, info};
 use redb::{Database, ReadableTable, TableDefinition};
 use tauri::{AppHandle, Manager, async_runtime};
-use tokio::fs as tfs;
 
-pub use self::{collection::collection_cache_get, delegate::*, deployment::*, util::*};
+pub use self::{
+    archive::{archive_retention_apply, archive_version_remove, archive_version_update},
+    backup::{BackupInfo, backup_create, backup_list, backup_restore, backup_verify},
+    collection::{
+        CollectionRecord, collection_cache_get, collection_list, collection_members,
+        collection_rename, collection_reorder, collection_save,
+    },
+    delegate::*,
+    deployment::*,
+    doctor::{DoctorIssue, DoctorIssueKind, DoctorReport, doctor_check, doctor_repair},
+    duplicate::{DuplicateGroup, DuplicateReason, MergeArchive, duplicate_find, duplicate_merge},
+    export::{ExportFormat, export_library},
+    gallery::{ImageImportFailure, ImageImportReport, ImageSource, image_import},
+    history::{FieldChange, HistoryOp, HistoryRecord, history_list, history_revert, history_undo},
+    import::{ImportConflict, ImportMode, ImportReport, import_library, import_preview},
+    index::{index_tag_list, metadata_index_query, metadata_list, metadata_recent},
+    launcher::{
+        PlaySession, launch, launch_candidates_get, launch_running, launch_set, play_sessions,
+    },
+    migration::{MigrationFailure, MigrationReport, MigrationStepReport, migration_dry_run},
+    relation::{
+        RelationEdge, RelationGraph, RelationKind, relation_add, relation_graph, relation_remove,
+    },
+    save::{
+        SaveLocations, SaveSnapshot, SaveTrigger, save_locations_get, save_locations_set,
+        save_restore, save_snapshot, save_snapshots,
+    },
+    search::{SearchResult, metadata_search},
+    stats::{LibraryStats, StatCount, stats_get},
+    tag::{TagInfo, TagRecord, tag_delete, tag_list, tag_merge, tag_rename, tag_save},
+    trash::{TrashEntry, trash_list, trash_purge, trash_restore},
+    util::*,
+};
 use crate::core::{
     AppStateExt,
-    data::{library::collection::collection_cache_sync_all, metadata::Metadata, state::DataState},
+    config::AppConfig,
+    data::{
+        library::{
+            backup::{BackupTrigger, backup_exists, backup_file, backup_online, backup_schedule},
+            collection::{TABLE_COLLECTION, collection_cache_sync_all, collection_check},
+            history::{TABLE_HISTORY, TABLE_HISTORY_ENTRY, history_prune},
+            index::index_check,
+            launcher::TABLE_PLAY_SESSION,
+            migration::{migration_pending, migration_run},
+            relation::TABLE_RELATION,
+            tag::{TABLE_TAG, TABLE_TAG_ALIAS},
+            trash::{TABLE_TRASH, trash_purge_expired},
+        },
+        state::DataState,
+    },
 };
 
 const LIB_FILE_STEM: &str = "lib";
@@ -24,153 +86,115 @@ const LIB_FILE_NAME: &str = formatc!("{LIB_FILE_STEM}.{LIB_FILE_EXT}");
 const LIB_FILE_EXPORT_EXT: &str = "json";
 const LIB_FILE_EXPORT_NAME: &str = formatc!("{LIB_FILE_STEM}.{LIB_FILE_EXPORT_EXT}");
 
-const DIR_BACKUP: &str = "backup";
-
 const TABLE_METADATA: TableDefinition<&str, Vec<u8>> = TableDefinition::new("metadata");
+/// Library level values, such as index version
+const TABLE_META: TableDefinition<&str, u64> = TableDefinition::new("meta");
 
 pub(super) fn init_library(app: &AppHandle) -> Result<()> {
     let config = app.state_config().get();
-    let path_data = config.path_data();
-
-    fn create_db(path_data: &Path) -> Result<Database> {
-        let db_path = path_data.join(LIB_FILE_NAME);
-        Ok(Database::create(db_path).map_err(|e| anyhow!("Error creating the database: {}", e))?)
-    }
-
-    fn configure_db(db: Database) -> Result<Database> {
-        let write = db.begin_write()?;
-        write.open_table(TABLE_METADATA)?.get("TEST")?;
-        write.commit()?;
-        Ok(db)
-    }
 
-    fn backup_db(path_data: &Path) -> Result<()> {
-        let path_data = path_data.to_path_buf();
-
-        let backup_dir = path_data.join(DIR_BACKUP);
-        if !backup_dir.exists() {
-            fs::create_dir_all(&backup_dir)?;
-        }
-
-        let db_path = path_data.join(LIB_FILE_NAME);
-        if !db_path.exists() {
-            return Ok(());
-        }
-
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let backup_filename = format!("{}_{}.{}", LIB_FILE_STEM, timestamp, LIB_FILE_EXT);
-        let backup_path = backup_dir.join(&backup_filename);
-
-        fs::copy(&db_path, &backup_path)?;
-        info!("Database backup created at: {}", backup_path.display());
-
-        let pattern = format!("{}_{}.{}", LIB_FILE_STEM, "*", LIB_FILE_EXT);
-        let entries = fs::read_dir(&backup_dir)?
-            .filter_map(Result::ok)
-            .filter(|entry| {
-                if let Some(name) = entry.file_name().to_str() {
-                    return glob::Pattern::new(&pattern).unwrap().matches(name);
-                }
-                false
-            })
-            .collect::<Vec<_>>();
-
-        if entries.len() > 5 {
-            let mut entries_with_time = entries
-                .iter()
-                .filter_map(|entry| {
-                    entry
-                        .metadata()
-                        .ok()
-                        .and_then(|meta| meta.modified().ok().map(|time| (entry.path(), time)))
-                })
-                .collect::<Vec<_>>();
-
-            entries_with_time.sort_by_key(|(_, time)| *time);
-
-            for (path, _) in entries_with_time.iter().take(entries_with_time.len() - 5) {
-                if let Err(e) = fs::remove_file(path) {
-                    warn!("Failed to remove old backup file {}: {}", path.display(), e);
-                } else {
-                    info!("Removed old backup file: {}", path.display());
-                }
-            }
-        }
-
-        Ok(())
+    if config.backup().on_startup {
+        backup_file(&config, BackupTrigger::Startup)?;
     }
-
-    backup_db(path_data)?;
-    app.manage(DataState::new(configure_db(create_db(path_data)?)?));
+    app.manage(DataState::new(open_library(&config)?));
 
     collection_cache_sync_all(app.state_data())?;
     deployment_cache_sync_all(app.state_data())?;
+    backup_schedule(app);
 
     Ok(())
 }
 
-pub async fn export_library(app: AppHandle) -> Result<()> {
-    let path = app
-        .state_config()
-        .get()
-        .path_data()
-        .join(LIB_FILE_EXPORT_NAME);
-    let all = metadata_get_all(app.state_data()).await?;
-    let json = serde_json::to_string(&all)
-        .map_err(|e| anyhow!("Failed to serialize metadata to JSON: {}", e))?;
-
-    tfs::write(&path, json).await?;
-
-    info!("Library exported to {}", path.display());
-
-    Ok(())
-}
-
-pub async fn import_library(app: AppHandle) -> Result<()> {
-    let path = app
-        .state_config()
-        .get()
-        .path_data()
-        .join(LIB_FILE_EXPORT_NAME);
-    if !path.exists() {
-        return Err(anyhow!("Library export file not found: {}", path.display()));
+pub(super) fn exit_library(app: &AppHandle) {
+    let config = app.state_config().get();
+    if let Some(data) = app.try_state::<DataState>()
+        && config.backup().on_shutdown
+    {
+        let _ = backup_online(&data.database(), &config, BackupTrigger::Shutdown)
+            .inspect_err(|e| error!("Failed to back up database on shutdown: {}", e));
     }
+}
 
-    let json = tfs::read_to_string(&path)
-        .await
-        .map_err(|e| anyhow!("Failed to read library export file: {}", e))?;
-
-    let entries: Vec<Metadata> =
-        serde_json::from_str(&json).map_err(|e| anyhow!("Failed to deserialize JSON: {}", e))?;
-
-    if entries.is_empty() {
-        warn!(
-            "No entries found in the library export file: {}",
-            path.display()
-        );
-        return Ok(());
+/// Closes the library and opens the one of profile `name`, then makes it the
+/// active profile. The current library stays open if the other one fails.
+pub async fn library_switch(name: String, app: AppHandle) -> Result<AppConfig> {
+    let current = app.state_config().get();
+    if current.profile() == name {
+        return Ok(current);
     }
-
-    let db = app.state_data().database();
+    let target = current.profile_target(&name)?;
+    let handle = app.clone();
     async_runtime::spawn_blocking(move || {
-        let write = db.begin_write()?;
-        {
-            let mut table = write.open_table(TABLE_METADATA)?;
-            for entry in &entries {
-                let raw =
-                    bson::to_vec(entry).map_err(|e| anyhow!("Failed to serialize entry: {}", e))?;
-                table.insert(&*entry.id.to_string(), raw)?;
-            }
+        if current.backup().on_shutdown {
+            backup_online(
+                &handle.state_data().database(),
+                &current,
+                BackupTrigger::Shutdown,
+            )?;
         }
-        write.commit()?;
-        Ok::<(), anyhow::Error>(())
+        if target.backup().on_startup {
+            backup_file(&target, BackupTrigger::Startup)?;
+        }
+        handle
+            .state_data()
+            .database_reopen(|| open_library(&target), || open_library(&current))
+            .inspect_err(|e| error!("Failed to open library of profile '{}': {}", name, e))?;
+        handle.state_config().profile_activate(&name)
     })
     .await??;
 
     collection_cache_sync_all(app.state_data())?;
     deployment_cache_sync_all(app.state_data())?;
+    Ok(app.state_config().get())
+}
 
-    info!("Library imported from {}", path.display());
+/// Opens the library in the data directory, migrating and indexing it if
+/// needed.
+fn open_library(config: &AppConfig) -> Result<Database> {
+    let path_data = config.path_data();
 
-    Ok(())
+    fn create_db(path_data: &Path) -> Result<Database> {
+        let db_path = path_data.join(LIB_FILE_NAME);
+        Ok(Database::create(db_path).map_err(|e| anyhow!("Error creating the database: {}", e))?)
+    }
+
+    fn configure_db(db: Database) -> Result<Database> {
+        let write = db.begin_write()?;
+        write.open_table(TABLE_METADATA)?.get("TEST")?;
+        write.open_table(TABLE_META)?;
+        write.open_table(TABLE_HISTORY)?;
+        write.open_multimap_table(TABLE_HISTORY_ENTRY)?;
+        write.open_table(TABLE_TRASH)?;
+        write.open_table(TABLE_TAG)?;
+        write.open_table(TABLE_TAG_ALIAS)?;
+        write.open_table(TABLE_COLLECTION)?;
+        write.open_multimap_table(TABLE_RELATION)?;
+        write.open_table(TABLE_PLAY_SESSION)?;
+        write.commit()?;
+        Ok(db)
+    }
+
+    let mut db = configure_db(create_db(path_data)?)?;
+    if let Some(version) = migration_pending(&db)? {
+        drop(db);
+        if config.backup().before_migration {
+            // A migration that left entries behind is tried again on every
+            // start, the unmigrated library is only backed up the first time
+            let trigger = BackupTrigger::Migration(version);
+            if backup_exists(config, trigger)? {
+                info!("Keeping the backup taken before migrating from schema version {version}");
+            } else {
+                info!("Backing up library before migrating from schema version {version}");
+                backup_file(config, trigger)?;
+            }
+        }
+        db = create_db(path_data)?;
+        migration_run(&db)?;
+    }
+    index_check(&db)?;
+    collection_check(&db)?;
+    trash_purge_expired(&db, config.trash_retention_days())?;
+    history_prune(&db, config.history_limit())?;
+
+    Ok(db)
 }
@@ -0,0 +1,305 @@
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{Result, anyhow};
+use log::info;
+use redb::{MultimapTableDefinition, ReadableMultimapTable, ReadableTable, WriteTransaction};
+use serde::{Deserialize, Serialize};
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::core::data::{
+    library::{TABLE_METADATA, index::metadata_get_many},
+    metadata::MetadataSummary,
+    state::DataState,
+};
+
+/// Entry id to (kind, other entry id), every link is stored from both sides
+pub(super) const TABLE_RELATION: MultimapTableDefinition<&str, (&str, &str)> =
+    MultimapTableDefinition::new("relation");
+
+/// Default depth of [relation_graph]
+const DEFAULT_GRAPH_DEPTH: u32 = 3;
+
+/// Type of a link from one entry to another, read as "entry is `kind` other"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum RelationKind {
+    SequelOf,
+    PrequelOf,
+    DlcOf,
+    HasDlc,
+    PatchFor,
+    HasPatch,
+    TranslationOf,
+    HasTranslation,
+    RemakeOf,
+    HasRemake,
+    BundledWith,
+}
+
+impl RelationKind {
+    const ALL: [Self; 11] = [
+        Self::SequelOf,
+        Self::PrequelOf,
+        Self::DlcOf,
+        Self::HasDlc,
+        Self::PatchFor,
+        Self::HasPatch,
+        Self::TranslationOf,
+        Self::HasTranslation,
+        Self::RemakeOf,
+        Self::HasRemake,
+        Self::BundledWith,
+    ];
+
+    /// The kind stored on the other side of the link
+    fn inverse(self) -> Self {
+        match self {
+            Self::SequelOf => Self::PrequelOf,
+            Self::PrequelOf => Self::SequelOf,
+            Self::DlcOf => Self::HasDlc,
+            Self::HasDlc => Self::DlcOf,
+            Self::PatchFor => Self::HasPatch,
+            Self::HasPatch => Self::PatchFor,
+            Self::TranslationOf => Self::HasTranslation,
+            Self::HasTranslation => Self::TranslationOf,
+            Self::RemakeOf => Self::HasRemake,
+            Self::HasRemake => Self::RemakeOf,
+            Self::BundledWith => Self::BundledWith,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::SequelOf => "SequelOf",
+            Self::PrequelOf => "PrequelOf",
+            Self::DlcOf => "DlcOf",
+            Self::HasDlc => "HasDlc",
+            Self::PatchFor => "PatchFor",
+            Self::HasPatch => "HasPatch",
+            Self::TranslationOf => "TranslationOf",
+            Self::HasTranslation => "HasTranslation",
+            Self::RemakeOf => "RemakeOf",
+            Self::HasRemake => "HasRemake",
+            Self::BundledWith => "BundledWith",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct RelationEdge {
+    pub from: String,
+    pub kind: RelationKind,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct RelationGraph {
+    pub nodes: Vec<MetadataSummary>,
+    /// Each link once, as seen from the entry nearer to the root
+    pub edges: Vec<RelationEdge>,
+}
+
+fn relation_put(write: &WriteTransaction, from: &str, kind: RelationKind, to: &str) -> Result<()> {
+    let mut table = write.open_multimap_table(TABLE_RELATION)?;
+    table.insert(from, (kind.name(), to))?;
+    table.insert(to, (kind.inverse().name(), from))?;
+    Ok(())
+}
+
+fn relation_take(
+    write: &WriteTransaction,
+    from: &str,
+    kind: RelationKind,
+    to: &str,
+) -> Result<bool> {
+    let mut table = write.open_multimap_table(TABLE_RELATION)?;
+    let removed = table.remove(from, (kind.name(), to))?;
+    table.remove(to, (kind.inverse().name(), from))?;
+    Ok(removed)
+}
+
+/// Links of one entry as (kind, other id)
+fn relation_links(
+    table: &impl ReadableMultimapTable<&'static str, (&'static str, &'static str)>,
+    id: &str,
+) -> Result<Vec<(RelationKind, String)>> {
+    let mut links = Vec::new();
+    for value in table.get(id)? {
+        let value = value?;
+        let (kind, other) = value.value();
+        if let Some(kind) = RelationKind::from_name(kind) {
+            links.push((kind, other.to_string()));
+        }
+    }
+    Ok(links)
+}
+
+/// Removes every link of a deleted entry from both sides, called for every
+/// removal from [TABLE_METADATA].
+pub(super) fn relation_remove_all(write: &WriteTransaction, id: &str) -> Result<()> {
+    let links = relation_links(&write.open_multimap_table(TABLE_RELATION)?, id)?;
+    for (kind, other) in links {
+        relation_take(write, id, kind, &other)?;
+    }
+    Ok(())
+}
+
+/// Moves every link of `from` to `to`, used when merging entries.
+pub(super) fn relation_move(write: &WriteTransaction, from: &str, to: &str) -> Result<()> {
+    let links = relation_links(&write.open_multimap_table(TABLE_RELATION)?, from)?;
+    for (kind, other) in links {
+        relation_take(write, from, kind, &other)?;
+        if other != to {
+            relation_put(write, to, kind, &other)?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn relation_add(
+    from: String,
+    kind: RelationKind,
+    to: String,
+    data: State<'_, DataState>,
+) -> Result<()> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        if from == to {
+            return Err(anyhow!("An entry can not be related to itself"));
+        }
+        let write = db.begin_write()?;
+        {
+            let table = write.open_table(TABLE_METADATA)?;
+            for id in [&from, &to] {
+                if table.get(id.as_str())?.is_none() {
+                    return Err(anyhow!("Key '{}' not found in library", id));
+                }
+            }
+        }
+        relation_put(&write, &from, kind, &to)?;
+        write.commit()?;
+
+        info!("Linked '{}' as {:?} '{}'", from, kind, to);
+        Ok(())
+    })
+    .await?
+}
+
+pub async fn relation_remove(
+    from: String,
+    kind: RelationKind,
+    to: String,
+    data: State<'_, DataState>,
+) -> Result<bool> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let removed = relation_take(&write, &from, kind, &to)?;
+        write.commit()?;
+        Ok(removed)
+    })
+    .await?
+}
+
+/// Walks the links from `id` up to `depth` steps, defaults to
+/// [DEFAULT_GRAPH_DEPTH].
+pub async fn relation_graph(
+    id: String,
+    depth: Option<u32>,
+    data: State<'_, DataState>,
+) -> Result<RelationGraph> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let table = read.open_multimap_table(TABLE_RELATION)?;
+        let depth = depth.unwrap_or(DEFAULT_GRAPH_DEPTH);
+
+        let mut order = vec![id.clone()];
+        let mut seen = HashSet::from([id.clone()]);
+        let mut edges = Vec::new();
+        let mut queue = VecDeque::from([(id, 0)]);
+        while let Some((current, level)) = queue.pop_front() {
+            if level >= depth {
+                continue;
+            }
+            for (kind, other) in relation_links(&table, &current)? {
+                let edge = RelationEdge {
+                    from: current.clone(),
+                    kind,
+                    to: other.clone(),
+                };
+                let inverse = RelationEdge {
+                    from: other.clone(),
+                    kind: kind.inverse(),
+                    to: current.clone(),
+                };
+                if !edges.contains(&inverse) {
+                    edges.push(edge);
+                }
+                if seen.insert(other.clone()) {
+                    order.push(other.clone());
+                    queue.push_back((other, level + 1));
+                }
+            }
+        }
+
+        Ok(RelationGraph {
+            nodes: metadata_get_many(&read, order.iter().map(String::as_str))?
+                .iter()
+                .map(MetadataSummary::from)
+                .collect(),
+            edges,
+        })
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+
+    #[test]
+    fn test_relation_cleanup() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let write = db.begin_write().unwrap();
+        relation_put(&write, "dlc", RelationKind::DlcOf, "base").unwrap();
+        relation_put(&write, "patch", RelationKind::PatchFor, "base").unwrap();
+        relation_put(&write, "base", RelationKind::BundledWith, "other").unwrap();
+
+        let links = |id: &str| {
+            relation_links(&write.open_multimap_table(TABLE_RELATION).unwrap(), id).unwrap()
+        };
+        assert_eq!(
+            links("dlc"),
+            vec![(RelationKind::DlcOf, String::from("base"))]
+        );
+        assert_eq!(links("base").len(), 3);
+        assert_eq!(
+            links("other"),
+            vec![(RelationKind::BundledWith, String::from("base"))]
+        );
+
+        relation_move(&write, "base", "merged").unwrap();
+        assert!(links("base").is_empty());
+        assert_eq!(
+            links("dlc"),
+            vec![(RelationKind::DlcOf, String::from("merged"))]
+        );
+
+        relation_remove_all(&write, "merged").unwrap();
+        assert!(links("dlc").is_empty());
+        assert!(links("patch").is_empty());
+        assert!(links("other").is_empty());
+    }
+}
@@ -0,0 +1,500 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use redb::ReadableTable;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State, async_runtime};
+use ts_rs::TS;
+
+use crate::core::{
+    AppStateExt,
+    config::AppConfig,
+    data::{
+        library::{TABLE_METADATA, delegate::metadata_write, metadata_get},
+        metadata::{DeployInfo, Metadata, SaveEngine},
+        state::DataState,
+    },
+};
+
+/// Description of a snapshot, stored inside its directory
+const SNAPSHOT_MANIFEST: &str = "snapshot.json";
+/// Snapshot directory of the locations inside the deployment
+const SNAPSHOT_DEPLOY: &str = "deploy";
+/// Snapshot directory of absolute locations, one numbered directory each
+const SNAPSHOT_ABSOLUTE: &str = "absolute";
+
+/// What caused a snapshot, appended to its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum SaveTrigger {
+    Manual,
+    /// Before the deployment was removed
+    DeployOff,
+    /// Safety copy of the saves replaced by a restore
+    Restore,
+}
+
+impl SaveTrigger {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Manual => "manual",
+            Self::DeployOff => "deploy_off",
+            Self::Restore => "restore",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct SaveSnapshot {
+    /// Directory name, used to restore the snapshot
+    #[serde(default)]
+    pub name: String,
+    pub time: DateTime<Utc>,
+    pub trigger: SaveTrigger,
+    /// Locations at the time of the snapshot, in the order they are stored
+    pub locations: Vec<String>,
+    pub files: u32,
+    #[ts(type = "number")]
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct SaveLocations {
+    /// Engine detected in the deployment
+    pub engine: Option<SaveEngine>,
+    /// Configured locations, or the defaults of the engine
+    pub locations: Vec<String>,
+    /// Whether `locations` are the defaults of the engine
+    pub default: bool,
+    /// Save files and directories found at the locations
+    pub paths: Vec<String>,
+}
+
+/// Matches a file name against a pattern where `*` stands for any text
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+fn location_check(location: &str) -> Result<()> {
+    let path = Path::new(location);
+    let parent_has_wildcard = path
+        .parent()
+        .is_some_and(|p| p.to_string_lossy().contains('*'));
+    if location.trim().is_empty()
+        || path.components().any(|c| c == Component::ParentDir)
+        || parent_has_wildcard
+    {
+        return Err(anyhow!("Invalid save location '{}'", location));
+    }
+    Ok(())
+}
+
+fn deploy_dir(metadata: &Metadata) -> Option<&Path> {
+    match &metadata.deploy_info {
+        DeployInfo::Directory { path, .. } if path.is_dir() => Some(path.as_path()),
+        _ => None,
+    }
+}
+
+/// Configured locations, or the defaults of the engine found in the
+/// deployment. Returns the engine and whether the defaults are used.
+fn save_locations_of(metadata: &Metadata) -> (Option<SaveEngine>, Vec<String>, bool) {
+    let engine = deploy_dir(metadata).and_then(SaveEngine::detect);
+    if !metadata.save_info.locations.is_empty() {
+        return (engine, metadata.save_info.locations.clone(), false);
+    }
+    let defaults = engine
+        .map(|e| e.locations().iter().map(|l| l.to_string()).collect())
+        .unwrap_or_default();
+    (engine, defaults, true)
+}
+
+/// Directory a location is restored into and its pattern, `None` for a
+/// relative location without deployment.
+fn location_base(location: &str, deploy: Option<&Path>) -> Option<(PathBuf, String)> {
+    let path = Path::new(location);
+    let full = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        deploy?.join(path)
+    };
+    let pattern = full.file_name()?.to_string_lossy().to_string();
+    Some((full.parent()?.to_path_buf(), pattern))
+}
+
+/// Existing entries in `base` matching `pattern`
+fn location_matches(base: &Path, pattern: &str) -> Vec<PathBuf> {
+    if !pattern.contains('*') {
+        let path = base.join(pattern);
+        return if path.exists() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+    }
+    let Ok(entries) = fs::read_dir(base) else {
+        return Vec::new();
+    };
+    let mut matches = entries
+        .filter_map(Result::ok)
+        .filter(|e| wildcard_match(pattern, &e.file_name().to_string_lossy()))
+        .map(|e| e.path())
+        .collect::<Vec<_>>();
+    matches.sort();
+    matches
+}
+
+/// Where the entries of location `index` are kept inside a snapshot
+fn snapshot_root(snapshot: &Path, index: usize, location: &str) -> PathBuf {
+    let path = Path::new(location);
+    if path.is_absolute() {
+        snapshot.join(SNAPSHOT_ABSOLUTE).join(index.to_string())
+    } else {
+        let mut root = snapshot.join(SNAPSHOT_DEPLOY);
+        if let Some(parent) = path.parent() {
+            root.push(parent);
+        }
+        root
+    }
+}
+
+/// Copies a file or a directory tree, returns the copied files and bytes.
+fn copy_tree(from: &Path, to: &Path) -> Result<(u32, u64)> {
+    let (mut files, mut size) = (0, 0);
+    for entry in walkdir::WalkDir::new(from) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(from)?;
+        // Joining an empty path would append a separator to a file target
+        let target = if relative.as_os_str().is_empty() {
+            to.to_path_buf()
+        } else {
+            to.join(relative)
+        };
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            size += fs::copy(entry.path(), &target)?;
+            files += 1;
+        }
+    }
+    Ok((files, size))
+}
+
+/// Snapshots of an entry, newest first
+fn snapshot_list(config: &AppConfig, id: &str) -> Result<Vec<SaveSnapshot>> {
+    let dir = config.dir_save().join(id);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = fs::read_dir(&dir)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let manifest = fs::read_to_string(entry.path().join(SNAPSHOT_MANIFEST)).ok()?;
+            let mut snapshot = serde_json::from_str::<SaveSnapshot>(&manifest)
+                .inspect_err(|e| warn!("Invalid snapshot '{}': {}", entry.path().display(), e))
+                .ok()?;
+            snapshot.name = entry.file_name().to_string_lossy().to_string();
+            Some(snapshot)
+        })
+        .collect::<Vec<_>>();
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.time));
+    Ok(snapshots)
+}
+
+/// Removes the oldest snapshots of an entry above the retention count.
+fn snapshot_prune(config: &AppConfig, id: &str) -> Result<()> {
+    let retention = config.save_retention() as usize;
+    if retention == 0 {
+        return Ok(());
+    }
+    for snapshot in snapshot_list(config, id)?.iter().skip(retention) {
+        let path = config.dir_save().join(id).join(&snapshot.name);
+        match fs::remove_dir_all(&path) {
+            Ok(()) => info!("Removed old save snapshot: {}", path.display()),
+            Err(e) => warn!("Failed to remove '{}': {}", path.display(), e),
+        }
+    }
+    Ok(())
+}
+
+/// Copies the saves of an entry into a new snapshot, returns `None` if no save
+/// was found.
+pub(super) fn snapshot_take(
+    config: &AppConfig,
+    metadata: &Metadata,
+    trigger: SaveTrigger,
+) -> Result<Option<SaveSnapshot>> {
+    let (_, locations, _) = save_locations_of(metadata);
+    let deploy = deploy_dir(metadata);
+    let found = locations
+        .iter()
+        .map(|l| location_base(l, deploy).map(|(base, pattern)| location_matches(&base, &pattern)))
+        .collect::<Vec<_>>();
+    if found.iter().flatten().all(|matches| matches.is_empty()) {
+        return Ok(None);
+    }
+
+    let id = metadata.id.to_string();
+    let name = format!(
+        "{}_{}",
+        chrono::Local::now().format("%Y%m%d_%H%M%S%3f"),
+        trigger.name()
+    );
+    let dir = config.dir_save().join(&id).join(&name);
+    fs::create_dir_all(&dir)?;
+    let mut snapshot = SaveSnapshot {
+        name,
+        time: Utc::now(),
+        trigger,
+        locations: locations.clone(),
+        files: 0,
+        size: 0,
+    };
+    for (index, (location, matches)) in locations.iter().zip(found).enumerate() {
+        let root = snapshot_root(&dir, index, location);
+        for path in matches.into_iter().flatten() {
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            let (files, size) = copy_tree(&path, &root.join(name))?;
+            snapshot.files += files;
+            snapshot.size += size;
+        }
+    }
+    fs::write(
+        dir.join(SNAPSHOT_MANIFEST),
+        serde_json::to_string_pretty(&snapshot)?,
+    )?;
+    info!(
+        "Saved {} save files of '{}' to {}",
+        snapshot.files,
+        metadata.title,
+        dir.display()
+    );
+    snapshot_prune(config, &id)?;
+    Ok(Some(snapshot))
+}
+
+/// Copies the saves of a snapshot back, replacing the matching files and
+/// directories. Relative locations are restored into the current deployment.
+fn snapshot_restore(dir: &Path, snapshot: &SaveSnapshot, deploy: Option<&Path>) -> Result<u32> {
+    let mut restored = 0;
+    for (index, location) in snapshot.locations.iter().enumerate() {
+        let root = snapshot_root(dir, index, location);
+        if !root.is_dir() {
+            continue;
+        }
+        let (base, pattern) = location_base(location, deploy).ok_or_else(|| {
+            anyhow!(
+                "Save location '{}' needs the entry to be deployed",
+                location
+            )
+        })?;
+        for stored in fs::read_dir(&root)?.filter_map(Result::ok) {
+            let name = stored.file_name();
+            if !wildcard_match(&pattern, &name.to_string_lossy()) {
+                continue;
+            }
+            let target = base.join(&name);
+            if target.is_dir() {
+                fs::remove_dir_all(&target)?;
+            }
+            restored += copy_tree(&stored.path(), &target)?.0;
+        }
+    }
+    Ok(restored)
+}
+
+/// Locations of an entry and the saves found there
+pub async fn save_locations_get(key: String, data: State<'_, DataState>) -> Result<SaveLocations> {
+    let metadata = metadata_get(key.clone(), data)
+        .await?
+        .ok_or_else(|| anyhow!("Key '{}' not found in library", key))?;
+    async_runtime::spawn_blocking(move || {
+        let (engine, locations, default) = save_locations_of(&metadata);
+        let deploy = deploy_dir(&metadata);
+        let paths = locations
+            .iter()
+            .filter_map(|l| location_base(l, deploy))
+            .flat_map(|(base, pattern)| location_matches(&base, &pattern))
+            .map(|p| p.display().to_string())
+            .collect();
+        Ok(SaveLocations {
+            engine,
+            locations,
+            default,
+            paths,
+        })
+    })
+    .await?
+}
+
+/// Sets the save locations of an entry, an empty list uses the defaults of the
+/// detected engine again.
+pub async fn save_locations_set(
+    key: String,
+    locations: Vec<String>,
+    data: State<'_, DataState>,
+) -> Result<()> {
+    for location in &locations {
+        location_check(location)?;
+    }
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let existing = {
+            let table = write.open_table(TABLE_METADATA)?;
+            let value = table.get(key.as_str())?;
+            value
+                .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+                .transpose()?
+        };
+        let mut metadata = existing.ok_or_else(|| anyhow!("Key '{}' not found in library", key))?;
+        metadata.save_info.locations = locations;
+        metadata.mark_update();
+        metadata_write(&write, &key, Some(&metadata))?;
+        write.commit()?;
+        Ok(())
+    })
+    .await?
+}
+
+pub async fn save_snapshot(key: String, app: AppHandle) -> Result<SaveSnapshot> {
+    let config = app.state_config().get();
+    let metadata = metadata_get(key.clone(), app.state_data())
+        .await?
+        .ok_or_else(|| anyhow!("Key '{}' not found in library", key))?;
+    async_runtime::spawn_blocking(move || {
+        snapshot_take(&config, &metadata, SaveTrigger::Manual)?
+            .ok_or_else(|| anyhow!("No saves found for '{}'", metadata.title))
+    })
+    .await?
+}
+
+/// Snapshots of an entry, newest first.
+pub async fn save_snapshots(key: String, app: AppHandle) -> Result<Vec<SaveSnapshot>> {
+    let config = app.state_config().get();
+    async_runtime::spawn_blocking(move || snapshot_list(&config, &key)).await?
+}
+
+/// Restores a snapshot from [save_snapshots]. The current saves are
+/// snapshotted first.
+pub async fn save_restore(key: String, name: String, app: AppHandle) -> Result<()> {
+    let config = app.state_config().get();
+    let metadata = metadata_get(key.clone(), app.state_data())
+        .await?
+        .ok_or_else(|| anyhow!("Key '{}' not found in library", key))?;
+    async_runtime::spawn_blocking(move || {
+        let snapshot = snapshot_list(&config, &key)?
+            .into_iter()
+            .find(|s| s.name == name)
+            .ok_or_else(|| anyhow!("Save snapshot '{}' not found", name))?;
+        let dir = config.dir_save().join(&key).join(&name);
+        snapshot_take(&config, &metadata, SaveTrigger::Restore)?;
+        let restored = snapshot_restore(&dir, &snapshot, deploy_dir(&metadata))?;
+        info!(
+            "Restored {} save files of '{}' from '{}'",
+            restored, metadata.title, name
+        );
+        Ok(())
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("Save*.rvdata2", "Save01.rvdata2"));
+        assert!(wildcard_match("save", "save"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("a*b*c", "aXbYc"));
+        assert!(!wildcard_match("Save*.rvdata2", "Save01.rvdata"));
+        assert!(!wildcard_match("a*a", "a"));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let root = std::env::temp_dir().join(format!("save_{}", Uuid::new_v4()));
+        let deploy = root.join("deploy");
+        let external = root.join("external");
+        fs::create_dir_all(deploy.join("www/save")).unwrap();
+        fs::create_dir_all(&external).unwrap();
+        fs::write(deploy.join("www/save/file1.rpgsave"), "one").unwrap();
+        fs::write(deploy.join("Save01.rvdata2"), "slot").unwrap();
+        fs::write(deploy.join("Game.rvdata2"), "game").unwrap();
+        fs::write(external.join("config.ini"), "config").unwrap();
+
+        let snapshot = SaveSnapshot {
+            name: String::new(),
+            time: Utc::now(),
+            trigger: SaveTrigger::Manual,
+            locations: vec![
+                String::from("www/save"),
+                String::from("Save*.rvdata2"),
+                external.join("config.ini").display().to_string(),
+            ],
+            files: 0,
+            size: 0,
+        };
+        let dir = root.join("snapshot");
+        for (index, location) in snapshot.locations.iter().enumerate() {
+            let (base, pattern) = location_base(location, Some(&deploy)).unwrap();
+            for path in location_matches(&base, &pattern) {
+                let target = snapshot_root(&dir, index, location).join(path.file_name().unwrap());
+                copy_tree(&path, &target).unwrap();
+            }
+        }
+        assert!(dir.join("deploy/www/save/file1.rpgsave").is_file());
+        assert!(dir.join("deploy/Save01.rvdata2").is_file());
+        assert!(!dir.join("deploy/Game.rvdata2").exists());
+        assert!(dir.join("absolute/2/config.ini").is_file());
+
+        fs::remove_dir_all(&deploy).unwrap();
+        fs::create_dir_all(deploy.join("www/save")).unwrap();
+        fs::write(deploy.join("www/save/file2.rpgsave"), "two").unwrap();
+        fs::write(external.join("config.ini"), "changed").unwrap();
+
+        let restored = snapshot_restore(&dir, &snapshot, Some(&deploy)).unwrap();
+        assert_eq!(restored, 3);
+        assert!(deploy.join("www/save/file1.rpgsave").is_file());
+        assert!(!deploy.join("www/save/file2.rpgsave").exists());
+        assert!(deploy.join("Save01.rvdata2").is_file());
+        assert_eq!(
+            fs::read_to_string(external.join("config.ini")).unwrap(),
+            "config"
+        );
+        assert!(snapshot_restore(&dir, &snapshot, None).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
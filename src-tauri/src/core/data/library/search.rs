@@ -0,0 +1,327 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use log::debug;
+use redb::{
+    ReadTransaction, ReadableTable, ReadableTableMetadata, TableDefinition, WriteTransaction,
+};
+use serde::Serialize;
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::core::{
+    data::{
+        library::{TABLE_META, index::metadata_get_many},
+        metadata::{GameDistribution, Metadata},
+        state::DataState,
+    },
+    util::text::tokenize,
+};
+
+/// (term, id) to the weighted term frequency
+pub(super) const TABLE_SEARCH_POSTING: TableDefinition<(&str, &str), u32> =
+    TableDefinition::new("search_posting");
+/// id to the weighted length of the document
+pub(super) const TABLE_SEARCH_DOC: TableDefinition<&str, u32> = TableDefinition::new("search_doc");
+
+const META_SEARCH_LEN_TOTAL: &str = "search_len_total";
+
+const WEIGHT_TITLE: u32 = 3;
+const WEIGHT_ALIAS: u32 = 3;
+const WEIGHT_ID: u32 = 3;
+const WEIGHT_TAG: u32 = 2;
+const WEIGHT_CREATOR: u32 = 2;
+const WEIGHT_DESCRIPTION: u32 = 1;
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct SearchResult {
+    pub score: f64,
+    pub metadata: Metadata,
+}
+
+/// Collects the weighted terms of all searchable fields.
+fn search_terms(metadata: &Metadata) -> HashMap<String, u32> {
+    let mut terms = HashMap::new();
+    let mut add = |text: &str, weight: u32| {
+        for term in tokenize(text) {
+            *terms.entry(term).or_insert(0) += weight;
+        }
+    };
+
+    add(&metadata.title, WEIGHT_TITLE);
+    for alias in &metadata.alias {
+        add(alias, WEIGHT_ALIAS);
+    }
+    for tag in &metadata.tags {
+        add(tag, WEIGHT_TAG);
+    }
+    if let Some(description) = &metadata.description {
+        add(description, WEIGHT_DESCRIPTION);
+    }
+    for creator in metadata.content_info.creators() {
+        add(creator, WEIGHT_CREATOR);
+    }
+    if let Some(distribution) = metadata.content_info.distribution() {
+        match distribution {
+            GameDistribution::Unknown => {},
+            GameDistribution::Steam(data) => add(&data.app_id, WEIGHT_ID),
+            GameDistribution::DLSite(data) => {
+                add(&data.id, WEIGHT_ID);
+                add(&data.full_id(), WEIGHT_ID);
+            },
+            GameDistribution::Other(data) => add(&data.id, WEIGHT_ID),
+        }
+    }
+
+    terms
+}
+
+/// Removes the postings of `old` and adds the ones of `new`, called from
+/// [super::index::index_sync].
+pub(super) fn search_sync(
+    write: &WriteTransaction,
+    old: Option<&Metadata>,
+    new: Option<&Metadata>,
+) -> Result<()> {
+    let mut posting = write.open_table(TABLE_SEARCH_POSTING)?;
+    let mut doc = write.open_table(TABLE_SEARCH_DOC)?;
+    let mut meta = write.open_table(TABLE_META)?;
+    let mut len_total = meta
+        .get(META_SEARCH_LEN_TOTAL)?
+        .map(|v| v.value())
+        .unwrap_or_default();
+
+    if let Some(old) = old {
+        let id = old.id.to_string();
+        for term in search_terms(old).keys() {
+            posting.remove((term.as_str(), id.as_str()))?;
+        }
+        if let Some(len) = doc.remove(id.as_str())? {
+            len_total = len_total.saturating_sub(len.value() as u64);
+        }
+    }
+
+    if let Some(new) = new {
+        let id = new.id.to_string();
+        let terms = search_terms(new);
+        let len = terms.values().sum::<u32>();
+        for (term, tf) in &terms {
+            posting.insert((term.as_str(), id.as_str()), tf)?;
+        }
+        doc.insert(id.as_str(), len)?;
+        len_total += len as u64;
+    }
+
+    meta.insert(META_SEARCH_LEN_TOTAL, len_total)?;
+    Ok(())
+}
+
+pub(super) fn search_clear(write: &WriteTransaction) -> Result<()> {
+    write.delete_table(TABLE_SEARCH_POSTING)?;
+    write.delete_table(TABLE_SEARCH_DOC)?;
+    write
+        .open_table(TABLE_META)?
+        .remove(META_SEARCH_LEN_TOTAL)?;
+    Ok(())
+}
+
+/// Ranks the entries against `query` with BM25.
+///
+/// Entries containing every query term are returned first, if there is none
+/// the entries containing any of them are returned instead.
+pub async fn metadata_search(
+    query: String,
+    limit: Option<u32>,
+    data: State<'_, DataState>,
+) -> Result<Vec<SearchResult>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || search_rank(&db.begin_read()?, &query, limit)).await?
+}
+
+fn search_rank(
+    read: &ReadTransaction,
+    query: &str,
+    limit: Option<u32>,
+) -> Result<Vec<SearchResult>> {
+    let query_terms = tokenize(query).into_iter().collect::<HashSet<_>>();
+    if query_terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let posting = read.open_table(TABLE_SEARCH_POSTING)?;
+    let doc = read.open_table(TABLE_SEARCH_DOC)?;
+    let doc_count = doc.len()?;
+    if doc_count == 0 {
+        return Ok(Vec::new());
+    }
+    let len_avg = read
+        .open_table(TABLE_META)?
+        .get(META_SEARCH_LEN_TOTAL)?
+        .map(|v| v.value())
+        .unwrap_or_default() as f64
+        / doc_count as f64;
+
+    // id to (score, matched term count)
+    let mut scores = HashMap::<String, (f64, usize)>::new();
+    for term in &query_terms {
+        let mut matched = Vec::new();
+        for entry in posting.range((term.as_str(), "")..)? {
+            let (key, tf) = entry?;
+            let (t, id) = key.value();
+            if t != term {
+                break;
+            }
+            matched.push((id.to_string(), tf.value()));
+        }
+
+        let df = matched.len() as f64;
+        let idf = (1.0 + (doc_count as f64 - df + 0.5) / (df + 0.5)).ln();
+        for (id, tf) in matched {
+            let len = doc.get(id.as_str())?.map(|v| v.value()).unwrap_or_default() as f64;
+            let tf = tf as f64;
+            let score = idf * tf * (BM25_K1 + 1.0)
+                / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * len / len_avg.max(1.0)));
+            let entry = scores.entry(id).or_insert((0.0, 0));
+            entry.0 += score;
+            entry.1 += 1;
+        }
+    }
+
+    let mut ranked = if scores.values().any(|(_, c)| *c == query_terms.len()) {
+        scores
+            .into_iter()
+            .filter(|(_, (_, c))| *c == query_terms.len())
+            .map(|(id, (score, _))| (id, score))
+            .collect::<Vec<_>>()
+    } else {
+        scores
+            .into_iter()
+            .map(|(id, (score, _))| (id, score))
+            .collect::<Vec<_>>()
+    };
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    if let Some(limit) = limit {
+        ranked.truncate(limit as usize);
+    }
+    debug!("Search '{}' matched {} entries", query, ranked.len());
+
+    let scores = ranked.iter().cloned().collect::<HashMap<_, _>>();
+    Ok(
+        metadata_get_many(read, ranked.iter().map(|(id, _)| id.as_str()))?
+            .into_iter()
+            .map(|metadata| SearchResult {
+                score: scores
+                    .get(&metadata.id.to_string())
+                    .copied()
+                    .unwrap_or_default(),
+                metadata,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+    use crate::core::data::library::delegate::metadata_write;
+
+    fn metadata(title: &str, description: &str) -> Metadata {
+        Metadata {
+            title: title.to_string(),
+            description: Some(description.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn db_with(entries: &[&Metadata]) -> Database {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let write = db.begin_write().unwrap();
+        for entry in entries {
+            metadata_write(&write, &entry.id.to_string(), Some(entry)).unwrap();
+        }
+        write.commit().unwrap();
+        db
+    }
+
+    fn titles(db: &Database, query: &str) -> Vec<String> {
+        search_rank(&db.begin_read().unwrap(), query, None)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.metadata.title)
+            .collect()
+    }
+
+    #[test]
+    fn test_search_rank() {
+        let title = metadata("Dragon Quest", "An adventure");
+        let description = metadata("Adventure", "A dragon appears in a long story");
+        let both = metadata("Dragon Knight", "Knight of the dragon");
+        let other = metadata("Farm", "Quiet life");
+        let db = db_with(&[&title, &description, &both, &other]);
+
+        // Term frequency and field weight decide the order
+        assert_eq!(
+            titles(&db, "dragon"),
+            vec!["Dragon Knight", "Dragon Quest", "Adventure"]
+        );
+        // Entries with every term come first, and only them
+        assert_eq!(titles(&db, "dragon quest"), vec!["Dragon Quest"]);
+        // Without such entries any term matches
+        assert_eq!(titles(&db, "quest farm").len(), 2);
+        assert!(titles(&db, "missing").is_empty());
+
+        let limited = search_rank(&db.begin_read().unwrap(), "dragon", Some(1)).unwrap();
+        assert_eq!(limited.len(), 1);
+        assert!(limited[0].score > 0.0);
+    }
+
+    #[test]
+    fn test_search_cjk() {
+        let entry = metadata("魔法少女の冒険", "");
+        let db = db_with(&[&entry, &metadata("少年", "")]);
+
+        assert_eq!(titles(&db, "法少"), vec!["魔法少女の冒険"]);
+        assert_eq!(titles(&db, "魔法少女"), vec!["魔法少女の冒険"]);
+        // Both share the unigram, only the bigram tells them apart
+        assert_eq!(titles(&db, "少女"), vec!["魔法少女の冒険"]);
+        assert_eq!(titles(&db, "少").len(), 2);
+    }
+
+    #[test]
+    fn test_search_sync() {
+        let mut entry = metadata("Alpha", "");
+        let key = entry.id.to_string();
+        let db = db_with(&[&entry]);
+        assert_eq!(titles(&db, "alpha"), vec!["Alpha"]);
+
+        entry.title = String::from("Beta");
+        let write = db.begin_write().unwrap();
+        metadata_write(&write, &key, Some(&entry)).unwrap();
+        write.commit().unwrap();
+        assert!(titles(&db, "alpha").is_empty());
+        assert_eq!(titles(&db, "beta"), vec!["Beta"]);
+
+        let write = db.begin_write().unwrap();
+        metadata_write(&write, &key, None).unwrap();
+        write.commit().unwrap();
+        assert!(titles(&db, "beta").is_empty());
+        let read = db.begin_read().unwrap();
+        assert_eq!(read.open_table(TABLE_SEARCH_DOC).unwrap().len().unwrap(), 0);
+        assert_eq!(
+            read.open_table(TABLE_META)
+                .unwrap()
+                .get(META_SEARCH_LEN_TOTAL)
+                .unwrap()
+                .map(|v| v.value()),
+            Some(0)
+        );
+    }
+}
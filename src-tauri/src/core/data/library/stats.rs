@@ -0,0 +1,251 @@
+use anyhow::Result;
+use redb::{
+    MultimapTableDefinition, ReadTransaction, ReadableMultimapTable, ReadableTable,
+    TableDefinition, WriteTransaction,
+};
+use serde::Serialize;
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::core::data::{
+    library::index::{TABLE_INDEX_CONTENT_TYPE, TABLE_INDEX_RATING, TABLE_INDEX_TAG},
+    metadata::{ContentInfo, DeployInfo, Metadata},
+    state::DataState,
+};
+
+/// Aggregated counters by group and key, kept up to date by [stats_sync]
+pub(super) const TABLE_INDEX_STATS: TableDefinition<(&str, &str), u64> =
+    TableDefinition::new("index_stats");
+
+const GROUP_DISTRIBUTION: &str = "distribution";
+const GROUP_GAME_TYPE: &str = "game_type";
+const GROUP_SIZE: &str = "size";
+const GROUP_COLLECTION_SIZE: &str = "collection_size";
+const GROUP_CREATED: &str = "created";
+const KEY_TOTAL: &str = "total";
+const KEY_DEPLOYED: &str = "deployed";
+const KEY_DEPLOYED_COUNT: &str = "deployed_count";
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct StatCount {
+    pub key: String,
+    #[ts(type = "number")]
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct LibraryStats {
+    #[ts(type = "number")]
+    pub total: u64,
+    pub content_type: Vec<StatCount>,
+    /// Games by store
+    pub distribution: Vec<StatCount>,
+    pub game_type: Vec<StatCount>,
+    pub rating: Vec<StatCount>,
+    /// Most used first
+    pub tags: Vec<StatCount>,
+    /// Bytes of all archives
+    #[ts(type = "number")]
+    pub archive_size: u64,
+    /// Archive bytes by collection, largest first
+    pub collection_size: Vec<StatCount>,
+    #[ts(type = "number")]
+    pub deployed: u64,
+    /// Archive bytes of deployed entries
+    #[ts(type = "number")]
+    pub deployed_size: u64,
+    /// Entries created by month as `YYYY-MM`, oldest first
+    pub growth: Vec<StatCount>,
+}
+
+/// Counter changes of one entry, as (group, key, value)
+fn stats_of(metadata: &Metadata) -> Vec<(&'static str, String, u64)> {
+    let size = metadata.archive_size();
+    let mut stats = vec![
+        (GROUP_SIZE, KEY_TOTAL.to_string(), size),
+        (
+            GROUP_CREATED,
+            metadata.create_time.format("%Y-%m").to_string(),
+            1,
+        ),
+    ];
+    if let Some(distribution) = metadata.content_info.distribution() {
+        stats.push((GROUP_DISTRIBUTION, distribution.type_name().to_string(), 1));
+    }
+    if let ContentInfo::Game(data) = &metadata.content_info {
+        stats.push((GROUP_GAME_TYPE, format!("{:?}", data.game_type), 1));
+    }
+    if let Some(collection) = &metadata.collection {
+        stats.push((GROUP_COLLECTION_SIZE, collection.clone(), size));
+    }
+    if metadata.deploy_info != DeployInfo::None {
+        stats.push((GROUP_SIZE, KEY_DEPLOYED.to_string(), size));
+        stats.push((GROUP_SIZE, KEY_DEPLOYED_COUNT.to_string(), 1));
+    }
+    stats
+}
+
+/// Subtracts the counters of `old` and adds the ones of `new`, called from
+/// [super::index::index_sync].
+pub(super) fn stats_sync(
+    write: &WriteTransaction,
+    old: Option<&Metadata>,
+    new: Option<&Metadata>,
+) -> Result<()> {
+    let mut table = write.open_table(TABLE_INDEX_STATS)?;
+    for (group, key, value) in old.map(stats_of).unwrap_or_default() {
+        let current = table
+            .get((group, key.as_str()))?
+            .map(|v| v.value())
+            .unwrap_or_default();
+        let next = current.saturating_sub(value);
+        if next == 0 {
+            table.remove((group, key.as_str()))?;
+        } else {
+            table.insert((group, key.as_str()), next)?;
+        }
+    }
+    for (group, key, value) in new.map(stats_of).unwrap_or_default() {
+        let current = table
+            .get((group, key.as_str()))?
+            .map(|v| v.value())
+            .unwrap_or_default();
+        if current + value > 0 {
+            table.insert((group, key.as_str()), current + value)?;
+        }
+    }
+    Ok(())
+}
+
+fn stats_group(read: &ReadTransaction, group: &str) -> Result<Vec<StatCount>> {
+    let table = read.open_table(TABLE_INDEX_STATS)?;
+    let mut counts = Vec::new();
+    for entry in table.range((group, "")..)? {
+        let (k, v) = entry?;
+        let (g, key) = k.value();
+        if g != group {
+            break;
+        }
+        counts.push(StatCount {
+            key: key.to_string(),
+            value: v.value(),
+        });
+    }
+    Ok(counts)
+}
+
+fn stats_value(read: &ReadTransaction, group: &str, key: &str) -> Result<u64> {
+    let table = read.open_table(TABLE_INDEX_STATS)?;
+    Ok(table
+        .get((group, key))?
+        .map(|v| v.value())
+        .unwrap_or_default())
+}
+
+fn stats_index<K: redb::Key + 'static>(
+    read: &ReadTransaction,
+    def: MultimapTableDefinition<K, &str>,
+    name: impl Fn(K::SelfType<'_>) -> String,
+) -> Result<Vec<StatCount>> {
+    let table = read.open_multimap_table(def)?;
+    let mut counts = Vec::new();
+    for entry in table.iter()? {
+        let (k, ids) = entry?;
+        counts.push(StatCount {
+            key: name(k.value()),
+            value: ids.len(),
+        });
+    }
+    Ok(counts)
+}
+
+/// Reads the library statistics from the counters and index tables without
+/// touching the entries themselves.
+pub async fn stats_get(data: State<'_, DataState>) -> Result<LibraryStats> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let content_type = stats_index(&read, TABLE_INDEX_CONTENT_TYPE, |k| k.to_string())?;
+        let mut tags = stats_index(&read, TABLE_INDEX_TAG, |k| k.to_string())?;
+        tags.sort_by_key(|c| std::cmp::Reverse(c.value));
+        let mut collection_size = stats_group(&read, GROUP_COLLECTION_SIZE)?;
+        collection_size.sort_by_key(|c| std::cmp::Reverse(c.value));
+
+        Ok(LibraryStats {
+            total: content_type.iter().map(|c| c.value).sum(),
+            content_type,
+            distribution: stats_group(&read, GROUP_DISTRIBUTION)?,
+            game_type: stats_group(&read, GROUP_GAME_TYPE)?,
+            rating: stats_index(&read, TABLE_INDEX_RATING, |k| k.to_string())?,
+            tags,
+            archive_size: stats_value(&read, GROUP_SIZE, KEY_TOTAL)?,
+            collection_size,
+            deployed: stats_value(&read, GROUP_SIZE, KEY_DEPLOYED_COUNT)?,
+            deployed_size: stats_value(&read, GROUP_SIZE, KEY_DEPLOYED)?,
+            growth: stats_group(&read, GROUP_CREATED)?,
+        })
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+    use crate::core::data::{
+        library::index::index_sync,
+        metadata::{ArchiveInfo, ArchiveVersion},
+    };
+
+    fn metadata(collection: Option<&str>, size: u64) -> Metadata {
+        Metadata {
+            collection: collection.map(str::to_string),
+            archives: vec![ArchiveVersion {
+                version: String::from("1.0"),
+                archive: ArchiveInfo::Directory {
+                    size,
+                    path: String::new(),
+                },
+                add_time: Utc::now(),
+                notes: None,
+                pinned: false,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stats_sync() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let a = metadata(Some("Series"), 100);
+        let b = metadata(Some("Series"), 50);
+        let write = db.begin_write().unwrap();
+        index_sync(&write, None, Some(&a)).unwrap();
+        index_sync(&write, None, Some(&b)).unwrap();
+
+        let mut deployed = b.clone();
+        deployed.collection = None;
+        deployed.deploy_info = DeployInfo::Directory {
+            path: Default::default(),
+            version: None,
+        };
+        index_sync(&write, Some(&b), Some(&deployed)).unwrap();
+        write.commit().unwrap();
+
+        let read = db.begin_read().unwrap();
+        assert_eq!(stats_value(&read, GROUP_SIZE, KEY_TOTAL).unwrap(), 150);
+        assert_eq!(stats_value(&read, GROUP_SIZE, KEY_DEPLOYED).unwrap(), 50);
+        let collection = stats_group(&read, GROUP_COLLECTION_SIZE).unwrap();
+        assert_eq!(collection.len(), 1);
+        assert_eq!(collection[0].value, 100);
+        let growth = stats_group(&read, GROUP_CREATED).unwrap();
+        assert_eq!(growth[0].value, 2);
+        assert!(stats_group(&read, GROUP_DISTRIBUTION).unwrap().is_empty());
+    }
+}
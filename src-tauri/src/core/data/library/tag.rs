@@ -0,0 +1,458 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{Result, anyhow};
+use log::info;
+use redb::{ReadableMultimapTable, ReadableTable, TableDefinition, WriteTransaction};
+use serde::{Deserialize, Serialize};
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::core::{
+    data::{
+        library::{
+            TABLE_METADATA,
+            delegate::metadata_write,
+            index::{TABLE_INDEX_TAG, index_keys},
+        },
+        metadata::Metadata,
+        state::DataState,
+    },
+    util::text::normalize,
+};
+
+/// Canonical tag name to BSON encoded [TagRecord]
+pub(super) const TABLE_TAG: TableDefinition<&str, Vec<u8>> = TableDefinition::new("tag");
+/// Normalized names and aliases to the canonical tag name
+pub(super) const TABLE_TAG_ALIAS: TableDefinition<&str, &str> = TableDefinition::new("tag_alias");
+
+/// A registered tag, entries using one of its aliases are written with the
+/// canonical name instead
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct TagRecord {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct TagInfo {
+    pub tag: TagRecord,
+    /// Whether the tag is in the registry or only used by entries
+    pub registered: bool,
+    /// Entries using the tag
+    #[ts(type = "number")]
+    pub count: u64,
+}
+
+fn alias_key(name: &str) -> String {
+    normalize(name.trim())
+}
+
+impl TagRecord {
+    fn alias_keys(&self) -> HashSet<String> {
+        std::iter::once(&self.name)
+            .chain(&self.aliases)
+            .map(|n| alias_key(n))
+            .collect()
+    }
+}
+
+fn tag_get(write: &WriteTransaction, name: &str) -> Result<Option<TagRecord>> {
+    let table = write.open_table(TABLE_TAG)?;
+    let value = table.get(name)?;
+    Ok(value
+        .map(|v| bson::from_slice::<TagRecord>(v.value().as_slice()))
+        .transpose()?)
+}
+
+fn tag_get_all(write: &WriteTransaction) -> Result<Vec<TagRecord>> {
+    let table = write.open_table(TABLE_TAG)?;
+    let mut records = Vec::new();
+    for entry in table.iter()? {
+        records.push(bson::from_slice::<TagRecord>(entry?.1.value().as_slice())?);
+    }
+    Ok(records)
+}
+
+/// Writes or removes a registry record together with its alias keys. Fails if
+/// an alias already belongs to another tag.
+fn tag_put(
+    write: &WriteTransaction,
+    old: Option<&TagRecord>,
+    new: Option<&TagRecord>,
+) -> Result<()> {
+    let mut table = write.open_table(TABLE_TAG)?;
+    let mut alias = write.open_table(TABLE_TAG_ALIAS)?;
+
+    if let Some(old) = old {
+        table.remove(old.name.as_str())?;
+        for key in old.alias_keys() {
+            alias.remove(key.as_str())?;
+        }
+    }
+    if let Some(new) = new {
+        let keys = new.alias_keys();
+        for key in &keys {
+            if let Some(owner) = alias.get(key.as_str())?
+                && owner.value() != new.name
+            {
+                return Err(anyhow!(
+                    "Alias '{}' already belongs to tag '{}'",
+                    key,
+                    owner.value()
+                ));
+            }
+        }
+        for key in &keys {
+            alias.insert(key.as_str(), new.name.as_str())?;
+        }
+        table.insert(new.name.as_str(), bson::to_vec(new)?)?;
+    }
+    Ok(())
+}
+
+/// Replaces tags with their canonical names, returns `None` if nothing
+/// changed. Called for every write of [TABLE_METADATA].
+pub(super) fn tag_canonical(
+    write: &WriteTransaction,
+    tags: &[String],
+) -> Result<Option<Vec<String>>> {
+    let alias = write.open_table(TABLE_TAG_ALIAS)?;
+    let mut result = Vec::with_capacity(tags.len());
+    let mut changed = false;
+    for tag in tags {
+        let canonical = match alias.get(alias_key(tag).as_str())? {
+            Some(name) if name.value() != tag => {
+                changed = true;
+                name.value().to_string()
+            },
+            _ => tag.clone(),
+        };
+        if result.contains(&canonical) {
+            changed = true;
+        } else {
+            result.push(canonical);
+        }
+    }
+    Ok(changed.then_some(result))
+}
+
+/// Replaces every tag in `from` with `to` on all entries using it, or removes
+/// it if `to` is `None`. Returns the count of changed entries.
+fn tag_replace(write: &WriteTransaction, from: &[String], to: Option<&str>) -> Result<u32> {
+    let ids = {
+        let index = write.open_multimap_table(TABLE_INDEX_TAG)?;
+        let mut ids = Vec::new();
+        for tag in from {
+            for id in index.get(tag.as_str())? {
+                let id = id?.value().to_string();
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+        ids
+    };
+
+    let mut count = 0;
+    for id in ids {
+        let existing = {
+            let table = write.open_table(TABLE_METADATA)?;
+            let value = table.get(id.as_str())?;
+            value
+                .map(|v| bson::from_slice::<Metadata>(v.value().as_slice()))
+                .transpose()?
+        };
+        let Some(mut metadata) = existing else {
+            continue;
+        };
+        let mut tags = Vec::with_capacity(metadata.tags.len());
+        for tag in &metadata.tags {
+            let tag = match to {
+                Some(to) if from.contains(tag) => to,
+                None if from.contains(tag) => continue,
+                _ => tag.as_str(),
+            };
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        if tags == metadata.tags {
+            continue;
+        }
+        metadata.tags = tags;
+        metadata.mark_update();
+        metadata_write(write, &id, Some(&metadata))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Points the children of `from` to `to`.
+fn tag_reparent(write: &WriteTransaction, from: &str, to: Option<&str>) -> Result<()> {
+    for mut record in tag_get_all(write)? {
+        if record.parent.as_deref() == Some(from) {
+            let old = record.clone();
+            record.parent = to.map(String::from);
+            tag_put(write, Some(&old), Some(&record))?;
+        }
+    }
+    Ok(())
+}
+
+/// Lists registered tags and tags only used by entries, by name.
+pub async fn tag_list(data: State<'_, DataState>) -> Result<Vec<TagInfo>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let index = read.open_multimap_table(TABLE_INDEX_TAG)?;
+        let mut tags = BTreeMap::new();
+        for entry in read.open_table(TABLE_TAG)?.iter()? {
+            let tag = bson::from_slice::<TagRecord>(entry?.1.value().as_slice())?;
+            let count = index.get(tag.name.as_str())?.len();
+            tags.insert(
+                tag.name.clone(),
+                TagInfo {
+                    tag,
+                    registered: true,
+                    count,
+                },
+            );
+        }
+        for name in index_keys(&read, TABLE_INDEX_TAG)? {
+            if !tags.contains_key(&name) {
+                let count = index.get(name.as_str())?.len();
+                tags.insert(
+                    name.clone(),
+                    TagInfo {
+                        tag: TagRecord {
+                            name,
+                            ..Default::default()
+                        },
+                        registered: false,
+                        count,
+                    },
+                );
+            }
+        }
+        Ok(tags.into_values().collect())
+    })
+    .await?
+}
+
+/// Registers or updates a tag. Entries using one of its aliases are rewritten
+/// to the canonical name. Returns the count of changed entries.
+pub async fn tag_save(tag: TagRecord, data: State<'_, DataState>) -> Result<u32> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        if tag.name.trim().is_empty() {
+            return Err(anyhow!("Tag name is empty"));
+        }
+        let write = db.begin_write()?;
+        let mut parent = tag.parent.clone();
+        while let Some(name) = parent {
+            if name == tag.name {
+                return Err(anyhow!("Tag '{}' can not be its own ancestor", tag.name));
+            }
+            parent = tag_get(&write, &name)?
+                .ok_or_else(|| anyhow!("Parent tag '{}' is not registered", name))?
+                .parent;
+        }
+
+        let old = tag_get(&write, &tag.name)?;
+        tag_put(&write, old.as_ref(), Some(&tag))?;
+
+        let keys = tag.alias_keys();
+        let sources = index_keys_write(&write)?
+            .into_iter()
+            .filter(|t| *t != tag.name && keys.contains(&alias_key(t)))
+            .collect::<Vec<_>>();
+        let count = tag_replace(&write, &sources, Some(&tag.name))?;
+        write.commit()?;
+
+        info!("Saved tag '{}', {} entries canonicalized", tag.name, count);
+        Ok(count)
+    })
+    .await?
+}
+
+fn index_keys_write(write: &WriteTransaction) -> Result<Vec<String>> {
+    let table = write.open_multimap_table(TABLE_INDEX_TAG)?;
+    let mut keys = Vec::new();
+    for entry in table.iter()? {
+        keys.push(entry?.0.value().to_string());
+    }
+    Ok(keys)
+}
+
+/// Renames a tag on every entry and in the registry. Returns the count of
+/// changed entries.
+pub async fn tag_rename(from: String, to: String, data: State<'_, DataState>) -> Result<u32> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        if to.trim().is_empty() {
+            return Err(anyhow!("Tag name is empty"));
+        }
+        let write = db.begin_write()?;
+        if tag_get(&write, &to)?.is_some() {
+            return Err(anyhow!("Tag '{}' already exists, merge instead", to));
+        }
+        if let Some(old) = tag_get(&write, &from)? {
+            let new = TagRecord {
+                name: to.clone(),
+                ..old.clone()
+            };
+            tag_put(&write, Some(&old), Some(&new))?;
+            tag_reparent(&write, &from, Some(&to))?;
+        }
+        let count = tag_replace(&write, std::slice::from_ref(&from), Some(&to))?;
+        write.commit()?;
+
+        info!("Renamed tag '{}' to '{}' on {} entries", from, to, count);
+        Ok(count)
+    })
+    .await?
+}
+
+/// Merges `sources` into `target`, the source names and their aliases become
+/// aliases of the target. Returns the count of changed entries.
+pub async fn tag_merge(
+    sources: Vec<String>,
+    target: String,
+    data: State<'_, DataState>,
+) -> Result<u32> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let sources = sources
+            .into_iter()
+            .filter(|s| *s != target)
+            .collect::<Vec<_>>();
+        let write = db.begin_write()?;
+
+        let old = tag_get(&write, &target)?;
+        let mut merged = old.clone().unwrap_or_else(|| TagRecord {
+            name: target.clone(),
+            ..Default::default()
+        });
+        for source in &sources {
+            if let Some(record) = tag_get(&write, source)? {
+                tag_put(&write, Some(&record), None)?;
+                merged.aliases.extend(record.aliases);
+                if merged.parent.is_none() && record.parent.as_ref() != Some(&target) {
+                    merged.parent = record.parent;
+                }
+            }
+            merged.aliases.push(source.clone());
+            tag_reparent(&write, source, Some(&target))?;
+        }
+        let mut seen = HashSet::new();
+        merged.aliases.retain(|a| seen.insert(alias_key(a)));
+        tag_put(&write, old.as_ref(), Some(&merged))?;
+
+        let count = tag_replace(&write, &sources, Some(&target))?;
+        write.commit()?;
+
+        info!(
+            "Merged tags {:?} into '{}' on {} entries",
+            sources, target, count
+        );
+        Ok(count)
+    })
+    .await?
+}
+
+/// Removes a tag from every entry and from the registry, its children move up
+/// to its parent. Returns the count of changed entries.
+pub async fn tag_delete(name: String, data: State<'_, DataState>) -> Result<u32> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let parent = match tag_get(&write, &name)? {
+            Some(record) => {
+                tag_put(&write, Some(&record), None)?;
+                record.parent
+            },
+            None => None,
+        };
+        tag_reparent(&write, &name, parent.as_deref())?;
+        let count = tag_replace(&write, std::slice::from_ref(&name), None)?;
+        write.commit()?;
+
+        info!("Deleted tag '{}' from {} entries", name, count);
+        Ok(count)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+
+    #[test]
+    fn test_tag_canonical() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let record = TagRecord {
+            name: String::from("RPG"),
+            aliases: vec![String::from("ロールプレイング")],
+            ..Default::default()
+        };
+        let mut metadata = Metadata {
+            tags: vec![String::from("rpg"), String::from("Fantasy")],
+            ..Default::default()
+        };
+
+        let write = db.begin_write().unwrap();
+        tag_put(&write, None, Some(&record)).unwrap();
+        metadata_write(&write, &metadata.id.to_string(), Some(&metadata)).unwrap();
+
+        let conflict = TagRecord {
+            name: String::from("Role Playing"),
+            aliases: vec![String::from("ﾛｰﾙﾌﾟﾚｲﾝｸﾞ")],
+            ..Default::default()
+        };
+        assert!(tag_put(&write, None, Some(&conflict)).is_err());
+
+        let tags = [
+            String::from("ロールプレイング"),
+            String::from("RPG"),
+            String::from("Fantasy"),
+        ];
+        assert_eq!(
+            tag_canonical(&write, &tags).unwrap(),
+            Some(vec![String::from("RPG"), String::from("Fantasy")])
+        );
+        assert_eq!(tag_canonical(&write, &tags[1..]).unwrap(), None);
+
+        metadata.tags = vec![String::from("RPG"), String::from("Fantasy")];
+        let stored = {
+            let table = write.open_table(TABLE_METADATA).unwrap();
+            let value = table
+                .get(metadata.id.to_string().as_str())
+                .unwrap()
+                .unwrap();
+            bson::from_slice::<Metadata>(value.value().as_slice()).unwrap()
+        };
+        assert_eq!(stored.tags, metadata.tags);
+
+        assert_eq!(
+            tag_replace(&write, &[String::from("RPG")], None).unwrap(),
+            1
+        );
+        assert_eq!(index_keys_write(&write).unwrap(), vec!["Fantasy"]);
+        // Entries whose tags stay the same are not rewritten
+        let fantasy = [String::from("Fantasy")];
+        assert_eq!(tag_replace(&write, &fantasy, Some("Fantasy")).unwrap(), 0);
+    }
+}
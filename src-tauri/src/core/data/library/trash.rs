@@ -0,0 +1,172 @@
+use std::cmp::Reverse;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
+use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition, WriteTransaction};
+use serde::{Deserialize, Serialize};
+use tauri::{State, async_runtime};
+use ts_rs::TS;
+
+use crate::core::{
+    AppStateExt,
+    data::{
+        library::{
+            collection::collection_cache_sync, delegate::metadata_write_as,
+            deployment::deployment_cache_sync, history::HistoryOp, launcher::play_session_prune,
+        },
+        metadata::Metadata,
+        state::DataState,
+    },
+    get_handle_ref,
+};
+
+/// Deleted entries by id, as BSON encoded [TrashEntry]
+pub(super) const TABLE_TRASH: TableDefinition<&str, Vec<u8>> = TableDefinition::new("trash");
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct TrashEntry {
+    pub metadata: Metadata,
+    pub delete_time: DateTime<Utc>,
+}
+
+/// Moves a deleted entry into the trash, called in the deleting transaction.
+pub(super) fn trash_put(write: &WriteTransaction, metadata: &Metadata) -> Result<()> {
+    let entry = TrashEntry {
+        metadata: metadata.clone(),
+        delete_time: Utc::now(),
+    };
+    write
+        .open_table(TABLE_TRASH)?
+        .insert(metadata.id.to_string().as_str(), bson::to_vec(&entry)?)?;
+    Ok(())
+}
+
+/// Drops `key` from the trash, so an id is never both in the library and the
+/// trash.
+pub(super) fn trash_remove(write: &WriteTransaction, key: &str) -> Result<Option<TrashEntry>> {
+    let mut table = write.open_table(TABLE_TRASH)?;
+    let removed = table.remove(key)?;
+    Ok(removed
+        .map(|v| bson::from_slice::<TrashEntry>(v.value().as_slice()))
+        .transpose()?)
+}
+
+/// Removes the entries deleted more than `retention_days` ago, `0` keeps them
+/// forever.
+pub(super) fn trash_purge_expired(db: &Database, retention_days: u32) -> Result<u32> {
+    if retention_days == 0 {
+        return Ok(0);
+    }
+    let deadline = Utc::now() - Duration::days(retention_days as i64);
+
+    let write = db.begin_write()?;
+    let count = {
+        let mut table = write.open_table(TABLE_TRASH)?;
+        let removed = table.extract_if(|_, v| {
+            bson::from_slice::<TrashEntry>(v.as_slice())
+                .is_ok_and(|entry| entry.delete_time < deadline)
+        })?;
+        removed.count() as u32
+    };
+    if count > 0 {
+        play_session_prune(&write)?;
+    }
+    write.commit()?;
+
+    if count > 0 {
+        info!(
+            "Purged {} trash entries older than {} days",
+            count, retention_days
+        );
+    }
+    Ok(count)
+}
+
+/// Returns the trashed entries, latest deleted first.
+pub async fn trash_list(data: State<'_, DataState>) -> Result<Vec<TrashEntry>> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let read = db.begin_read()?;
+        let table = read.open_table(TABLE_TRASH)?;
+        let mut entries = Vec::new();
+        for entry in table.iter()? {
+            let (k, v) = entry?;
+            match bson::from_slice::<TrashEntry>(v.value().as_slice()) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => error!("Failed to deserialize trash entry '{}': {}", k.value(), e),
+            }
+        }
+        entries.sort_by_key(|entry| Reverse(entry.delete_time));
+        Ok(entries)
+    })
+    .await?
+}
+
+/// Puts a trashed entry back into the library.
+pub async fn trash_restore(key: String, data: State<'_, DataState>) -> Result<Metadata> {
+    let db = data.database();
+    let metadata = async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let entry = trash_remove(&write, &key)?
+            .ok_or_else(|| anyhow!("Key '{}' not found in trash", key))?;
+        let previous = metadata_write_as(
+            &write,
+            &key,
+            Some(&entry.metadata),
+            Some(HistoryOp::Restore),
+            None,
+        )?;
+        if previous.is_some() {
+            return Err(anyhow!("Key '{}' already exists in library", key));
+        }
+        write.commit()?;
+        Ok::<Metadata, anyhow::Error>(entry.metadata)
+    })
+    .await??;
+
+    let data = get_handle_ref().state_data();
+    let _ = collection_cache_sync(&metadata, data.clone())
+        .inspect_err(|e| error!("Failed to sync collection cache: {}", e));
+    let _ = deployment_cache_sync(&metadata, data)
+        .inspect_err(|e| error!("Failed to sync deployment cache: {}", e));
+    info!(
+        "Restored library entry with id '{}' from trash",
+        metadata.id
+    );
+
+    Ok(metadata)
+}
+
+/// Permanently removes the given entries from the trash, or all of them if
+/// `keys` is `None`. Returns the removed count.
+pub async fn trash_purge(keys: Option<Vec<String>>, data: State<'_, DataState>) -> Result<u32> {
+    let db = data.database();
+    async_runtime::spawn_blocking(move || {
+        let write = db.begin_write()?;
+        let count = match keys {
+            Some(keys) => {
+                let mut table = write.open_table(TABLE_TRASH)?;
+                let mut count = 0;
+                for key in keys {
+                    if table.remove(key.as_str())?.is_some() {
+                        count += 1;
+                    }
+                }
+                count
+            },
+            None => {
+                let count = write.open_table(TABLE_TRASH)?.len()? as u32;
+                write.delete_table(TABLE_TRASH)?;
+                write.open_table(TABLE_TRASH)?;
+                count
+            },
+        };
+        play_session_prune(&write)?;
+        write.commit()?;
+        info!("Purged {} entries from trash", count);
+        Ok(count)
+    })
+    .await?
+}
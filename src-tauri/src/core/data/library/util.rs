@@ -9,18 +9,34 @@ use tokio::fs as tfs;
 use crate::core::{
     AppStateExt,
     data::{
-        library::{TABLE_METADATA, metadata_get_all},
+        library::{
+            TABLE_METADATA, collection::collection_list, delegate::metadata_write,
+            metadata_get_all, trash::trash_list,
+        },
         metadata::Metadata,
     },
 };
 
 pub async fn clear_unused_images(app: AppHandle) -> Result<u32> {
-    let all_used_images = metadata_get_all(app.state_data())
+    let image_hashes = |metadata: Metadata| metadata.images.into_iter().map(|i| i.hash);
+    let mut all_used_images = metadata_get_all(app.state_data())
         .await?
-        .iter()
-        .map(|data| data.image.clone())
-        .flatten()
+        .into_iter()
+        .flat_map(image_hashes)
         .collect::<HashSet<_>>();
+    // Keep images of trashed entries, they come back on restore
+    all_used_images.extend(
+        trash_list(app.state_data())
+            .await?
+            .into_iter()
+            .flat_map(|entry| image_hashes(entry.metadata)),
+    );
+    all_used_images.extend(
+        collection_list(app.state_data())
+            .await?
+            .into_iter()
+            .filter_map(|record| record.cover),
+    );
     let dir_image = app.state_config().get().dir_image();
     if !dir_image.exists() || !dir_image.is_dir() {
         return Err(anyhow!(
@@ -115,24 +131,23 @@ pub async fn recalculate_archive_size(app: AppHandle) -> Result<()> {
         for entry in table.iter()?.filter_map(Result::ok) {
             let (key, value) = (entry.0.value().to_owned(), entry.1.value());
             let mut metadata = bson::from_slice::<Metadata>(&value)?;
-            let _ = metadata
-                .archive_info
-                .update_size_blocking()
-                .inspect_err(|e| warn!("Failed to update archive info size of {}: {}", key, e));
-            new_metadata.push((key.clone(), bson::to_vec(&metadata)?));
+            for version in &mut metadata.archives {
+                let _ = version
+                    .archive
+                    .update_size_blocking()
+                    .inspect_err(|e| warn!("Failed to update archive info size of {}: {}", key, e));
+            }
+            new_metadata.push((key, metadata));
         }
-        Ok::<Vec<(String, Vec<u8>)>, Error>(new_metadata)
+        Ok::<Vec<(String, Metadata)>, Error>(new_metadata)
     })
     .await??;
 
     async_runtime::spawn_blocking(move || {
         let write = db.begin_write()?;
-        {
-            let mut table = write.open_table(TABLE_METADATA)?;
-            for (key, value) in edited_metadata {
-                table.insert(&*key, value)?;
-                debug!("Updated archive size for metadata ID: {}", key);
-            }
+        for (key, value) in edited_metadata {
+            metadata_write(&write, &key, Some(&value))?;
+            debug!("Updated archive size for metadata ID: {}", key);
         }
         write.commit()?;
 
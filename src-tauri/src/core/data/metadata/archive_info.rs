@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
 use log::{info, warn};
@@ -40,6 +43,41 @@ pub enum ArchiveInfo {
 }
 
 impl ArchiveInfo {
+    /// Stored size in bytes, 0 if there is no archive
+    pub fn size(&self) -> u64 {
+        match self {
+            Self::None => 0,
+            Self::ArchiveFile { size, .. }
+            | Self::CommonFile { size, .. }
+            | Self::Directory { size, .. } => *size,
+        }
+    }
+
+    /// Absolute path of the archive without checking that it exists,
+    /// `ArchiveFile` paths are relative to `dir_archive`
+    pub fn path_in(&self, dir_archive: &Path) -> Option<PathBuf> {
+        match self {
+            Self::None => None,
+            Self::ArchiveFile { path, .. } => Some(dir_archive.join(path)),
+            Self::CommonFile { path, .. } | Self::Directory { path, .. } => {
+                Some(PathBuf::from(path))
+            },
+        }
+    }
+
+    /// Removes the file of an `ArchiveFile`, which is owned by the library.
+    /// Other archives point to files of the user and are left untouched.
+    pub fn remove_stored(&self) -> Result<bool> {
+        if let Self::ArchiveFile { .. } = self
+            && let This(path) = self.try_resolve()?
+        {
+            fs::remove_file(&path)?;
+            info!("Removed archive file: {}", path.display());
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
     pub(super) fn try_resolve(&self) -> anyhow::Result<Whether<PathBuf, Self>> {
         match self {
             Self::None => {
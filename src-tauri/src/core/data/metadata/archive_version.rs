@@ -0,0 +1,188 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::core::{
+    config::ArchiveRetention,
+    data::metadata::{ArchiveInfo, ContentInfo, Metadata},
+};
+
+/// One stored version of an entry, the archive it is deployed from
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ArchiveVersion {
+    /// Version label, unique within an entry
+    pub version: String,
+    pub archive: ArchiveInfo,
+    pub add_time: DateTime<Utc>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Pinned versions are never removed by [ArchiveRetention]
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+const ARCHIVE_NONE: &ArchiveInfo = &ArchiveInfo::None;
+
+impl ContentInfo {
+    /// Label of a version added without one, the game version if known
+    pub(super) fn version_label(&self) -> String {
+        match self.version() {
+            Some(version) if !version.is_empty() => version.to_string(),
+            _ => Utc::now().format("%Y%m%d%H%M%S").to_string(),
+        }
+    }
+}
+
+impl Metadata {
+    /// Archive of the latest version
+    pub fn archive(&self) -> &ArchiveInfo {
+        self.archives.last().map_or(ARCHIVE_NONE, |v| &v.archive)
+    }
+
+    /// Stored size of all versions in bytes
+    pub fn archive_size(&self) -> u64 {
+        self.archives.iter().map(|v| v.archive.size()).sum()
+    }
+
+    /// The given version, or the latest one with `None`
+    pub fn archive_version(&self, version: Option<&str>) -> Result<&ArchiveVersion> {
+        match version {
+            Some(version) => self
+                .archives
+                .iter()
+                .find(|v| v.version == version)
+                .ok_or_else(|| anyhow!("Version '{}' not found in '{}'", version, self.title)),
+            None => self
+                .archives
+                .last()
+                .ok_or_else(|| anyhow!("Entry '{}' has no archive", self.title)),
+        }
+    }
+
+    /// Version currently deployed, if it is known
+    pub fn archive_deployed(&self) -> Option<&str> {
+        self.deploy_info.version()
+    }
+
+    /// Sets the archive of `version`, the latest one with `None`. An unknown
+    /// version is added as the latest, [ArchiveInfo::None] removes the version.
+    /// Returns the archive that was replaced or removed.
+    pub fn archive_set(
+        &mut self,
+        archive: ArchiveInfo,
+        version: Option<String>,
+    ) -> Option<ArchiveInfo> {
+        let position = match &version {
+            Some(version) => self.archives.iter().position(|v| v.version == *version),
+            None => self.archives.len().checked_sub(1),
+        };
+        match (position, archive) {
+            (Some(i), ArchiveInfo::None) => Some(self.archives.remove(i).archive),
+            (Some(i), archive) => Some(std::mem::replace(&mut self.archives[i].archive, archive)),
+            (None, ArchiveInfo::None) => None,
+            (None, archive) => {
+                self.archives.push(ArchiveVersion {
+                    version: version.unwrap_or_else(|| self.content_info.version_label()),
+                    archive,
+                    add_time: Utc::now(),
+                    notes: None,
+                    pinned: false,
+                });
+                None
+            },
+        }
+    }
+
+    /// Removes the old versions `retention` does not keep and returns them.
+    /// The latest, the deployed and pinned versions are kept.
+    pub fn archive_prune(&mut self, retention: &ArchiveRetention) -> Vec<ArchiveVersion> {
+        let now = Utc::now();
+        let latest = self.archives.len().saturating_sub(1);
+        let deployed = self.archive_deployed().map(str::to_string);
+        let mut kept = 0;
+        let mut removed = Vec::new();
+        // Newest first so the count keeps the latest versions
+        for i in (0..self.archives.len()).rev() {
+            let version = &self.archives[i];
+            let expired = retention.keep_days > 0
+                && version.add_time < now - Duration::days(retention.keep_days as i64);
+            let exceeded = retention.keep_versions > 0 && kept >= retention.keep_versions;
+            if i == latest
+                || version.pinned
+                || deployed.as_deref() == Some(version.version.as_str())
+                || !(expired || exceeded)
+            {
+                kept += 1;
+                continue;
+            }
+            removed.push(self.archives.remove(i));
+        }
+        removed.reverse();
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::data::metadata::DeployInfo;
+
+    fn archive(path: &str) -> ArchiveInfo {
+        ArchiveInfo::ArchiveFile {
+            size: 10,
+            path: path.to_string(),
+            password: None,
+        }
+    }
+
+    #[test]
+    fn test_archive_versions() {
+        let mut metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000000",
+            "create_time": "2024-01-01T00:00:00Z",
+            "update_time": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        assert_eq!(metadata.archive(), &ArchiveInfo::None);
+        assert!(metadata.archive_set(ArchiveInfo::None, None).is_none());
+
+        for version in ["1.0", "1.1", "1.2", "1.3"] {
+            metadata.archive_set(archive(version), Some(version.to_string()));
+        }
+        assert_eq!(metadata.archive(), &archive("1.3"));
+        assert_eq!(metadata.archive_size(), 40);
+        let replaced = metadata.archive_set(archive("1.3b"), None);
+        assert_eq!(replaced, Some(archive("1.3")));
+        assert_eq!(
+            metadata.archive_version(Some("1.1")).unwrap().archive,
+            archive("1.1")
+        );
+        assert!(metadata.archive_version(Some("2.0")).is_err());
+
+        metadata.archives[0].pinned = true;
+        metadata.deploy_info = DeployInfo::Directory {
+            path: "deploy".into(),
+            version: Some(String::from("1.1")),
+        };
+        let retention = ArchiveRetention {
+            keep_versions: 1,
+            keep_days: 0,
+        };
+        let removed = metadata.archive_prune(&retention);
+        assert_eq!(
+            removed
+                .iter()
+                .map(|v| v.version.as_str())
+                .collect::<Vec<_>>(),
+            vec!["1.2"]
+        );
+        let versions = metadata
+            .archives
+            .iter()
+            .map(|v| v.version.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec!["1.0", "1.1", "1.3"]);
+    }
+}
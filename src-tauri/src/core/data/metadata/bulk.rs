@@ -0,0 +1,125 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::core::data::metadata::{ContentInfo, GameData, GameDistribution, Metadata};
+
+/// One change applied to many entries at once
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(tag = "type", content = "value")]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum BulkEdit {
+    AddTags(Vec<String>),
+    RemoveTags(Vec<String>),
+    /// Set or clear the collection
+    SetCollection(Option<String>),
+    SetRating(u8),
+    /// Set or clear the developer, only for [ContentInfo::Game]
+    SetDeveloper(Option<String>),
+    /// Set or clear the publisher, only for [ContentInfo::Game]
+    SetPublisher(Option<String>),
+    /// Only for [ContentInfo::Game]
+    SetDistribution(GameDistribution),
+}
+
+/// Outcome of a [BulkEdit] for one id, entries with an error are left
+/// untouched
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct BulkEditResult {
+    pub id: String,
+    pub changed: bool,
+    pub error: Option<String>,
+}
+
+impl Metadata {
+    /// Applies `edit` in place, returns whether anything changed. The update
+    /// time is only touched on change.
+    pub fn bulk_edit(&mut self, edit: &BulkEdit) -> Result<bool> {
+        fn game_data(content_info: &mut ContentInfo) -> Result<&mut GameData> {
+            match content_info {
+                ContentInfo::Game(data) => Ok(data),
+                other => Err(anyhow!(
+                    "Content type '{}' has no game data",
+                    other.type_name()
+                )),
+            }
+        }
+
+        fn replace<T: PartialEq>(target: &mut T, value: T) -> bool {
+            if *target == value {
+                false
+            } else {
+                *target = value;
+                true
+            }
+        }
+
+        let changed = match edit {
+            BulkEdit::AddTags(tags) => {
+                let before = self.tags.len();
+                for tag in tags {
+                    if !self.tags.contains(tag) {
+                        self.tags.push(tag.clone());
+                    }
+                }
+                self.tags.len() != before
+            },
+            BulkEdit::RemoveTags(tags) => {
+                let before = self.tags.len();
+                self.tags.retain(|tag| !tags.contains(tag));
+                self.tags.len() != before
+            },
+            BulkEdit::SetCollection(collection) => {
+                replace(&mut self.collection, collection.clone())
+            },
+            BulkEdit::SetRating(rating) => replace(&mut self.rating, *rating),
+            BulkEdit::SetDeveloper(developer) => replace(
+                &mut game_data(&mut self.content_info)?.developer,
+                developer.clone(),
+            ),
+            BulkEdit::SetPublisher(publisher) => replace(
+                &mut game_data(&mut self.content_info)?.publisher,
+                publisher.clone(),
+            ),
+            BulkEdit::SetDistribution(distribution) => replace(
+                &mut game_data(&mut self.content_info)?.distribution,
+                distribution.clone(),
+            ),
+        };
+
+        if changed {
+            self.mark_update();
+        }
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bulk_edit() {
+        let mut metadata = Metadata {
+            tags: vec![String::from("RPG")],
+            ..Default::default()
+        };
+
+        let add = BulkEdit::AddTags(vec![String::from("RPG"), String::from("Fantasy")]);
+        assert!(metadata.bulk_edit(&add).unwrap());
+        assert_eq!(metadata.tags, vec!["RPG", "Fantasy"]);
+        assert!(!metadata.bulk_edit(&add).unwrap());
+
+        let remove = BulkEdit::RemoveTags(vec![String::from("RPG")]);
+        assert!(metadata.bulk_edit(&remove).unwrap());
+        assert_eq!(metadata.tags, vec!["Fantasy"]);
+
+        let collection = BulkEdit::SetCollection(Some(String::from("Series")));
+        assert!(metadata.bulk_edit(&collection).unwrap());
+        assert!(!metadata.bulk_edit(&collection).unwrap());
+
+        let developer = BulkEdit::SetDeveloper(Some(String::from("Studio")));
+        assert!(metadata.bulk_edit(&developer).is_err());
+    }
+}
@@ -15,16 +15,203 @@ pub enum ContentInfo {
     Undefined,
 
     Game(GameData),
+    Comic(ComicData),
+    Audio(AudioData),
+    Video(VideoData),
+    Novel(NovelData),
+    Software(SoftwareData),
 }
 
+/// DLSite work categories of each content type, in the languages of
+/// [crate::core::Language]. Categories not listed here are games.
+const DL_CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "Comic",
+        &[
+            "マンガ",
+            "劇画",
+            "CG・イラスト",
+            "単話",
+            "単行本",
+            "雑誌/アンソロ",
+            "Webtoon",
+            "ボイスコミック",
+            "Manga",
+            "Gekiga",
+            "CG + Illustrations",
+            "Single Episode",
+            "Books",
+            "Magazines / Anthologies",
+            "Voiced Comics",
+            "漫画",
+            "剧画",
+            "CG・插画",
+            "单话",
+            "单行本",
+            "杂志/选集",
+            "有声漫画",
+        ],
+    ),
+    (
+        "Audio",
+        &[
+            "ボイス・ASMR",
+            "音楽",
+            "Voice / ASMR",
+            "Music",
+            "音声・ASMR",
+            "音声",
+            "音乐",
+        ],
+    ),
+    (
+        "Video",
+        &["動画", "アニメ", "Video", "Anime", "视频", "动画"],
+    ),
+    (
+        "Novel",
+        &[
+            "ノベル",
+            "ライトノベル",
+            "Novel",
+            "Light Novels",
+            "小说",
+            "轻小说",
+        ],
+    ),
+    (
+        "Software",
+        &[
+            "ツール/アクセサリ",
+            "画像素材",
+            "音素材",
+            "Tools / Accessories",
+            "Illustration Materials",
+            "Music Materials",
+            "工具/配件",
+            "图片素材",
+            "音频素材",
+        ],
+    ),
+];
+
 impl ContentInfo {
-    fn dir_name(&self) -> Vec<&'static str> {
+    /// Name of the variant, used as index key
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Undefined => "Undefined",
+            Self::Game(_) => "Game",
+            Self::Comic(_) => "Comic",
+            Self::Audio(_) => "Audio",
+            Self::Video(_) => "Video",
+            Self::Novel(_) => "Novel",
+            Self::Software(_) => "Software",
+        }
+    }
+
+    /// Maps a DLSite work to a content type by its categories, falling back
+    /// to the store it is sold in.
+    pub fn from_dl_site(content_type: &DLContentType, id: &str, categories: &[String]) -> Self {
+        let type_name = categories
+            .iter()
+            .find_map(|category| {
+                DL_CATEGORIES
+                    .iter()
+                    .find(|(_, names)| names.contains(&category.trim()))
+                    .map(|(type_name, _)| *type_name)
+            })
+            .unwrap_or(match content_type {
+                DLContentType::Comics | DLContentType::ComicsR18 => "Comic",
+                _ => "Game",
+            });
+        let distribution = GameDistribution::DLSite(DLSiteDistributionData {
+            id: id.to_string(),
+            content_type: content_type.clone(),
+        });
+        match type_name {
+            "Comic" => Self::Comic(ComicData {
+                distribution,
+                ..Default::default()
+            }),
+            "Audio" => Self::Audio(AudioData {
+                distribution,
+                ..Default::default()
+            }),
+            "Video" => Self::Video(VideoData {
+                distribution,
+                ..Default::default()
+            }),
+            "Novel" => Self::Novel(NovelData {
+                distribution,
+                ..Default::default()
+            }),
+            "Software" => Self::Software(SoftwareData {
+                distribution,
+                ..Default::default()
+            }),
+            _ => Self::Game(GameData {
+                distribution,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Where the content was obtained, `None` for [ContentInfo::Undefined]
+    pub fn distribution(&self) -> Option<&GameDistribution> {
+        match self {
+            Self::Undefined => None,
+            Self::Game(data) => Some(&data.distribution),
+            Self::Comic(data) => Some(&data.distribution),
+            Self::Audio(data) => Some(&data.distribution),
+            Self::Video(data) => Some(&data.distribution),
+            Self::Novel(data) => Some(&data.distribution),
+            Self::Software(data) => Some(&data.distribution),
+        }
+    }
+
+    /// Version of a game or software, `None` for other types
+    pub fn version(&self) -> Option<&str> {
         match self {
-            Self::Undefined => vec!["undefined"],
-            Self::Game(data) => vec!["game", data.distribution.dir_name()],
+            Self::Game(data) => Some(&data.version),
+            Self::Software(data) => Some(&data.version),
+            _ => None,
         }
     }
 
+    /// Developers, authors and performers, used for search
+    pub fn creators(&self) -> Vec<&str> {
+        let mut creators = match self {
+            Self::Undefined => Vec::new(),
+            Self::Game(data) => vec![data.developer.as_deref(), data.publisher.as_deref()],
+            Self::Comic(data) => vec![data.author.as_deref()],
+            Self::Audio(data) => vec![data.circle.as_deref()],
+            Self::Video(data) => vec![data.studio.as_deref()],
+            Self::Novel(data) => vec![data.author.as_deref(), data.publisher.as_deref()],
+            Self::Software(data) => vec![data.developer.as_deref()],
+        }
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if let Self::Audio(data) = self {
+            creators.extend(data.voice_actors.iter().map(String::as_str));
+        }
+        creators
+    }
+
+    fn dir_name(&self) -> Vec<&'static str> {
+        let base = match self {
+            Self::Undefined => return vec!["undefined"],
+            Self::Game(_) => "game",
+            Self::Comic(_) => "comic",
+            Self::Audio(_) => "audio",
+            Self::Video(_) => "video",
+            Self::Novel(_) => "novel",
+            Self::Software(_) => "software",
+        };
+        let distribution = self.distribution().map_or("unknown", |d| d.dir_name());
+        vec![base, distribution]
+    }
+
     /// Returns the relative path starting from `.`, but it **should** start
     /// from `config.dir_archive()`
     ///
@@ -40,7 +227,10 @@ impl ContentInfo {
     pub(super) fn file_name(&self) -> String {
         match self {
             Self::Undefined => format!("Content-{}", Utc::now().format("%Y%m%d%H%M%S")),
-            Self::Game(data) => data.distribution.file_name(),
+            _ => self
+                .distribution()
+                .map(GameDistribution::file_name)
+                .unwrap_or_default(),
         }
     }
 }
@@ -64,6 +254,127 @@ pub struct GameData {
     pub distribution: GameDistribution,
 }
 
+impl Default for GameData {
+    fn default() -> Self {
+        Self {
+            version: default_version(),
+            game_type: GameType::default(),
+            developer: None,
+            publisher: None,
+            sys_platform: Vec::new(),
+            distribution: GameDistribution::default(),
+        }
+    }
+}
+
+/// Comics, manga and CG collections
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS, Default)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct ComicData {
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub pages: Option<u32>,
+    #[serde(default)]
+    pub direction: ReadingDirection,
+    #[serde(default)]
+    pub distribution: GameDistribution,
+}
+
+/// Page order of a comic
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS, Default)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum ReadingDirection {
+    /// Japanese manga
+    #[default]
+    RightToLeft,
+    LeftToRight,
+    /// Webtoons, scrolled from top to bottom
+    Vertical,
+}
+
+/// Voice works, ASMR and music
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS, Default)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct AudioData {
+    #[serde(default)]
+    pub circle: Option<String>,
+    #[serde(default)]
+    pub voice_actors: Vec<String>,
+    #[serde(default)]
+    pub tracks: Vec<AudioTrack>,
+    #[serde(default)]
+    pub distribution: GameDistribution,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct AudioTrack {
+    pub title: String,
+    /// Length in seconds
+    #[serde(default)]
+    pub duration: Option<u32>,
+}
+
+impl AudioData {
+    /// Total length of the tracks with a known duration, in seconds
+    pub fn duration(&self) -> u32 {
+        self.tracks.iter().filter_map(|t| t.duration).sum()
+    }
+}
+
+/// Anime and other videos
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS, Default)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct VideoData {
+    #[serde(default)]
+    pub studio: Option<String>,
+    /// Length in seconds
+    #[serde(default)]
+    pub duration: Option<u32>,
+    #[serde(default)]
+    pub distribution: GameDistribution,
+}
+
+/// Novels and light novels
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS, Default)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct NovelData {
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub publisher: Option<String>,
+    #[serde(default)]
+    pub pages: Option<u32>,
+    #[serde(default)]
+    pub distribution: GameDistribution,
+}
+
+/// Applications, tools and assets
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct SoftwareData {
+    #[serde(default = "default_version")]
+    pub version: String,
+    #[serde(default)]
+    pub developer: Option<String>,
+    #[serde(default)]
+    pub sys_platform: Vec<GameSysPlatform>,
+    #[serde(default)]
+    pub distribution: GameDistribution,
+}
+
+impl Default for SoftwareData {
+    fn default() -> Self {
+        Self {
+            version: default_version(),
+            developer: None,
+            sys_platform: Vec::new(),
+            distribution: GameDistribution::default(),
+        }
+    }
+}
+
 /// Represents the type of game, such as RPG Maker
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS, Default)]
 #[ts(export, export_to = "../../src/api/types.ts")]
@@ -88,7 +399,8 @@ pub enum GameSysPlatform {
     Web,
 }
 
-/// Represents the distribution method of a game, such as Steam or DLSite
+/// Represents the distribution method of a game, such as Steam or DLSite. Also
+/// used by the other content types.
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS, Default)]
 #[serde(tag = "type", content = "data")]
 #[ts(export, export_to = "../../src/api/types.ts")]
@@ -121,12 +433,22 @@ pub struct OtherDistributionData {
 }
 
 impl DLSiteDistributionData {
-    fn full_id(&self) -> String {
+    pub fn full_id(&self) -> String {
         self.content_type.build_id(&self.id)
     }
 }
 
 impl GameDistribution {
+    /// Name of the variant, used for statistics
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Unknown => "Unknown",
+            Self::Steam(_) => "Steam",
+            Self::DLSite(_) => "DLSite",
+            Self::Other(_) => "Other",
+        }
+    }
+
     fn dir_name(&self) -> &'static str {
         match self {
             Self::Unknown => "unknown",
@@ -149,3 +471,45 @@ impl GameDistribution {
 fn default_version() -> String {
     String::from("1.0.0")
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_dl_site() {
+        let categories = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        let audio = ContentInfo::from_dl_site(
+            &DLContentType::DoujinR18,
+            "01239331",
+            &categories(&["ボイス・ASMR", "MP3"]),
+        );
+        assert_eq!(audio.type_name(), "Audio");
+        assert_eq!(audio.path_rel(), PathBuf::from("audio/dl"));
+        assert_eq!(audio.file_name(), "RJ01239331");
+
+        let comic = ContentInfo::from_dl_site(&DLContentType::Doujin, "1", &categories(&["漫画"]));
+        assert_eq!(comic.type_name(), "Comic");
+        let novel =
+            ContentInfo::from_dl_site(&DLContentType::ComicsR18, "1", &categories(&["Novel"]));
+        assert_eq!(novel.path_rel(), PathBuf::from("novel/dl"));
+        // Digital novels are games, not novels
+        let game = ContentInfo::from_dl_site(
+            &DLContentType::DoujinR18,
+            "1",
+            &categories(&["デジタルノベル"]),
+        );
+        assert_eq!(game.type_name(), "Game");
+        assert_eq!(game.version(), Some("1.0.0"));
+        let comic = ContentInfo::from_dl_site(&DLContentType::ComicsR18, "1", &[]);
+        assert_eq!(comic.type_name(), "Comic");
+
+        let video = ContentInfo::Video(VideoData {
+            studio: Some(String::from("Studio")),
+            ..Default::default()
+        });
+        assert_eq!(video.path_rel(), PathBuf::from("video/unknown"));
+        assert_eq!(video.creators(), vec!["Studio"]);
+    }
+}
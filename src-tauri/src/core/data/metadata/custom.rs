@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Number;
+use ts_rs::TS;
+
+use crate::core::{
+    config::{CustomField, CustomFieldKind},
+    data::metadata::Metadata,
+};
+
+/// Value of a user defined field, see [CustomField]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", content = "value")]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum CustomValue {
+    String(String),
+    Number(#[ts(type = "number")] Number),
+    Bool(bool),
+    Date(NaiveDate),
+    Enum(String),
+    Url(String),
+}
+
+impl CustomValue {
+    /// Plain text form, used for CSV export and text matching
+    pub fn text(&self) -> String {
+        match self {
+            Self::String(value) | Self::Enum(value) | Self::Url(value) => value.clone(),
+            Self::Number(value) => value.to_string(),
+            Self::Bool(value) => value.to_string(),
+            Self::Date(value) => value.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// Checks that the value has the declared kind of `field`.
+    pub fn check(&self, field: &CustomField) -> Result<()> {
+        match (&field.kind, self) {
+            (CustomFieldKind::String, Self::String(_))
+            | (CustomFieldKind::Number, Self::Number(_))
+            | (CustomFieldKind::Bool, Self::Bool(_))
+            | (CustomFieldKind::Date, Self::Date(_)) => Ok(()),
+            (CustomFieldKind::Enum(options), Self::Enum(value)) => {
+                if options.contains(value) {
+                    Ok(())
+                } else {
+                    Err(anyhow!(
+                        "'{}' is not an option of field '{}'",
+                        value,
+                        field.name
+                    ))
+                }
+            },
+            (CustomFieldKind::Url, Self::Url(value)) => Url::parse(value)
+                .map(|_| ())
+                .map_err(|e| anyhow!("Invalid URL for field '{}': {}", field.name, e)),
+            (kind, _) => Err(anyhow!(
+                "Field '{}' expects a value of type {:?}",
+                field.name,
+                kind
+            )),
+        }
+    }
+}
+
+impl Metadata {
+    /// Sets the given custom values, `None` removes one. Fields not declared
+    /// in `fields` are rejected, values of fields removed from the config are
+    /// kept until cleared.
+    pub fn custom_patch(
+        &mut self,
+        values: BTreeMap<String, Option<CustomValue>>,
+        fields: &[CustomField],
+    ) -> Result<()> {
+        for (name, value) in &values {
+            if let Some(value) = value {
+                let field = fields
+                    .iter()
+                    .find(|f| f.name == *name)
+                    .ok_or_else(|| anyhow!("Custom field '{}' is not declared", name))?;
+                value.check(field)?;
+            }
+        }
+        for (name, value) in values {
+            match value {
+                Some(value) => self.custom.insert(name, value),
+                None => self.custom.remove(&name),
+            };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_custom_patch() {
+        let fields = [
+            CustomField {
+                name: String::from("translated_by"),
+                label: String::from("Translated by"),
+                kind: CustomFieldKind::String,
+            },
+            CustomField {
+                name: String::from("progress"),
+                label: String::new(),
+                kind: CustomFieldKind::Enum(vec![String::from("Playing"), String::from("Done")]),
+            },
+        ];
+        let mut metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000000",
+            "create_time": "2024-01-01T00:00:00Z",
+            "update_time": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+
+        let patch = |pairs: &[(&str, Option<CustomValue>)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        metadata
+            .custom_patch(
+                patch(&[
+                    (
+                        "translated_by",
+                        Some(CustomValue::String(String::from("Team"))),
+                    ),
+                    ("progress", Some(CustomValue::Enum(String::from("Done")))),
+                ]),
+                &fields,
+            )
+            .unwrap();
+        assert_eq!(metadata.custom.len(), 2);
+
+        let invalid = [
+            ("progress", Some(CustomValue::Enum(String::from("Dropped")))),
+            ("progress", Some(CustomValue::Bool(true))),
+            ("unknown", Some(CustomValue::Bool(true))),
+        ];
+        for pair in invalid {
+            assert!(metadata.custom_patch(patch(&[pair]), &fields).is_err());
+        }
+        assert_eq!(metadata.custom.len(), 2);
+
+        metadata
+            .custom_patch(patch(&[("progress", None)]), &fields)
+            .unwrap();
+        assert_eq!(metadata.custom.len(), 1);
+
+        let bytes = bson::to_vec(&metadata).unwrap();
+        assert_eq!(bson::from_slice::<Metadata>(&bytes).unwrap(), metadata);
+    }
+}
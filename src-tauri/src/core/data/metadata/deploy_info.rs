@@ -17,19 +17,49 @@ pub enum DeployInfo {
     None,
     File {
         path: PathBuf,
+        /// Archive version deployed, unknown for deployments made before
+        /// versions were tracked
+        #[serde(default)]
+        version: Option<String>,
     },
     Directory {
         path: PathBuf,
+        #[serde(default)]
+        version: Option<String>,
     },
 }
 
 impl DeployInfo {
-    pub(super) fn new_file(path: PathBuf) -> Self {
-        DeployInfo::File { path }
+    pub(super) fn new_file(path: PathBuf, version: String) -> Self {
+        DeployInfo::File {
+            path,
+            version: Some(version),
+        }
+    }
+
+    pub(super) fn new_dir(path: PathBuf, version: String) -> Self {
+        DeployInfo::Directory {
+            path,
+            version: Some(version),
+        }
     }
 
-    pub(super) fn new_dir(path: PathBuf) -> Self {
-        DeployInfo::Directory { path }
+    /// Deployed path without checking that it exists
+    pub fn path(&self) -> Option<&PathBuf> {
+        match self {
+            DeployInfo::None => None,
+            DeployInfo::File { path, .. } | DeployInfo::Directory { path, .. } => Some(path),
+        }
+    }
+
+    /// Archive version deployed, if known
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            DeployInfo::None => None,
+            DeployInfo::File { version, .. } | DeployInfo::Directory { version, .. } => {
+                version.as_deref()
+            },
+        }
     }
 
     pub(super) fn try_resolve(&self) -> Whether<PathBuf, DeployInfo> {
@@ -38,7 +68,7 @@ impl DeployInfo {
                 warn!("DeployInfo is unset, cannot resolve path.");
                 That(DeployInfo::None)
             },
-            DeployInfo::File { path } => {
+            DeployInfo::File { path, .. } => {
                 if path.exists() {
                     This(path.clone())
                 } else {
@@ -46,7 +76,7 @@ impl DeployInfo {
                     That(DeployInfo::None)
                 }
             },
-            DeployInfo::Directory { path } => {
+            DeployInfo::Directory { path, .. } => {
                 if path.exists() {
                     This(path.clone())
                 } else {
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::core::data::metadata::Metadata;
+
+/// What an image shows, the first [ImageRole::Cover] is displayed in lists
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum ImageRole {
+    #[default]
+    Cover,
+    Screenshot,
+    Banner,
+    Sample,
+}
+
+/// One image of an entry, the hash returned by the `process_image_*` functions
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct MetadataImage {
+    pub hash: String,
+    #[serde(default)]
+    pub role: ImageRole,
+}
+
+impl Metadata {
+    /// Hash of the first cover image
+    pub fn cover(&self) -> Option<&str> {
+        self.images
+            .iter()
+            .find(|i| i.role == ImageRole::Cover)
+            .map(|i| i.hash.as_str())
+    }
+
+    /// Replaces the first cover image, or removes every cover with `None`.
+    pub fn cover_set(&mut self, hash: Option<String>) {
+        let position = self.images.iter().position(|i| i.role == ImageRole::Cover);
+        match (hash, position) {
+            (Some(hash), Some(i)) => self.images[i].hash = hash,
+            (Some(hash), None) => self.images.insert(
+                0,
+                MetadataImage {
+                    hash,
+                    role: ImageRole::Cover,
+                },
+            ),
+            (None, _) => self.images.retain(|i| i.role != ImageRole::Cover),
+        }
+    }
+
+    /// Appends images not in the list yet, returns the added ones.
+    pub fn images_add(
+        &mut self,
+        images: impl IntoIterator<Item = MetadataImage>,
+    ) -> Vec<MetadataImage> {
+        let mut added = Vec::new();
+        for image in images {
+            if !self.images.iter().any(|i| i.hash == image.hash) {
+                self.images.push(image.clone());
+                added.push(image);
+            }
+        }
+        added
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cover_set() {
+        let mut metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000000",
+            "images": [{ "hash": "shot", "role": "Screenshot" }],
+            "create_time": "2024-01-01T00:00:00Z",
+            "update_time": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        assert_eq!(metadata.cover(), None);
+
+        metadata.cover_set(Some(String::from("a")));
+        assert_eq!(metadata.images[0].hash, "a");
+        metadata.cover_set(Some(String::from("b")));
+        assert_eq!(metadata.cover(), Some("b"));
+        assert_eq!(metadata.images.len(), 2);
+
+        let image = |hash: &str| MetadataImage {
+            hash: hash.to_string(),
+            role: ImageRole::Sample,
+        };
+        let added = metadata.images_add([image("shot"), image("c"), image("c")]);
+        assert_eq!(added, vec![image("c")]);
+
+        metadata.cover_set(None);
+        assert_eq!(metadata.cover(), None);
+        assert_eq!(metadata.images.len(), 2);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{ffi::OsStr, fs, path::Path};
+use std::{collections::BTreeMap, ffi::OsStr, path::Path};
 
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
@@ -10,11 +10,14 @@ use tokio::fs as tfs;
 use ts_rs::TS;
 use uuid::Uuid;
 
-pub use self::{archive_info::*, content_info::*, deploy_info::*};
+pub use self::{
+    archive_info::*, archive_version::*, bulk::*, content_info::*, custom::*, deploy_info::*,
+    image::*, play_info::*, save_info::*, summary::*,
+};
 use crate::core::{
     AppStateExt,
     Whether::{That, This},
-    get_handle,
+    get_handle, get_handle_ref,
     util::{
         compress::{compress, decompress},
         path_ext::PathExt,
@@ -22,8 +25,15 @@ use crate::core::{
 };
 
 mod archive_info;
+mod archive_version;
+mod bulk;
 mod content_info;
+mod custom;
 mod deploy_info;
+mod image;
+mod play_info;
+mod save_info;
+mod summary;
 
 /// Basic metadata structure for data item
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, TS)]
@@ -48,22 +58,31 @@ pub struct Metadata {
     /// Description of the data item
     #[serde(default)]
     pub description: Option<String>,
-    /// Image hash, if any
+    /// Images in display order
     #[serde(default)]
-    pub image: Option<String>,
+    pub images: Vec<MetadataImage>,
     /// Rating
     #[serde(default)]
     pub rating: u8,
+    /// Values of the user defined fields, by field name
+    #[serde(default)]
+    pub custom: BTreeMap<String, CustomValue>,
 
     /// The content type of the data item
     #[serde(default)]
     pub content_info: ContentInfo,
-    /// Archive information
+    /// Stored versions, oldest first
     #[serde(default)]
-    pub archive_info: ArchiveInfo,
+    pub archives: Vec<ArchiveVersion>,
     /// Deployment information, if any
     #[serde(default)]
     pub deploy_info: DeployInfo,
+    /// Launch settings and play time
+    #[serde(default)]
+    pub play_info: PlayInfo,
+    /// Save locations inside or outside the deployment
+    #[serde(default)]
+    pub save_info: SaveInfo,
 
     pub create_time: DateTime<Utc>,
     pub update_time: DateTime<Utc>,
@@ -93,14 +112,26 @@ pub struct MetadataOption {
     pub collection: Option<String>,
     #[serde(default)]
     pub description: Option<String>,
+    /// Hash of the cover image, replaces the current cover
     #[serde(default)]
     pub image: Option<String>,
+    /// Replaces all images, applied before `image`
+    #[serde(default)]
+    pub images: Option<Vec<MetadataImage>>,
     #[serde(default)]
     pub rating: Option<u8>,
+    /// Custom values to set, `null` removes one
+    #[serde(default)]
+    pub custom: Option<BTreeMap<String, Option<CustomValue>>>,
     #[serde(default)]
     pub content_info: Option<ContentInfo>,
+    /// Archive of `archive_version`, [ArchiveInfo::None] removes the version
     #[serde(default)]
     pub archive_info: Option<ArchiveInfo>,
+    /// Version `archive_info` belongs to. Without it the latest version is
+    /// replaced, or a created archive is stored as the game version.
+    #[serde(default)]
+    pub archive_version: Option<String>,
 
     #[serde(default)]
     pub flag_create_archive: bool,
@@ -117,32 +148,47 @@ impl Metadata {
             tags: opt.tags.unwrap_or_default(),
             collection: opt.collection,
             description: opt.description,
-            image: opt.image,
+            images: opt.images.unwrap_or_default(),
             rating: opt.rating.unwrap_or_default(),
+            custom: BTreeMap::new(),
             content_info: opt.content_info.unwrap_or_default(),
-            archive_info: opt.archive_info.clone().unwrap_or_default(),
+            archives: Vec::new(),
             deploy_info: DeployInfo::None,
+            play_info: PlayInfo::default(),
+            save_info: SaveInfo::default(),
             create_time: time,
             update_time: time,
             id,
         };
+        if let Some(image) = opt.image {
+            created.cover_set(Some(image));
+        }
+        if let Some(custom) = opt.custom {
+            let config = get_handle_ref().state_config().get();
+            created.custom_patch(custom, config.custom_fields())?;
+        }
 
-        if opt.flag_create_archive {
-            if let Some(archive_info) = opt.archive_info {
-                if let ArchiveInfo::ArchiveFile { .. } = archive_info {
-                    created.process_archive().await?;
-                }
+        if let Some(archive_info) = opt.archive_info {
+            if opt.flag_create_archive && matches!(archive_info, ArchiveInfo::ArchiveFile { .. }) {
+                created
+                    .archive_create(archive_info, opt.archive_version)
+                    .await?;
             } else {
-                warn!(
-                    "Set 'flag_create_archive' but no archive_info provided, skipping archive creation."
-                );
+                created.archive_set(archive_info, opt.archive_version);
             }
+        } else if opt.flag_create_archive {
+            warn!(
+                "Set 'flag_create_archive' but no archive_info provided, skipping archive creation."
+            );
         }
 
         Ok(created)
     }
 
-    pub async fn patch(&mut self, opt: MetadataOption) -> Result<()> {
+    /// Applies the set fields of `opt`. Returns the versions dropped by the
+    /// archive retention, their files are left to the caller to remove once
+    /// the change is committed.
+    pub async fn patch(&mut self, opt: MetadataOption) -> Result<Vec<ArchiveVersion>> {
         info!("Patching metadata: {} with {:?}", self.id, opt);
         if let Some(title) = opt.title {
             self.title = title;
@@ -159,36 +205,39 @@ impl Metadata {
         if let Some(description) = opt.description {
             self.description = Some(description);
         }
+        if let Some(images) = opt.images {
+            self.images = images;
+        }
         if let Some(image) = opt.image {
-            self.image = Some(image);
+            self.cover_set(Some(image));
         }
         if let Some(rating) = opt.rating {
             self.rating = rating;
         }
+        if let Some(custom) = opt.custom {
+            let config = get_handle_ref().state_config().get();
+            self.custom_patch(custom, config.custom_fields())?;
+        }
         if let Some(content_info) = opt.content_info {
             self.content_info = content_info;
         }
+        let mut pruned = Vec::new();
         if let Some(archive_info) = opt.archive_info {
+            let count = self.archives.len();
             if opt.flag_create_archive {
                 if let ArchiveInfo::ArchiveFile { .. } = archive_info {
-                    if let This(existing_path) = self.archive_info.try_resolve()? {
-                        info!(
-                            "Found existing archive file: {}, removing",
-                            existing_path.display()
-                        );
-                        fs::remove_file(existing_path).unwrap_or_else(|e| {
-                            warn!("Failed to remove existing archive file: {}", e);
-                        });
-                    }
-                    self.archive_info = archive_info;
-                    self.process_archive().await?;
+                    self.archive_create(archive_info, opt.archive_version)
+                        .await?;
                 } else {
                     warn!(
                         "Set 'flag_create_archive' but archive_info is not ArchiveFile, skipping archive creation."
                     );
                 }
             } else {
-                self.archive_info = archive_info;
+                self.archive_set(archive_info, opt.archive_version);
+            }
+            if self.archives.len() > count {
+                pruned = self.archive_retain();
             }
         }
 
@@ -196,15 +245,49 @@ impl Metadata {
         self.update_time = update_time;
         info!("Updating metadata: {} at {}", self.id, update_time);
 
-        Ok(())
+        Ok(pruned)
     }
 
     pub fn mark_update(&mut self) {
         self.update_time = Utc::now();
     }
 
-    async fn process_archive(&mut self) -> Result<()> {
-        let (raw_path, password) = match self.archive_info.clone() {
+    /// Compresses the source directory of `source` into the archive directory
+    /// as `version`, replacing the stored archive of that version if any.
+    async fn archive_create(&mut self, source: ArchiveInfo, version: Option<String>) -> Result<()> {
+        let version = version.unwrap_or_else(|| self.content_info.version_label());
+        let archive = self.process_archive(source, &version).await?;
+        if let Some(replaced) = self.archive_set(archive.clone(), Some(version))
+            && replaced != archive
+        {
+            info!("Removing replaced archive {:?}", replaced);
+            let _ = replaced
+                .remove_stored()
+                .inspect_err(|e| warn!("Failed to remove existing archive file: {}", e));
+        }
+        Ok(())
+    }
+
+    /// Applies the configured [ArchiveRetention] after a version was added,
+    /// returns the dropped versions.
+    fn archive_retain(&mut self) -> Vec<ArchiveVersion> {
+        let retention = get_handle_ref()
+            .state_config()
+            .get()
+            .archive_retention()
+            .clone();
+        let pruned = self.archive_prune(&retention);
+        for removed in &pruned {
+            info!(
+                "Dropping version '{}' of metadata {} by retention",
+                removed.version, self.id
+            );
+        }
+        pruned
+    }
+
+    async fn process_archive(&self, source: ArchiveInfo, version: &str) -> Result<ArchiveInfo> {
+        let (raw_path, password) = match source {
             ArchiveInfo::ArchiveFile { path, password, .. } => (path, password),
             _ => unreachable!(),
         };
@@ -227,7 +310,14 @@ impl Metadata {
         let dir_base = app.state_config().get().dir_archive();
         let dir_rel = self.content_info.path_rel();
 
-        let file_name = self.content_info.file_name();
+        let file_name = format!(
+            "{}_{}",
+            self.content_info.file_name(),
+            version.replace(
+                |c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-',
+                "_"
+            )
+        );
 
         let mut target_path = dir_base.join(&dir_rel);
         tfs::create_dir_all(&target_path).await.map_err(|e| {
@@ -261,7 +351,7 @@ impl Metadata {
             target_path
         });
 
-        self.archive_info = ArchiveInfo::ArchiveFile {
+        let archive = ArchiveInfo::ArchiveFile {
             size: target_path_resolve.calculate_size_async().await,
             path: dir_rel.join(file_name).to_string_lossy().to_string(),
             password,
@@ -272,10 +362,18 @@ impl Metadata {
             target_path_resolve.display()
         );
 
-        Ok(())
+        Ok(archive)
     }
 
-    pub async fn deploy(&mut self, target: impl AsRef<Path>, app: &AppHandle) -> Result<bool> {
+    /// Deploys `version` to `target`, the latest version with `None`.
+    pub async fn deploy(
+        &mut self,
+        target: impl AsRef<Path>,
+        version: Option<&str>,
+        app: &AppHandle,
+    ) -> Result<bool> {
+        let version = self.archive_version(version)?.clone();
+        let label = version.version;
         let target_path = target.as_ref().to_owned();
         if !target_path.exists() {
             return Err(anyhow!(
@@ -290,8 +388,8 @@ impl Metadata {
             ));
         }
 
-        match self.archive_info.try_resolve()? {
-            This(source_path) => match &self.archive_info {
+        match version.archive.try_resolve()? {
+            This(source_path) => match &version.archive {
                 ArchiveInfo::CommonFile { .. } => {
                     let target_file = target_path.join(
                         source_path
@@ -305,7 +403,7 @@ impl Metadata {
                     );
 
                     tfs::copy(&source_path, &target_file).await?;
-                    self.deploy_info = DeployInfo::new_file(target_file.clone());
+                    self.deploy_info = DeployInfo::new_file(target_file.clone(), label);
                     self.mark_update();
 
                     info!("Deployed common file to: {}", target_file.display());
@@ -329,7 +427,7 @@ impl Metadata {
 
                     decompress(app, source_path, &target_path, password.as_deref()).await?;
 
-                    self.deploy_info = DeployInfo::new_dir(target_path.to_owned());
+                    self.deploy_info = DeployInfo::new_dir(target_path.to_owned(), label);
                     self.mark_update();
 
                     info!("Deployed archive to: {}", target_path.display());
@@ -361,7 +459,7 @@ impl Metadata {
                     })
                     .await??;
 
-                    self.deploy_info = DeployInfo::new_dir(target_path.to_owned());
+                    self.deploy_info = DeployInfo::new_dir(target_path.to_owned(), label);
                     self.mark_update();
 
                     info!("Deployed directory to: {}", target_path.display());
@@ -370,9 +468,11 @@ impl Metadata {
                 },
                 _ => unreachable!(),
             },
-            That(archive_info) => {
-                warn!("Archive info is not resolved, cannot deploy",);
-                self.archive_info = archive_info;
+            That(_) => {
+                warn!(
+                    "Archive of version '{}' is not resolved, cannot deploy",
+                    label
+                );
                 Ok(false)
             },
         }
@@ -412,6 +512,32 @@ impl Metadata {
     }
 }
 
+#[cfg(test)]
+impl Default for Metadata {
+    /// An empty entry with a fresh id, for test fixtures
+    fn default() -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            title: String::new(),
+            alias: Vec::new(),
+            tags: Vec::new(),
+            collection: None,
+            description: None,
+            images: Vec::new(),
+            rating: 0,
+            custom: Default::default(),
+            content_info: Default::default(),
+            archives: Vec::new(),
+            deploy_info: Default::default(),
+            play_info: Default::default(),
+            save_info: Default::default(),
+            create_time: now,
+            update_time: now,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::path::PathBuf;
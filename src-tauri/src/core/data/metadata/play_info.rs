@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Launch settings and play time of an entry, the sessions themselves are kept
+/// in the library
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct PlayInfo {
+    /// Executable relative to the deployment directory, detected on launch if
+    /// not set
+    #[serde(default)]
+    pub executable: Option<String>,
+    /// Total seconds played
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub playtime: u64,
+    #[serde(default)]
+    pub last_played: Option<DateTime<Utc>>,
+}
+
+impl PlayInfo {
+    /// Adds a finished session.
+    pub fn record(&mut self, start: DateTime<Utc>, end: DateTime<Utc>) {
+        self.playtime += (end - start).num_seconds().max(0) as u64;
+        self.last_played = self.last_played.max(Some(end));
+    }
+
+    /// Combines the play time of two records of the same work, the executable
+    /// of `self` is kept if set.
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            executable: self.executable.clone().or(other.executable.clone()),
+            playtime: self.playtime + other.playtime,
+            last_played: self.last_played.max(other.last_played),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Where an entry keeps its save data
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct SaveInfo {
+    /// Save files or directories, relative paths are inside the deployment.
+    /// The last component may contain `*`. Empty uses the defaults of the
+    /// detected [SaveEngine].
+    #[serde(default)]
+    pub locations: Vec<String>,
+}
+
+/// Engines that keep their saves inside the game directory
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum SaveEngine {
+    RpgMakerMv,
+    RpgMakerMz,
+    RpgMakerVxAce,
+    RpgMakerVx,
+    RpgMakerXp,
+    RenPy,
+    WolfRpg,
+    KiriKiri,
+}
+
+impl SaveEngine {
+    /// Marker files of each engine, relative to the game directory
+    const MARKERS: &[(Self, &[&str])] = &[
+        (Self::RpgMakerMv, &["www/js/rpg_core.js"]),
+        (Self::RpgMakerMz, &["js/rmmz_core.js"]),
+        (Self::RpgMakerVxAce, &["Game.rgss3a", "Data/System.rvdata2"]),
+        (Self::RpgMakerVx, &["Game.rgss2a", "Data/System.rvdata"]),
+        (Self::RpgMakerXp, &["Game.rgssad", "Data/System.rxdata"]),
+        (Self::RenPy, &["renpy", "game/script_version.txt"]),
+        (Self::WolfRpg, &["Data.wolf", "Data/BasicData"]),
+        (Self::KiriKiri, &["data.xp3"]),
+    ];
+
+    /// Guesses the engine from the files in `dir`.
+    pub fn detect(dir: &Path) -> Option<Self> {
+        Self::MARKERS
+            .iter()
+            .find(|(_, markers)| markers.iter().any(|m| dir.join(m).exists()))
+            .map(|(engine, _)| *engine)
+    }
+
+    /// Default save locations, relative to the game directory
+    pub fn locations(&self) -> &'static [&'static str] {
+        match self {
+            Self::RpgMakerMv => &["www/save"],
+            Self::RpgMakerMz => &["save"],
+            Self::RpgMakerVxAce => &["Save*.rvdata2"],
+            Self::RpgMakerVx => &["Save*.rvdata"],
+            Self::RpgMakerXp => &["Save*.rxdata"],
+            Self::RenPy => &["game/saves"],
+            Self::WolfRpg => &["Save"],
+            Self::KiriKiri => &["savedata"],
+        }
+    }
+}
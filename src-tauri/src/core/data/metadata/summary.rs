@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::core::data::metadata::{DeployInfo, Metadata};
+
+/// Fields of [Metadata] needed to render a list or grid item
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct MetadataSummary {
+    pub id: Uuid,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub collection: Option<String>,
+    /// Hash of the cover image
+    pub image: Option<String>,
+    pub rating: u8,
+    /// Name of the [super::ContentInfo] variant
+    pub content_type: String,
+    #[ts(type = "number")]
+    pub archive_size: u64,
+    pub deployed: bool,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
+
+impl From<&Metadata> for MetadataSummary {
+    fn from(metadata: &Metadata) -> Self {
+        Self {
+            id: metadata.id,
+            title: metadata.title.clone(),
+            tags: metadata.tags.clone(),
+            collection: metadata.collection.clone(),
+            image: metadata.cover().map(str::to_string),
+            rating: metadata.rating,
+            content_type: metadata.content_info.type_name().to_string(),
+            archive_size: metadata.archive_size(),
+            deployed: metadata.deploy_info != DeployInfo::None,
+            create_time: metadata.create_time,
+            update_time: metadata.update_time,
+        }
+    }
+}
+
+/// One page of [MetadataSummary]
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct SummaryPage {
+    /// Count of all entries, not only this page
+    pub total: u32,
+    pub items: Vec<MetadataSummary>,
+}
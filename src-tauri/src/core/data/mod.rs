@@ -1,13 +1,18 @@
 use anyhow::Result;
 use tauri::AppHandle;
 
-use crate::core::data::library::init_library;
+use crate::core::data::library::{exit_library, init_library};
 
 pub mod library;
 pub mod metadata;
+pub mod query;
 pub mod state;
 
 pub fn init_data(app: &AppHandle) -> Result<()> {
     init_library(app)?;
     Ok(())
 }
+
+pub fn exit_data(app: &AppHandle) {
+    exit_library(app);
+}
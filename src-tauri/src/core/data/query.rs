@@ -0,0 +1,792 @@
+//! A small query language for filtering the library, for example:
+//!
+//! ```text
+//! tag:RPG rating>=4 collection:"Series A" type:game dist:dlsite deployed:true -tag:trial
+//! ```
+//!
+//! Terms separated by whitespace must all match, `OR` joins alternatives, `-`
+//! or `NOT` negates a term and parentheses group terms. A term without a field
+//! is matched against title, alias and tags. Custom fields are addressed as
+//! `field.<name>`, for example `field.completion>=50`.
+
+use std::cmp::Ordering;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Months, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::core::{
+    data::metadata::{
+        ArchiveInfo, ContentInfo, CustomValue, DeployInfo, GameDistribution, Metadata,
+    },
+    util::text::normalize,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Colon,
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextField {
+    Title,
+    Alias,
+    Tag,
+    Collection,
+    Description,
+    Type,
+    Dist,
+    Developer,
+    Publisher,
+    Creator,
+    Version,
+    GameType,
+    Platform,
+    Archive,
+    Id,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberField {
+    Rating,
+    Size,
+    Versions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeField {
+    Created,
+    Updated,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HasField {
+    Image,
+    Cover,
+    Collection,
+    Description,
+    Archive,
+    Alias,
+    Tags,
+    Custom(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cond {
+    /// Bare word, matched against title, alias and tags
+    Any(String),
+    Text(TextField, String),
+    Number(NumberField, Op, u64),
+    /// Time in `[start, end)`
+    Time(TimeField, Op, DateTime<Utc>, DateTime<Utc>),
+    Deployed(bool),
+    Has(HasField),
+    /// Custom field name and the raw value, interpreted by the stored type
+    Custom(String, Op, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Cond(Cond),
+}
+
+/// A parsed query, evaluated with [Query::matches]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Expr);
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self> {
+        // An empty query matches everything, an empty operand is an error
+        if input.trim().is_empty() {
+            return Ok(Self(Expr::And(Vec::new())));
+        }
+        let mut parser = Parser {
+            chars: input.chars().collect(),
+            pos: 0,
+        };
+        let expr = parser.parse_or()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(anyhow!(
+                "Unexpected '{}' at position {}",
+                parser.chars[parser.pos],
+                parser.pos
+            ));
+        }
+        Ok(Self(expr))
+    }
+
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        self.0.eval(metadata)
+    }
+}
+
+/// Keys usable for sorting the library
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub enum SortKey {
+    Title,
+    CreateTime,
+    #[default]
+    UpdateTime,
+    Rating,
+    ArchiveSize,
+}
+
+impl SortKey {
+    pub fn compare(&self, a: &Metadata, b: &Metadata) -> Ordering {
+        match self {
+            Self::Title => a.title.cmp(&b.title),
+            Self::CreateTime => a.create_time.cmp(&b.create_time),
+            Self::UpdateTime => a.update_time.cmp(&b.update_time),
+            Self::Rating => a.rating.cmp(&b.rating),
+            Self::ArchiveSize => a.archive_size().cmp(&b.archive_size()),
+        }
+    }
+}
+
+/// One page of a sorted result
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../../src/api/types.ts")]
+pub struct QueryPage {
+    /// Count of all matching entries, not only this page
+    pub total: u32,
+    pub items: Vec<Metadata>,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes `keyword` if it is the next whole word.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let end = self.pos + keyword.chars().count();
+        if end > self.chars.len() {
+            return false;
+        }
+        let word = self.chars[self.pos..end].iter().collect::<String>();
+        let bounded = self
+            .chars
+            .get(end)
+            .is_none_or(|c| c.is_whitespace() || *c == '(');
+        if word == keyword && bounded {
+            self.pos = end;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut items = vec![self.parse_and()?];
+        loop {
+            self.skip_whitespace();
+            if self.eat_keyword("OR") {
+                items.push(self.parse_and()?);
+            } else {
+                break;
+            }
+        }
+        Ok(if items.len() == 1 {
+            items.remove(0)
+        } else {
+            Expr::Or(items)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some(')') => break,
+                _ => {},
+            }
+            if self.eat_keyword("AND") {
+                continue;
+            }
+            let start = self.pos;
+            if self.eat_keyword("OR") {
+                self.pos = start;
+                break;
+            }
+            items.push(self.parse_unary()?);
+        }
+        if items.is_empty() {
+            return Err(anyhow!("Empty expression at position {}", self.pos));
+        }
+        Ok(if items.len() == 1 {
+            items.remove(0)
+        } else {
+            Expr::And(items)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        self.skip_whitespace();
+        if self.peek() == Some('-') {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            let expr = self.parse_or()?;
+            self.skip_whitespace();
+            if self.peek() != Some(')') {
+                return Err(anyhow!("Missing ')' at position {}", self.pos));
+            }
+            self.pos += 1;
+            return Ok(expr);
+        }
+        self.parse_term().map(Expr::Cond)
+    }
+
+    fn parse_value(&mut self) -> Result<String> {
+        let mut value = String::new();
+        if self.peek() == Some('"') {
+            let start = self.pos;
+            self.pos += 1;
+            loop {
+                match self.peek() {
+                    Some('"') => {
+                        self.pos += 1;
+                        break;
+                    },
+                    Some(c) => {
+                        value.push(c);
+                        self.pos += 1;
+                    },
+                    None => return Err(anyhow!("Unclosed quote at position {}", start)),
+                }
+            }
+        } else {
+            while let Some(c) = self.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                value.push(c);
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn parse_op(&mut self) -> Option<Op> {
+        let (op, len) = match (self.peek()?, self.chars.get(self.pos + 1)) {
+            (':', _) => (Op::Colon, 1),
+            ('>', Some('=')) => (Op::Ge, 2),
+            ('<', Some('=')) => (Op::Le, 2),
+            ('>', _) => (Op::Gt, 1),
+            ('<', _) => (Op::Lt, 1),
+            ('=', _) => (Op::Eq, 1),
+            _ => return None,
+        };
+        self.pos += len;
+        Some(op)
+    }
+
+    fn parse_term(&mut self) -> Result<Cond> {
+        let start = self.pos;
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphabetic() || c == '_' {
+                name.push(c.to_ascii_lowercase());
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        if name == "field"
+            && self.peek() == Some('.')
+            && let Some(custom) = self.parse_custom(start)?
+        {
+            return Ok(custom);
+        }
+        self.pos = start + name.chars().count();
+
+        if !name.is_empty()
+            && let Some(op) = self.parse_op()
+            && let Some(cond) = self.parse_cond(&name, op, start)?
+        {
+            return Ok(cond);
+        }
+
+        // Not a known field, the whole term is a bare word
+        self.pos = start;
+        let value = self.parse_value()?;
+        if value.is_empty() {
+            return Err(anyhow!("Empty term at position {}", start));
+        }
+        Ok(Cond::Any(normalize(&value)))
+    }
+
+    /// Parses `.<name><op><value>` after the `field` prefix.
+    fn parse_custom(&mut self, start: usize) -> Result<Option<Cond>> {
+        self.pos += 1;
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                name.push(c);
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            return Ok(None);
+        }
+        let Some(op) = self.parse_op() else {
+            return Ok(None);
+        };
+        let value = self.parse_value()?;
+        if value.is_empty() {
+            return Err(anyhow!(
+                "Missing value for 'field.{}' at position {}",
+                name,
+                start
+            ));
+        }
+        Ok(Some(Cond::Custom(name, op, value)))
+    }
+
+    fn parse_cond(&mut self, name: &str, op: Op, start: usize) -> Result<Option<Cond>> {
+        let text_field = match name {
+            "title" => Some(TextField::Title),
+            "alias" => Some(TextField::Alias),
+            "tag" => Some(TextField::Tag),
+            "collection" => Some(TextField::Collection),
+            "desc" | "description" => Some(TextField::Description),
+            "type" => Some(TextField::Type),
+            "dist" | "distribution" => Some(TextField::Dist),
+            "dev" | "developer" => Some(TextField::Developer),
+            "pub" | "publisher" => Some(TextField::Publisher),
+            "creator" | "by" => Some(TextField::Creator),
+            "version" => Some(TextField::Version),
+            "gametype" => Some(TextField::GameType),
+            "platform" => Some(TextField::Platform),
+            "archive" | "kind" => Some(TextField::Archive),
+            "id" => Some(TextField::Id),
+            _ => None,
+        };
+        let number_field = match name {
+            "rating" => Some(NumberField::Rating),
+            "size" => Some(NumberField::Size),
+            "versions" => Some(NumberField::Versions),
+            _ => None,
+        };
+        let time_field = match name {
+            "created" => Some(TimeField::Created),
+            "updated" => Some(TimeField::Updated),
+            _ => None,
+        };
+        if text_field.is_none()
+            && number_field.is_none()
+            && time_field.is_none()
+            && !matches!(name, "deployed" | "has")
+        {
+            return Ok(None);
+        }
+
+        let value = self.parse_value()?;
+        if value.is_empty() {
+            return Err(anyhow!(
+                "Missing value for '{}' at position {}",
+                name,
+                start
+            ));
+        }
+        let require_colon = || {
+            if matches!(op, Op::Colon | Op::Eq) {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Field '{}' does not support comparison at position {}",
+                    name,
+                    start
+                ))
+            }
+        };
+
+        if let Some(field) = text_field {
+            require_colon()?;
+            return Ok(Some(Cond::Text(field, normalize(&value))));
+        }
+        if let Some(field) = number_field {
+            let number = match field {
+                NumberField::Rating | NumberField::Versions => value.parse::<u64>().ok(),
+                NumberField::Size => parse_size(&value),
+            }
+            .ok_or_else(|| anyhow!("Invalid number '{}' at position {}", value, start))?;
+            return Ok(Some(Cond::Number(field, op, number)));
+        }
+        if let Some(field) = time_field {
+            let (from, to) = parse_time_range(&value)
+                .ok_or_else(|| anyhow!("Invalid date '{}' at position {}", value, start))?;
+            return Ok(Some(Cond::Time(field, op, from, to)));
+        }
+
+        require_colon()?;
+        match name {
+            "deployed" => match value.to_ascii_lowercase().as_str() {
+                "true" | "yes" => Ok(Some(Cond::Deployed(true))),
+                "false" | "no" => Ok(Some(Cond::Deployed(false))),
+                _ => Err(anyhow!("Invalid bool '{}' at position {}", value, start)),
+            },
+            _ => {
+                if let Some(name) = value.strip_prefix("field.")
+                    && !name.is_empty()
+                {
+                    return Ok(Some(Cond::Has(HasField::Custom(name.to_string()))));
+                }
+                let field = match value.to_ascii_lowercase().as_str() {
+                    "image" | "images" => HasField::Image,
+                    "cover" => HasField::Cover,
+                    "collection" => HasField::Collection,
+                    "desc" | "description" => HasField::Description,
+                    "archive" => HasField::Archive,
+                    "alias" => HasField::Alias,
+                    "tag" | "tags" => HasField::Tags,
+                    _ => return Err(anyhow!("Unknown field '{}' at position {}", value, start)),
+                };
+                Ok(Some(Cond::Has(field)))
+            },
+        }
+    }
+}
+
+/// Parses sizes like `1024`, `500MB` or `1.5GiB`, units are binary.
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 1u64,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => return None,
+    };
+    let number = number.parse::<f64>().ok()?;
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Parses `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into the covered period.
+fn parse_time_range(value: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let parts = value
+        .split('-')
+        .map(|p| p.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (date, span) = match parts.as_slice() {
+        [y] => (NaiveDate::from_ymd_opt(*y as i32, 1, 1)?, Months::new(12)),
+        [y, m] => (NaiveDate::from_ymd_opt(*y as i32, *m, 1)?, Months::new(1)),
+        [y, m, d] => {
+            let date = NaiveDate::from_ymd_opt(*y as i32, *m, *d)?;
+            let from = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?);
+            return Some((from, from + chrono::Duration::days(1)));
+        },
+        _ => return None,
+    };
+    let from = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?);
+    Some((from, from.checked_add_months(span)?))
+}
+
+fn compare<T: PartialOrd>(op: Op, left: T, right: T) -> bool {
+    match op {
+        Op::Colon | Op::Eq => left == right,
+        Op::Gt => left > right,
+        Op::Ge => left >= right,
+        Op::Lt => left < right,
+        Op::Le => left <= right,
+    }
+}
+
+/// Compares `time` against the period `[from, to)`.
+fn compare_time(op: Op, time: DateTime<Utc>, from: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+    match op {
+        Op::Colon | Op::Eq => from <= time && time < to,
+        Op::Gt => time >= to,
+        Op::Ge => time >= from,
+        Op::Lt => time < from,
+        Op::Le => time < to,
+    }
+}
+
+impl Expr {
+    fn eval(&self, metadata: &Metadata) -> bool {
+        match self {
+            Self::And(items) => items.iter().all(|e| e.eval(metadata)),
+            Self::Or(items) => items.iter().any(|e| e.eval(metadata)),
+            Self::Not(expr) => !expr.eval(metadata),
+            Self::Cond(cond) => cond.eval(metadata),
+        }
+    }
+}
+
+impl Cond {
+    fn eval(&self, metadata: &Metadata) -> bool {
+        let contains = |text: &str, value: &str| normalize(text).contains(value);
+        let equals = |text: &str, value: &str| normalize(text) == value;
+        let game = match &metadata.content_info {
+            ContentInfo::Game(data) => Some(data),
+            _ => None,
+        };
+
+        match self {
+            Self::Any(value) => {
+                contains(&metadata.title, value)
+                    || metadata.alias.iter().any(|a| contains(a, value))
+                    || metadata.tags.iter().any(|t| contains(t, value))
+            },
+            Self::Text(field, value) => match field {
+                TextField::Title => contains(&metadata.title, value),
+                TextField::Alias => metadata.alias.iter().any(|a| contains(a, value)),
+                TextField::Tag => metadata.tags.iter().any(|t| equals(t, value)),
+                TextField::Collection => metadata
+                    .collection
+                    .as_deref()
+                    .is_some_and(|c| equals(c, value)),
+                TextField::Description => metadata
+                    .description
+                    .as_deref()
+                    .is_some_and(|d| contains(d, value)),
+                TextField::Type => equals(metadata.content_info.type_name(), value),
+                TextField::Dist => metadata
+                    .content_info
+                    .distribution()
+                    .is_some_and(|d| match d {
+                        GameDistribution::Unknown => value == "unknown",
+                        GameDistribution::Steam(_) => value == "steam",
+                        GameDistribution::DLSite(_) => value == "dlsite" || value == "dl",
+                        GameDistribution::Other(data) => {
+                            value == "other" || equals(&data.name, value)
+                        },
+                    }),
+                TextField::Developer => game
+                    .and_then(|g| g.developer.as_deref())
+                    .is_some_and(|d| contains(d, value)),
+                TextField::Publisher => game
+                    .and_then(|g| g.publisher.as_deref())
+                    .is_some_and(|p| contains(p, value)),
+                TextField::Creator => metadata
+                    .content_info
+                    .creators()
+                    .iter()
+                    .any(|c| contains(c, value)),
+                TextField::Version => metadata
+                    .content_info
+                    .version()
+                    .is_some_and(|v| equals(v, value)),
+                TextField::GameType => {
+                    game.is_some_and(|g| equals(&format!("{:?}", g.game_type), value))
+                },
+                TextField::Platform => game.is_some_and(|g| {
+                    g.sys_platform
+                        .iter()
+                        .any(|p| equals(&format!("{:?}", p), value))
+                }),
+                TextField::Archive => match metadata.archive() {
+                    ArchiveInfo::None => value == "none",
+                    ArchiveInfo::ArchiveFile { .. } => value == "archive",
+                    ArchiveInfo::CommonFile { .. } => value == "file",
+                    ArchiveInfo::Directory { .. } => value == "dir" || value == "directory",
+                },
+                TextField::Id => metadata.id.to_string().starts_with(value.as_str()),
+            },
+            Self::Number(field, op, number) => {
+                let left = match field {
+                    NumberField::Rating => metadata.rating as u64,
+                    NumberField::Size => metadata.archive_size(),
+                    NumberField::Versions => metadata.archives.len() as u64,
+                };
+                compare(*op, left, *number)
+            },
+            Self::Time(field, op, from, to) => {
+                let time = match field {
+                    TimeField::Created => metadata.create_time,
+                    TimeField::Updated => metadata.update_time,
+                };
+                compare_time(*op, time, *from, *to)
+            },
+            Self::Deployed(deployed) => (metadata.deploy_info != DeployInfo::None) == *deployed,
+            Self::Has(field) => match field {
+                HasField::Image => !metadata.images.is_empty(),
+                HasField::Cover => metadata.cover().is_some(),
+                HasField::Collection => metadata.collection.is_some(),
+                HasField::Description => metadata
+                    .description
+                    .as_deref()
+                    .is_some_and(|d| !d.is_empty()),
+                HasField::Archive => !metadata.archives.is_empty(),
+                HasField::Alias => !metadata.alias.is_empty(),
+                HasField::Tags => !metadata.tags.is_empty(),
+                HasField::Custom(name) => metadata.custom.contains_key(name),
+            },
+            Self::Custom(name, op, value) => match metadata.custom.get(name) {
+                None => false,
+                Some(CustomValue::String(text) | CustomValue::Url(text)) => {
+                    matches!(op, Op::Colon | Op::Eq) && contains(text, &normalize(value))
+                },
+                Some(CustomValue::Enum(text)) => {
+                    matches!(op, Op::Colon | Op::Eq) && equals(text, &normalize(value))
+                },
+                Some(CustomValue::Bool(flag)) => {
+                    matches!(op, Op::Colon | Op::Eq)
+                        && match value.to_ascii_lowercase().as_str() {
+                            "true" | "yes" => *flag,
+                            "false" | "no" => !*flag,
+                            _ => false,
+                        }
+                },
+                Some(CustomValue::Number(number)) => {
+                    match (number.as_f64(), value.parse::<f64>()) {
+                        (Some(left), Ok(right)) => compare(*op, left, right),
+                        _ => false,
+                    }
+                },
+                Some(CustomValue::Date(date)) => {
+                    match (date.and_hms_opt(0, 0, 0), parse_time_range(value)) {
+                        (Some(date), Some((from, to))) => {
+                            compare_time(*op, Utc.from_utc_datetime(&date), from, to)
+                        },
+                        _ => false,
+                    }
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        api::dl_site::DLContentType,
+        core::data::metadata::{
+            ArchiveVersion, DLSiteDistributionData, GameData, GameSysPlatform, GameType,
+        },
+    };
+
+    fn metadata() -> Metadata {
+        Metadata {
+            title: String::from("クルセイダー・プリンセス"),
+            alias: vec![String::from("Crusader Princess")],
+            tags: vec![String::from("RPG"), String::from("Fantasy")],
+            collection: Some(String::from("Series A")),
+            rating: 4,
+            custom: [
+                (
+                    String::from("translated_by"),
+                    CustomValue::String(String::from("Fan Team")),
+                ),
+                (
+                    String::from("completion"),
+                    CustomValue::Number(serde_json::Number::from(80)),
+                ),
+                (
+                    String::from("purchased"),
+                    CustomValue::Date(NaiveDate::from_ymd_opt(2024, 5, 2).unwrap()),
+                ),
+            ]
+            .into(),
+            content_info: ContentInfo::Game(GameData {
+                version: String::from("1.0.0"),
+                game_type: GameType::RPG,
+                developer: Some(String::from("Circle")),
+                publisher: None,
+                sys_platform: vec![GameSysPlatform::Windows],
+                distribution: GameDistribution::DLSite(DLSiteDistributionData {
+                    id: String::from("01239331"),
+                    content_type: DLContentType::DoujinR18,
+                }),
+            }),
+            archives: vec![ArchiveVersion {
+                version: String::from("1.0"),
+                archive: ArchiveInfo::ArchiveFile {
+                    size: 3 << 30,
+                    path: String::from("game/dl/RJ01239331"),
+                    password: None,
+                },
+                add_time: Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap(),
+                notes: None,
+                pinned: false,
+            }],
+            create_time: Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap(),
+            update_time: Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn check(query: &str) -> bool {
+        Query::parse(query)
+            .unwrap_or_else(|e| panic!("{query}: {e}"))
+            .matches(&metadata())
+    }
+
+    #[test]
+    fn test_query_match() {
+        assert!(check(
+            r#"tag:RPG rating>=4 collection:"Series A" type:game dist:dlsite deployed:false -tag:trial"#
+        ));
+        assert!(check("プリンセス"));
+        assert!(check("crusader size>2GB size<4GiB archive:archive"));
+        assert!(check("versions:1 -versions>1"));
+        assert!(check("creator:circle -by:other"));
+        assert!(check("created:2024-03 updated>=2024 updated<2025"));
+        assert!(check("(tag:SLG OR gametype:rpg) platform:windows"));
+        assert!(check("NOT has:image has:collection"));
+        assert!(!check("tag:trial"));
+        assert!(!check("rating>4"));
+        assert!(!check("deployed:true OR dev:other"));
+        assert!(!check("created<2024-03-15"));
+        assert!(check(
+            r#"field.translated_by:"fan team" field.completion>=50 field.purchased:2024-05"#
+        ));
+        assert!(check("has:field.completion -has:field.price"));
+        assert!(!check("field.completion<50 OR field.purchased<2024"));
+    }
+
+    #[test]
+    fn test_query_parse_error() {
+        assert!(Query::parse("rating>=high").is_err());
+        assert!(Query::parse("title>3").is_err());
+        assert!(Query::parse("collection:\"Series A").is_err());
+        assert!(Query::parse("(tag:RPG").is_err());
+        assert!(Query::parse("has:nothing").is_err());
+        assert!(Query::parse("tag:a OR").is_err());
+        assert!(Query::parse("OR tag:a").is_err());
+        assert!(Query::parse("()").is_err());
+        assert!(check(" "));
+        assert_eq!(
+            Query::parse("Re:Zero").unwrap(),
+            Query(Expr::Cond(Cond::Any(String::from("re:zero"))))
+        );
+    }
+}
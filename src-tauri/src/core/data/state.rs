@@ -1,35 +1,83 @@
 use std::{
     collections::HashSet,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, PoisonError, RwLock},
 };
 
 use anyhow::{Result, anyhow};
-use redb::Database;
+use redb::{Database, backends::InMemoryBackend};
 
 pub struct DataState {
-    database: Arc<Database>,
+    database: RwLock<Arc<Database>>,
     collection_cache: Mutex<HashSet<String>>,
     deployment_cache: Mutex<HashSet<String>>,
+    launch_cache: Mutex<HashSet<String>>,
 }
 
 impl DataState {
     pub(super) fn new(database: Database) -> Self {
         Self {
-            database: Arc::new(database),
+            database: RwLock::new(Arc::new(database)),
             collection_cache: Mutex::new(HashSet::new()),
             deployment_cache: Mutex::new(HashSet::new()),
+            launch_cache: Mutex::new(HashSet::new()),
         }
     }
 
-    /// Directly returns a reference to the database.
-    pub fn database_ref(&self) -> &Database {
-        &self.database
-    }
-
     /// Returns a clone of the database wrapped in an `Arc`.
     /// Use this across threads to avoid ownership issues.
     pub fn database(&self) -> Arc<Database> {
-        Arc::clone(&self.database)
+        // The guarded value is only ever replaced whole, so a poisoned lock
+        // still holds a usable database
+        Arc::clone(&self.database.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Closes the database and installs the one returned by `reopen`, the file
+    /// is free to be replaced inside `reopen`. Callers of [Self::database] wait
+    /// until it is done.
+    ///
+    /// Fails right away without calling `reopen` if another command still
+    /// holds the database. If `reopen` fails, the database returned by
+    /// `fallback` is installed instead, which should put back the previous
+    /// file, and the error of `reopen` is returned. An empty in-memory database
+    /// is left in place only if both fail.
+    pub(super) fn database_reopen(
+        &self,
+        reopen: impl FnOnce() -> Result<Database>,
+        fallback: impl FnOnce() -> Result<Database>,
+    ) -> Result<()> {
+        let mut guard = self
+            .database
+            .write()
+            .map_err(|e| anyhow!("Failed to lock database: {}", e))?;
+        let placeholder = Database::builder().create_with_backend(InMemoryBackend::new())?;
+        // No one can clone the database while the lock is held, so other
+        // holders only go away and there is nothing to wait for
+        let current = std::mem::replace(&mut *guard, Arc::new(placeholder));
+        match Arc::try_unwrap(current) {
+            Ok(database) => drop(database),
+            Err(database) => {
+                *guard = database;
+                return Err(anyhow!("Library is busy, try again later"));
+            },
+        }
+
+        match reopen() {
+            Ok(database) => {
+                *guard = Arc::new(database);
+                Ok(())
+            },
+            Err(e) => match fallback() {
+                Ok(database) => {
+                    *guard = Arc::new(database);
+                    Err(e)
+                },
+                Err(fallback) => Err(anyhow!(
+                    "Failed to reopen database: {}, and to fall back: {}",
+                    e,
+                    fallback
+                )),
+            },
+        }
     }
 
     pub fn collection_cache(&self) -> &Mutex<HashSet<String>> {
@@ -57,4 +105,67 @@ impl DataState {
         f(&mut deployments);
         Ok(())
     }
+
+    /// Ids of the entries with a running process
+    pub fn launch_cache(&self) -> &Mutex<HashSet<String>> {
+        &self.launch_cache
+    }
+
+    pub fn launch_cache_edit(&self, f: impl FnOnce(&mut HashSet<String>)) -> Result<()> {
+        let mut running = self
+            .launch_cache
+            .lock()
+            .map_err(|e| anyhow!("Failed to lock launch temp set: {}", e))?;
+        f(&mut running);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use redb::TableDefinition;
+
+    use super::*;
+
+    const TABLE: TableDefinition<&str, u8> = TableDefinition::new("test");
+
+    fn database(value: u8) -> Result<Database> {
+        let db = Database::builder().create_with_backend(InMemoryBackend::new())?;
+        let write = db.begin_write()?;
+        write.open_table(TABLE)?.insert("value", value)?;
+        write.commit()?;
+        Ok(db)
+    }
+
+    fn value(state: &DataState) -> u8 {
+        let read = state.database().begin_read().unwrap();
+        read.open_table(TABLE)
+            .unwrap()
+            .get("value")
+            .unwrap()
+            .unwrap()
+            .value()
+    }
+
+    #[test]
+    fn test_database_reopen() {
+        let state = DataState::new(database(1).unwrap());
+        state
+            .database_reopen(|| database(2), || database(1))
+            .unwrap();
+        assert_eq!(value(&state), 2);
+
+        let failed = state.database_reopen(|| Err(anyhow!("broken")), || database(2));
+        assert_eq!(failed.unwrap_err().to_string(), "broken");
+        assert_eq!(value(&state), 2);
+
+        let held = state.database();
+        let busy = state.database_reopen(|| database(3), || database(2));
+        assert_eq!(
+            busy.unwrap_err().to_string(),
+            "Library is busy, try again later"
+        );
+        assert_eq!(value(&state), 2);
+        drop(held);
+    }
 }
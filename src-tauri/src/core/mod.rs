@@ -9,7 +9,7 @@ use ts_rs::TS;
 
 use crate::core::{
     config::{ConfigState, init_config},
-    data::{init_data, state::DataState},
+    data::{exit_data, init_data, state::DataState},
 };
 
 pub mod config;
@@ -57,6 +57,12 @@ fn init_core_internal(app: &AppHandle) -> Result<()> {
     Ok(())
 }
 
+/// Runs the shutdown tasks of the core components.
+pub fn exit_core(app: &AppHandle) {
+    info!("Core shutdown started");
+    exit_data(app);
+}
+
 pub trait StringResult<T, E>
 where E: std::fmt::Display
 {
@@ -11,11 +11,35 @@ use tokio::fs as tfs;
 const EVENT_COMPRESSION_PROGRESS: &str = "compression_progress";
 const EVENT_DECOMPRESSION_PROGRESS: &str = "decompression_progress";
 
+/// Archive format written by [compress_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressFormat {
+    SevenZip,
+    Zip,
+}
+
 pub async fn compress(
     app: &AppHandle,
     input_dir: impl AsRef<Path>,
     output_file: impl AsRef<Path>,
     password: Option<&str>,
+) -> Result<()> {
+    compress_as(
+        app,
+        input_dir,
+        output_file,
+        password,
+        CompressFormat::SevenZip,
+    )
+    .await
+}
+
+pub async fn compress_as(
+    app: &AppHandle,
+    input_dir: impl AsRef<Path>,
+    output_file: impl AsRef<Path>,
+    password: Option<&str>,
+    format: CompressFormat,
 ) -> Result<()> {
     let shell = app.shell();
     let command = shell
@@ -23,10 +47,11 @@ pub async fn compress(
         .map_err(|e| anyhow!("Failed to get 7z sidecar: {e}"))?;
 
     info!(
-        "Compressing: input_dir: {}, output_file: {}, password: {:?}",
+        "Compressing: input_dir: {}, output_file: {}, password: {:?}, format: {:?}",
         input_dir.as_ref().display(),
         output_file.as_ref().display(),
-        password
+        password,
+        format
     );
 
     let input_path = input_dir.as_ref().to_owned();
@@ -40,19 +65,17 @@ pub async fn compress(
         info!("Removed existing output file: {}", output_path.display());
     }
 
-    let mut command = command
-        .arg("a")
-        .arg(&output_path)
-        .arg(input_path.join("*"))
-        .arg("-t7z")
-        .arg("-mx9")
-        .arg("-ms4g")
-        .arg("-md64m")
-        .arg("-mmt")
-        .arg("-r")
-        .arg("-bsp1");
+    let command = command.arg("a").arg(&output_path).arg(input_path.join("*"));
+    let command = match format {
+        CompressFormat::SevenZip => command.args(["-t7z", "-mx9", "-ms4g", "-md64m"]),
+        CompressFormat::Zip => command.args(["-tzip", "-mx5"]),
+    };
+    let mut command = command.arg("-mmt").arg("-r").arg("-bsp1");
     if let Some(pwd) = password {
-        command = command.arg(format!("-p{pwd}")).arg("-mhe");
+        command = command.arg(format!("-p{pwd}"));
+        if format == CompressFormat::SevenZip {
+            command = command.arg("-mhe");
+        }
     }
 
     let regex = Regex::new(r"^\s*(\d+)%\s+(\d+)\s+\+\s+(.+)$")
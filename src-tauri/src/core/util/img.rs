@@ -2,7 +2,7 @@ use std::path::Path;
 
 use anyhow::{Result, anyhow};
 use image::{DynamicImage, ImageFormat, RgbaImage};
-use image_hasher::HasherConfig;
+use image_hasher::{HasherConfig, ImageHash};
 use log::info;
 use tauri::{AppHandle, async_runtime};
 use tokio::fs as tfs;
@@ -49,6 +49,17 @@ pub async fn process_image_bytes(data: (Vec<u8>, u32, u32), app: AppHandle) -> R
     process_image_internal(img, app).await
 }
 
+/// Makes a base64 image hash usable as file name
+fn image_name_encode(hash: &str) -> String {
+    hash.replace('/', "_").replace('+', "-")
+}
+
+/// Decodes the perceptual hash from an image name returned by the
+/// `process_image_*` functions.
+pub fn image_name_hash(name: &str) -> Option<ImageHash> {
+    ImageHash::from_base64(&name.replace('_', "/").replace('-', "+")).ok()
+}
+
 async fn process_image_internal(img: DynamicImage, app: AppHandle) -> Result<String> {
     let width = img.width();
     let height = img.height();
@@ -74,7 +85,7 @@ async fn process_image_internal(img: DynamicImage, app: AppHandle) -> Result<Str
         let hasher = HasherConfig::new().to_hasher();
         let hash = hasher.hash_image(&img);
         let hash_str = hash.to_base64();
-        (hash_str.replace('/', "_").replace('+', "-"), img)
+        (image_name_encode(&hash_str), img)
     })
     .await?;
     let (hash_str, img) = hash_str;
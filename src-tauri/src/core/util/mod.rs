@@ -3,6 +3,7 @@ use const_format::formatc;
 pub mod compress;
 pub mod img;
 pub mod path_ext;
+pub mod text;
 
 #[cfg(not(debug_assertions))]
 pub const APP_ROOT: &str = ".";
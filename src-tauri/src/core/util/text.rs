@@ -0,0 +1,149 @@
+/// Half-width katakana `U+FF61..=U+FF9F` mapped to their full-width forms
+const HALF_WIDTH_KANA: [char; 63] = [
+    '。', '「', '」', '、', '・', 'ヲ', 'ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ャ', 'ュ', 'ョ', 'ッ', 'ー',
+    'ア', 'イ', 'ウ', 'エ', 'オ', 'カ', 'キ', 'ク', 'ケ', 'コ', 'サ', 'シ', 'ス', 'セ', 'ソ', 'タ',
+    'チ', 'ツ', 'テ', 'ト', 'ナ', 'ニ', 'ヌ', 'ネ', 'ノ', 'ハ', 'ヒ', 'フ', 'ヘ', 'ホ', 'マ', 'ミ',
+    'ム', 'メ', 'モ', 'ヤ', 'ユ', 'ヨ', 'ラ', 'リ', 'ル', 'レ', 'ロ', 'ワ', 'ン', '゛', '゜',
+];
+
+const SOUND_MARK_VOICED: char = '゛';
+const SOUND_MARK_SEMI_VOICED: char = '゜';
+
+/// Folds a text for searching:
+/// - full-width ASCII and half-width katakana are converted to their common
+///   width
+/// - katakana are converted to hiragana
+/// - everything is lowercased
+pub fn normalize(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '\u{FF61}'..='\u{FF9F}' => {
+                let base = HALF_WIDTH_KANA[(c as u32 - 0xFF61) as usize];
+                match chars.peek() {
+                    Some(&'\u{FF9E}') => {
+                        chars.next();
+                        compose_sound_mark(base, SOUND_MARK_VOICED)
+                    },
+                    Some(&'\u{FF9F}') => {
+                        chars.next();
+                        compose_sound_mark(base, SOUND_MARK_SEMI_VOICED)
+                    },
+                    _ => base,
+                }
+            },
+            _ => c,
+        };
+        let c = match c {
+            '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        };
+        result.extend(c.to_lowercase());
+    }
+
+    result
+}
+
+fn compose_sound_mark(base: char, mark: char) -> char {
+    let offset = match (base, mark) {
+        ('ウ', SOUND_MARK_VOICED) => return 'ヴ',
+        (
+            'カ' | 'キ' | 'ク' | 'ケ' | 'コ' | 'サ' | 'シ' | 'ス' | 'セ' | 'ソ' | 'タ' | 'チ'
+            | 'ツ' | 'テ' | 'ト' | 'ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ',
+            SOUND_MARK_VOICED,
+        ) => 1,
+        ('ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ', SOUND_MARK_SEMI_VOICED) => 2,
+        _ => 0,
+    };
+    char::from_u32(base as u32 + offset).unwrap_or(base)
+}
+
+/// Whether the char belongs to a script written without spaces, such texts
+/// are split into n-grams instead of words.
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FA}' | '\u{30FC}'..='\u{30FF}' // Hiragana, Katakana except '・'
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul Syllables
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+    )
+}
+
+/// Splits a text into search terms after [normalize].
+///
+/// Alphanumeric runs become one term each, CJK runs become their unigrams and
+/// bigrams so that any substring of two chars or more can be matched.
+/// Duplicated terms are kept, the count is used as term frequency.
+pub fn tokenize(text: &str) -> Vec<String> {
+    fn flush_cjk(run: &mut Vec<char>, terms: &mut Vec<String>) {
+        for (i, c) in run.iter().enumerate() {
+            terms.push(c.to_string());
+            if let Some(next) = run.get(i + 1) {
+                terms.push([*c, *next].iter().collect());
+            }
+        }
+        run.clear();
+    }
+
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    let mut cjk = Vec::new();
+
+    for c in normalize(text).chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+            cjk.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk, &mut terms);
+            word.push(c);
+        } else {
+            flush_cjk(&mut cjk, &mut terms);
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+        }
+    }
+    flush_cjk(&mut cjk, &mut terms);
+    if !word.is_empty() {
+        terms.push(word);
+    }
+
+    terms
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("ＲＰＧ　Ｍａｋｅｒ"), "rpg maker");
+        assert_eq!(normalize("カタカナ"), "かたかな");
+        assert_eq!(normalize("ｶﾞｲﾄﾞ"), "がいど");
+        assert_eq!(normalize("ﾊﾟﾝ"), "ぱん");
+        assert_eq!(normalize("ｳﾞｧ"), "ゔぁ");
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("Hello, World"), vec!["hello", "world"]);
+        assert_eq!(
+            tokenize("救出のお嬢様"),
+            vec![
+                "救", "救出", "出", "出の", "の", "のお", "お", "お嬢", "嬢", "嬢様", "様"
+            ]
+        );
+        assert_eq!(
+            tokenize("RJ01239331 ゲーム"),
+            vec!["rj01239331", "げ", "げー", "ー", "ーむ", "む"]
+        );
+        assert_eq!(tokenize("ゲーム"), tokenize("ｹﾞｰﾑ"));
+    }
+}
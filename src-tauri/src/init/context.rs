@@ -1,7 +1,14 @@
-use tauri::{Builder, Wry, generate_context};
+use tauri::{Builder, RunEvent, Wry, generate_context};
+
+use crate::core::exit_core;
 
 pub fn run(builder: Builder<Wry>) {
     builder
-        .run(generate_context!())
-        .expect("Error while running application");
+        .build(generate_context!())
+        .expect("Error while building application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                exit_core(app);
+            }
+        });
 }
@@ -5,28 +5,85 @@ use crate::cmd::*;
 pub fn register_invoke_handler(builder: Builder<Wry>) -> Builder<Wry> {
     builder.invoke_handler(generate_handler![
         metadata_update,
+        metadata_bulk_edit,
         metadata_get_all,
+        metadata_list,
         metadata_get,
         metadata_delete,
         metadata_deploy,
         metadata_deploy_off,
+        metadata_archive_version_update,
+        metadata_archive_version_remove,
+        metadata_archive_retention_apply,
         metadata_collection_cache,
         metadata_deployment_cache,
+        metadata_collection_list,
+        metadata_collection_members,
+        metadata_collection_save,
+        metadata_collection_rename,
+        metadata_collection_reorder,
+        metadata_index_query,
+        metadata_index_tags,
+        metadata_tag_list,
+        metadata_tag_save,
+        metadata_tag_rename,
+        metadata_tag_merge,
+        metadata_tag_delete,
+        metadata_recent,
+        metadata_search,
+        metadata_query,
+        metadata_stats,
+        metadata_duplicates,
+        metadata_duplicate_merge,
+        metadata_image_import,
+        metadata_relation_add,
+        metadata_relation_remove,
+        metadata_relation_graph,
+        metadata_launch,
+        metadata_launch_candidates,
+        metadata_launch_set,
+        metadata_launch_running,
+        metadata_play_sessions,
+        metadata_save_locations,
+        metadata_save_locations_set,
+        metadata_save_snapshot,
+        metadata_save_snapshots,
+        metadata_save_restore,
+        metadata_history,
+        metadata_revert,
+        metadata_undo,
+        metadata_trash_list,
+        metadata_trash_restore,
+        metadata_trash_purge,
         metadata_export,
         metadata_import,
+        metadata_import_preview,
+        backup_list,
+        backup_create,
+        backup_verify,
+        backup_restore,
         util_process_img_file,
         util_process_img_web,
         util_process_img_bytes,
         util_clear_unused_images,
         util_clear_unused_deploy_dirs,
         util_recalculate_archive_size,
+        util_migration_report,
+        util_doctor_check,
+        util_doctor_repair,
         util_dl_fetch_info,
+        util_provider_list,
+        util_provider_lookup,
+        util_provider_search,
         util_dark_state,
         open_config_dir,
         open_log_dir,
         path_resolve_img,
         path_resolve_archive,
         config_get,
-        config_update
+        config_update,
+        config_profile_save,
+        config_profile_remove,
+        config_profile_switch
     ])
 }
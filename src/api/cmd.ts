@@ -1,10 +1,49 @@
 import type {
   AppConfig,
+  ArchiveVersionArg,
+  BackupInfo,
+  BulkEditArg,
+  BulkEditResult,
+  CollectionArg,
+  CollectionRecord,
   DeployArg,
   DLFetchArg,
   DLFetchInfo,
+  DoctorIssue,
+  DoctorReport,
+  DuplicateGroup,
+  DuplicateMergeArg,
+  ExportArg,
+  HistoryRecord,
+  ImageImportArg,
+  ImageImportReport,
+  ImportArg,
+  ImportReport,
+  IndexQueryArg,
+  LibraryProfile,
+  LibraryStats,
+  ListArg,
   Metadata,
   MetadataOption,
+  MetadataSummary,
+  MigrationReport,
+  PlaySession,
+  ProviderHit,
+  ProviderInfo,
+  ProviderLookupArg,
+  ProviderResult,
+  ProviderSearchArg,
+  QueryArg,
+  QueryPage,
+  RelationGraph,
+  RelationKind,
+  SaveLocations,
+  SaveSnapshot,
+  SearchResult,
+  SummaryPage,
+  TagInfo,
+  TagRecord,
+  TrashEntry,
 } from '@/api/types.ts';
 import { invoke } from '@tauri-apps/api/core';
 
@@ -13,10 +52,18 @@ export class Command {
     return await invoke('metadata_update', { opt });
   }
 
+  static async metadataBulkEdit(arg: BulkEditArg): Promise<BulkEditResult[]> {
+    return await invoke('metadata_bulk_edit', { arg });
+  }
+
   static async metadataGetAll(): Promise<Metadata[]> {
     return await invoke('metadata_get_all');
   }
 
+  static async metadataList(arg: ListArg): Promise<SummaryPage> {
+    return await invoke('metadata_list', { arg });
+  }
+
   static async metadataGet(key: string): Promise<Metadata | null> {
     return await invoke('metadata_get', { key });
   }
@@ -29,8 +76,20 @@ export class Command {
     return await invoke('metadata_deploy', { key, arg });
   }
 
-  static async metadataDeployOff(key: string): Promise<void> {
-    return await invoke('metadata_deploy_off', { key });
+  static async metadataDeployOff(key: string, force = false): Promise<void> {
+    return await invoke('metadata_deploy_off', { key, force });
+  }
+
+  static async metadataArchiveVersionUpdate(arg: ArchiveVersionArg): Promise<void> {
+    return await invoke('metadata_archive_version_update', { arg });
+  }
+
+  static async metadataArchiveVersionRemove(key: string, version: string): Promise<void> {
+    return await invoke('metadata_archive_version_remove', { key, version });
+  }
+
+  static async metadataArchiveRetentionApply(): Promise<number> {
+    return await invoke('metadata_archive_retention_apply');
   }
 
   static async metadataCollectionCache(): Promise<string[]> {
@@ -41,12 +100,192 @@ export class Command {
     return await invoke('metadata_deployment_cache');
   }
 
-  static async metadataExport(): Promise<void> {
-    return await invoke('metadata_export');
+  static async metadataCollectionList(): Promise<CollectionRecord[]> {
+    return await invoke('metadata_collection_list');
+  }
+
+  static async metadataCollectionMembers(name: string): Promise<MetadataSummary[]> {
+    return await invoke('metadata_collection_members', { name });
+  }
+
+  static async metadataCollectionSave(arg: CollectionArg): Promise<CollectionRecord> {
+    return await invoke('metadata_collection_save', { arg });
+  }
+
+  static async metadataCollectionRename(from: string, to: string): Promise<number> {
+    return await invoke('metadata_collection_rename', { from, to });
+  }
+
+  static async metadataCollectionReorder(name: string, ids: string[]): Promise<void> {
+    return await invoke('metadata_collection_reorder', { name, ids });
+  }
+
+  static async metadataIndexQuery(arg: IndexQueryArg): Promise<Metadata[]> {
+    return await invoke('metadata_index_query', { arg });
+  }
+
+  static async metadataIndexTags(): Promise<[string, number][]> {
+    return await invoke('metadata_index_tags');
+  }
+
+  static async metadataTagList(): Promise<TagInfo[]> {
+    return await invoke('metadata_tag_list');
+  }
+
+  static async metadataTagSave(tag: TagRecord): Promise<number> {
+    return await invoke('metadata_tag_save', { tag });
+  }
+
+  static async metadataTagRename(from: string, to: string): Promise<number> {
+    return await invoke('metadata_tag_rename', { from, to });
+  }
+
+  static async metadataTagMerge(sources: string[], target: string): Promise<number> {
+    return await invoke('metadata_tag_merge', { sources, target });
+  }
+
+  static async metadataTagDelete(name: string): Promise<number> {
+    return await invoke('metadata_tag_delete', { name });
+  }
+
+  static async metadataRecent(limit: number): Promise<Metadata[]> {
+    return await invoke('metadata_recent', { limit });
+  }
+
+  static async metadataSearch(query: string, limit?: number): Promise<SearchResult[]> {
+    return await invoke('metadata_search', { query, limit });
+  }
+
+  static async metadataQuery(arg: QueryArg): Promise<QueryPage> {
+    return await invoke('metadata_query', { arg });
+  }
+
+  static async metadataStats(): Promise<LibraryStats> {
+    return await invoke('metadata_stats');
+  }
+
+  static async metadataDuplicates(minScore?: number): Promise<DuplicateGroup[]> {
+    return await invoke('metadata_duplicates', { minScore });
+  }
+
+  static async metadataDuplicateMerge(arg: DuplicateMergeArg): Promise<Metadata> {
+    return await invoke('metadata_duplicate_merge', { arg });
+  }
+
+  static async metadataImageImport(arg: ImageImportArg): Promise<ImageImportReport> {
+    return await invoke('metadata_image_import', { arg });
+  }
+
+  static async metadataRelationAdd(from: string, kind: RelationKind, to: string): Promise<void> {
+    return await invoke('metadata_relation_add', { from, kind, to });
+  }
+
+  static async metadataRelationRemove(
+    from: string,
+    kind: RelationKind,
+    to: string,
+  ): Promise<boolean> {
+    return await invoke('metadata_relation_remove', { from, kind, to });
+  }
+
+  static async metadataRelationGraph(id: string, depth?: number): Promise<RelationGraph> {
+    return await invoke('metadata_relation_graph', { id, depth });
+  }
+
+  static async metadataLaunch(key: string): Promise<void> {
+    return await invoke('metadata_launch', { key });
+  }
+
+  static async metadataLaunchCandidates(key: string): Promise<string[]> {
+    return await invoke('metadata_launch_candidates', { key });
+  }
+
+  static async metadataLaunchSet(key: string, executable: string | null): Promise<void> {
+    return await invoke('metadata_launch_set', { key, executable });
+  }
+
+  static async metadataLaunchRunning(): Promise<string[]> {
+    return await invoke('metadata_launch_running');
+  }
+
+  static async metadataPlaySessions(key: string): Promise<PlaySession[]> {
+    return await invoke('metadata_play_sessions', { key });
+  }
+
+  static async metadataSaveLocations(key: string): Promise<SaveLocations> {
+    return await invoke('metadata_save_locations', { key });
+  }
+
+  static async metadataSaveLocationsSet(key: string, locations: string[]): Promise<void> {
+    return await invoke('metadata_save_locations_set', { key, locations });
+  }
+
+  static async metadataSaveSnapshot(key: string): Promise<SaveSnapshot> {
+    return await invoke('metadata_save_snapshot', { key });
+  }
+
+  static async metadataSaveSnapshots(key: string): Promise<SaveSnapshot[]> {
+    return await invoke('metadata_save_snapshots', { key });
+  }
+
+  static async metadataSaveRestore(key: string, name: string): Promise<void> {
+    return await invoke('metadata_save_restore', { key, name });
+  }
+
+  static async metadataHistory(key: string): Promise<HistoryRecord[]> {
+    return await invoke('metadata_history', { key });
+  }
+
+  static async metadataRevert(key: string, seq: number): Promise<void> {
+    return await invoke('metadata_revert', { key, seq });
+  }
+
+  static async metadataUndo(count: number): Promise<HistoryRecord[]> {
+    return await invoke('metadata_undo', { count });
+  }
+
+  static async metadataTrashList(): Promise<TrashEntry[]> {
+    return await invoke('metadata_trash_list');
+  }
+
+  static async metadataTrashRestore(key: string): Promise<Metadata> {
+    return await invoke('metadata_trash_restore', { key });
+  }
+
+  static async metadataTrashPurge(keys: string[] | null = null): Promise<number> {
+    return await invoke('metadata_trash_purge', { keys });
+  }
+
+  static async metadataExport(
+    arg: ExportArg = { path: null, format: 'Json', ids: null, query: null, fields: null },
+  ): Promise<string> {
+    return await invoke('metadata_export', { arg });
   }
 
-  static async metadataImport(): Promise<void> {
-    return await invoke('metadata_import');
+  static async metadataImport(
+    arg: ImportArg = { path: null, mode: 'Overwrite' },
+  ): Promise<ImportReport> {
+    return await invoke('metadata_import', { arg });
+  }
+
+  static async metadataImportPreview(arg: ImportArg): Promise<ImportReport> {
+    return await invoke('metadata_import_preview', { arg });
+  }
+
+  static async backupList(): Promise<BackupInfo[]> {
+    return await invoke('backup_list');
+  }
+
+  static async backupCreate(): Promise<BackupInfo> {
+    return await invoke('backup_create');
+  }
+
+  static async backupVerify(name: string): Promise<BackupInfo> {
+    return await invoke('backup_verify', { name });
+  }
+
+  static async backupRestore(name: string): Promise<void> {
+    return await invoke('backup_restore', { name });
   }
 
   static async utilProcessImgFile(source: string): Promise<string> {
@@ -73,10 +312,34 @@ export class Command {
     return await invoke('util_recalculate_archive_size');
   }
 
+  static async utilDoctorCheck(): Promise<DoctorReport> {
+    return await invoke('util_doctor_check');
+  }
+
+  static async utilDoctorRepair(issues: DoctorIssue[]): Promise<number> {
+    return await invoke('util_doctor_repair', { issues });
+  }
+
+  static async utilMigrationReport(): Promise<MigrationReport> {
+    return await invoke('util_migration_report');
+  }
+
   static async utilDlFetchInfo(arg: DLFetchArg): Promise<DLFetchInfo> {
     return await invoke('util_dl_fetch_info', { arg });
   }
 
+  static async utilProviderList(): Promise<ProviderInfo[]> {
+    return await invoke('util_provider_list');
+  }
+
+  static async utilProviderLookup(arg: ProviderLookupArg): Promise<ProviderResult> {
+    return await invoke('util_provider_lookup', { arg });
+  }
+
+  static async utilProviderSearch(arg: ProviderSearchArg): Promise<ProviderHit[]> {
+    return await invoke('util_provider_search', { arg });
+  }
+
   static async openConfigDir(): Promise<void> {
     return await invoke('open_config_dir');
   }
@@ -100,4 +363,16 @@ export class Command {
   static async configUpdate(name: keyof AppConfig, value: unknown): Promise<void> {
     return await invoke('config_update', { name, value });
   }
+
+  static async configProfileSave(profile: LibraryProfile): Promise<AppConfig> {
+    return await invoke('config_profile_save', { profile });
+  }
+
+  static async configProfileRemove(name: string): Promise<AppConfig> {
+    return await invoke('config_profile_remove', { name });
+  }
+
+  static async configProfileSwitch(name: string): Promise<AppConfig> {
+    return await invoke('config_profile_switch', { name });
+  }
 }
@@ -1,16 +1,175 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type AppConfig = { lang: Language, path_data: string, path_deploy: string | null, };
+export type AppConfig = { lang: Language, path_data: string, path_deploy: string | null, 
+/**
+ * Days before a deleted entry is purged from trash, `0` keeps it forever
+ */
+trash_retention_days: number, backup: BackupPolicy, 
+/**
+ * Name of the active profile, `lang` and the paths above are its values
+ */
+profile: string, profiles: Array<LibraryProfile>, 
+/**
+ * Fields users add to every entry, values are stored on each entry
+ */
+custom_fields: Array<CustomField>, archive_retention: ArchiveRetention, 
+/**
+ * Save snapshots kept per entry, older ones are removed, `0` keeps all
+ */
+save_retention: number, 
+/**
+ * History records kept across the library, older ones are dropped, `0`
+ * keeps all
+ */
+history_limit: number, };
 
 /**
  * Represents archive information for a data item, such as size and path
  */
 export type ArchiveInfo = { "type": "None" } | { "type": "ArchiveFile", "data": { size: number, path: string, password: string | null, } } | { "type": "CommonFile", "data": { size: number, path: string, } } | { "type": "Directory", "data": { size: number, path: string, } };
 
+/**
+ * Which old archive versions are removed when an entry gets a new one. The
+ * latest, the deployed and pinned versions are always kept.
+ */
+export type ArchiveRetention = { 
+/**
+ * Versions to keep per entry, older ones are removed, `0` keeps all
+ */
+keep_versions: number, 
+/**
+ * Days after which an old version is removed, `0` keeps it forever
+ */
+keep_days: number, };
+
+/**
+ * One stored version of an entry, the archive it is deployed from
+ */
+export type ArchiveVersion = { 
+/**
+ * Version label, unique within an entry
+ */
+version: string, archive: ArchiveInfo, add_time: string, notes: string | null, 
+/**
+ * Pinned versions are never removed by [ArchiveRetention]
+ */
+pinned: boolean, };
+
+/**
+ * Notes and pin of an archive version, fields not given are kept
+ */
+export type ArchiveVersionArg = { id: string, version: string, 
+/**
+ * New notes, an empty string clears them
+ */
+notes: string | null, pinned: boolean | null, };
+
+/**
+ * Voice works, ASMR and music
+ */
+export type AudioData = { circle: string | null, voice_actors: Array<string>, tracks: Array<AudioTrack>, distribution: GameDistribution, };
+
+export type AudioTrack = { title: string, 
+/**
+ * Length in seconds
+ */
+duration: number | null, };
+
+export type BackupInfo = { name: string, size: number, time: string, 
+/**
+ * Library entries in the backup
+ */
+entries: number, 
+/**
+ * Entries that can not be read
+ */
+unreadable: number, 
+/**
+ * Set if the backup can not be opened
+ */
+error: string | null, };
+
+/**
+ * When and where database backups are taken
+ */
+export type BackupPolicy = { 
+/**
+ * Backups to keep per trigger, older ones are removed, `0` keeps all
+ */
+retention: number, on_startup: boolean, on_shutdown: boolean, before_import: boolean, before_migration: boolean, 
+/**
+ * Hours between periodic backups while running, `0` disables them
+ */
+interval_hours: number, 
+/**
+ * Backup directory, defaults to `backup` in the data directory
+ */
+dir: string | null, };
+
+/**
+ * One change applied to many entries at once
+ */
+export type BulkEdit = { "type": "AddTags", "value": Array<string> } | { "type": "RemoveTags", "value": Array<string> } | { "type": "SetCollection", "value": string | null } | { "type": "SetRating", "value": number } | { "type": "SetDeveloper", "value": string | null } | { "type": "SetPublisher", "value": string | null } | { "type": "SetDistribution", "value": GameDistribution };
+
+/**
+ * Applies `edit` to every entry in `ids` within one transaction
+ */
+export type BulkEditArg = { ids: Array<string>, edit: BulkEdit, };
+
+/**
+ * Outcome of a [BulkEdit] for one id, entries with an error are left
+ * untouched
+ */
+export type BulkEditResult = { id: string, changed: boolean, error: string | null, };
+
+/**
+ * Description and cover of a collection, its members are kept
+ */
+export type CollectionArg = { name: string, description: string | null, cover: string | null, };
+
+/**
+ * A collection and its members in series order. A record without description
+ * and cover only lives as long as it has members.
+ */
+export type CollectionRecord = { name: string, description: string | null, 
+/**
+ * Image hash, same as [Metadata::image]
+ */
+cover: string | null, 
+/**
+ * Member ids in series order
+ */
+members: Array<string>, };
+
+/**
+ * Comics, manga and CG collections
+ */
+export type ComicData = { author: string | null, pages: number | null, direction: ReadingDirection, distribution: GameDistribution, };
+
 /**
  * Represents the type of content for a data item, with detailed information
  */
-export type ContentInfo = { "type": "Undefined" } | { "type": "Game", "data": GameData };
+export type ContentInfo = { "type": "Undefined" } | { "type": "Game", "data": GameData } | { "type": "Comic", "data": ComicData } | { "type": "Audio", "data": AudioData } | { "type": "Video", "data": VideoData } | { "type": "Novel", "data": NovelData } | { "type": "Software", "data": SoftwareData };
+
+/**
+ * Declaration of a user defined field
+ */
+export type CustomField = { 
+/**
+ * Key of the values, letters, digits and `_` only so it can be queried
+ */
+name: string, 
+/**
+ * Display name, the name is shown if empty
+ */
+label: string, kind: CustomFieldKind, };
+
+export type CustomFieldKind = { "type": "String" } | { "type": "Number" } | { "type": "Bool" } | { "type": "Date" } | { "type": "Enum", "options": Array<string> } | { "type": "Url" };
+
+/**
+ * Value of a user defined field, see [CustomField]
+ */
+export type CustomValue = { "type": "String", "value": string } | { "type": "Number", "value": number } | { "type": "Bool", "value": boolean } | { "type": "Date", "value": string } | { "type": "Enum", "value": string } | { "type": "Url", "value": string };
 
 /**
  * Types of content available on the DLSite.
@@ -19,13 +178,63 @@ export type DLContentType = "Doujin" | "Comics" | "PcGames" | "SmartphoneGames"
 
 export type DLFetchArg = { id: string, content_type: DLContentType, };
 
-export type DLFetchInfo = { title: string, circle: string, scenario: Array<string>, illustration: Array<string>, category: Array<string>, tags: Array<string>, description: Array<string>, og_image: string | null, };
+export type DLFetchInfo = { title: string, circle: string, scenario: Array<string>, illustration: Array<string>, category: Array<string>, tags: Array<string>, description: Array<string>, og_image: string | null, 
+/**
+ * Content type guessed from the categories
+ */
+content_info: ContentInfo, };
 
 export type DLSiteDistributionData = { id: string, content_type: DLContentType, };
 
-export type DeployArg = { use_config_dir: boolean, target_dir: string | null, };
+export type DeployArg = { use_config_dir: boolean, target_dir: string | null, 
+/**
+ * Archive version to deploy, the latest one if not set
+ */
+version: string | null, };
+
+export type DeployInfo = { "type": "None" } | { "type": "File", "data": { path: string, 
+/**
+ * Archive version deployed, unknown for deployments made before
+ * versions were tracked
+ */
+version: string | null, } } | { "type": "Directory", "data": { path: string, version: string | null, } };
+
+export type DoctorIssue = { kind: DoctorIssueKind, 
+/**
+ * Entry id, `None` for orphaned archives
+ */
+id: string | null, path: string | null, detail: string, };
+
+/**
+ * A problem found by [doctor_check], each kind has one repair action
+ */
+export type DoctorIssueKind = "ArchiveMissing" | "ArchiveOrphan" | "DeployMissing" | "ImageMissing" | "SizeStale" | "RecordUnreadable";
+
+export type DoctorReport = { entries: number, issues: Array<DoctorIssue>, };
+
+export type DuplicateGroup = { 
+/**
+ * Highest pair score in the group, up to 100
+ */
+score: number, reasons: Array<DuplicateReason>, items: Array<MetadataSummary>, };
+
+/**
+ * Merges the duplicate `remove` into `keep`
+ */
+export type DuplicateMergeArg = { keep: string, remove: string, archive: MergeArchive, };
+
+export type DuplicateReason = "Distribution" | "Title" | "ArchiveSize" | "Cover";
 
-export type DeployInfo = { "type": "None" } | { "type": "File", "data": { path: string, } } | { "type": "Directory", "data": { path: string, } };
+/**
+ * Library export, `path` defaults to `lib.<ext>` in the data directory.
+ * Entries are selected by `ids` and `query` when given, `fields` limits the
+ * written fields or CSV columns, bundles always hold whole entries.
+ */
+export type ExportArg = { path: string | null, format: ExportFormat, ids: Array<string> | null, query: string | null, fields: Array<string> | null, };
+
+export type ExportFormat = "Json" | "JsonLines" | "Csv" | "Bundle";
+
+export type FieldChange = { field: string, before: unknown, after: unknown, };
 
 /**
  * Represents game data, including version, developer, publisher, and platform
@@ -34,7 +243,8 @@ export type DeployInfo = { "type": "None" } | { "type": "File", "data": { path:
 export type GameData = { version: string, game_type: GameType, developer: string | null, publisher: string | null, sys_platform: Array<GameSysPlatform>, distribution: GameDistribution, };
 
 /**
- * Represents the distribution method of a game, such as Steam or DLSite
+ * Represents the distribution method of a game, such as Steam or DLSite. Also
+ * used by the other content types.
  */
 export type GameDistribution = { "type": "Unknown" } | { "type": "Steam", "data": SteamDistributionData } | { "type": "DLSite", "data": DLSiteDistributionData } | { "type": "Other", "data": OtherDistributionData };
 
@@ -48,8 +258,125 @@ export type GameSysPlatform = "Windows" | "Linux" | "MacOS" | "Android" | "IOS"
  */
 export type GameType = "Unspecified" | "RPG" | "SLG" | "AVG";
 
+export type HistoryOp = "Create" | "Patch" | "Delete" | "Deploy" | "DeployOff" | "Import" | "Revert" | "Undo" | "Restore" | "Play";
+
+/**
+ * One recorded operation on a library entry
+ */
+export type HistoryRecord = { seq: number, id: string, time: string, op: HistoryOp, 
+/**
+ * The record reverted by this one, set for [HistoryOp::Undo]
+ */
+undo_of: number | null, changes: Array<FieldChange>, };
+
+/**
+ * Images from `source` added to entry `id` with `role`, at most `limit`
+ * images are processed, 50 by default
+ */
+export type ImageImportArg = { id: string, source: ImageSource, role: ImageRole, limit: number | null, };
+
+export type ImageImportFailure = { 
+/**
+ * Path or URL of the image
+ */
+source: string, error: string, };
+
+export type ImageImportReport = { 
+/**
+ * Images appended to the entry, already present ones are left out
+ */
+added: Array<MetadataImage>, failed: Array<ImageImportFailure>, };
+
+/**
+ * What an image shows, the first [ImageRole::Cover] is displayed in lists
+ */
+export type ImageRole = "Cover" | "Screenshot" | "Banner" | "Sample";
+
+/**
+ * Where [image_import] takes images from
+ */
+export type ImageSource = { "type": "Folder", "value": string } | { "type": "Urls", "value": Array<string> } | { "type": "Entry" };
+
+/**
+ * Library import from a JSON export, `path` defaults to the export file in the
+ * data directory
+ */
+export type ImportArg = { path: string | null, mode: ImportMode, };
+
+export type ImportConflict = { id: string, title: string, 
+/**
+ * Names of the differing fields
+ */
+fields: Array<string>, };
+
+/**
+ * How an imported entry is resolved against an existing one with the same id
+ */
+export type ImportMode = "Overwrite" | "KeepNewer" | "KeepExisting" | "Merge";
+
+export type ImportReport = { 
+/**
+ * Ids not in the library yet
+ */
+added: Array<string>, 
+/**
+ * Ids of existing entries that are written
+ */
+changed: Array<string>, unchanged: number, 
+/**
+ * Existing entries that differ from the imported ones
+ */
+conflicts: Array<ImportConflict>, };
+
+/**
+ * Conditions looked up through the library index tables, all given conditions
+ * must match
+ */
+export type IndexQueryArg = { tags: Array<string>, collection: string | null, content_type: string | null, rating_min: number | null, rating_max: number | null, };
+
 export type Language = "zh-CN" | "en-US" | "ja-JP";
 
+/**
+ * A named library with its own data directory, deploy directory and language
+ */
+export type LibraryProfile = { name: string, path_data: string, path_deploy: string | null, lang: Language, };
+
+export type LibraryStats = { total: number, content_type: Array<StatCount>, 
+/**
+ * Games by store
+ */
+distribution: Array<StatCount>, game_type: Array<StatCount>, rating: Array<StatCount>, 
+/**
+ * Most used first
+ */
+tags: Array<StatCount>, 
+/**
+ * Bytes of all archives
+ */
+archive_size: number, 
+/**
+ * Archive bytes by collection, largest first
+ */
+collection_size: Array<StatCount>, deployed: number, 
+/**
+ * Archive bytes of deployed entries
+ */
+deployed_size: number, 
+/**
+ * Entries created by month as `YYYY-MM`, oldest first
+ */
+growth: Array<StatCount>, };
+
+/**
+ * Sorting and pagination of the summary listing
+ */
+export type ListArg = { sort: SortKey, desc: boolean, offset: number, limit: number | null, };
+
+/**
+ * Which archive versions the merged entry keeps
+ */
+export type MergeArchive = "Keep" | "Remove" | "Both";
+
 /**
  * Basic metadata structure for data item
  */
@@ -80,31 +407,306 @@ collection: string | null,
  */
 description: string | null, 
 /**
- * Image hash, if any
+ * Images in display order
  */
-image: string | null, 
+images: Array<MetadataImage>, 
 /**
  * Rating
  */
 rating: number, 
+/**
+ * Values of the user defined fields, by field name
+ */
+custom: { [key in string]?: CustomValue }, 
 /**
  * The content type of the data item
  */
 content_info: ContentInfo, 
 /**
- * Archive information
+ * Stored versions, oldest first
  */
-archive_info: ArchiveInfo, 
+archives: Array<ArchiveVersion>, 
 /**
  * Deployment information, if any
  */
-deploy_info: DeployInfo, create_time: string, update_time: string, };
+deploy_info: DeployInfo, 
+/**
+ * Launch settings and play time
+ */
+play_info: PlayInfo, 
+/**
+ * Save locations inside or outside the deployment
+ */
+save_info: SaveInfo, create_time: string, update_time: string, };
+
+/**
+ * One image of an entry, the hash returned by the `process_image_*` functions
+ */
+export type MetadataImage = { hash: string, role: ImageRole, };
 
 /**
  * Fields in [Metadata] with optional, used in communication with the frontend
  */
-export type MetadataOption = { id: string | null, title?: string | null, alias: Array<string> | null, tags: Array<string> | null, collection: string | null, description: string | null, image: string | null, rating: number | null, content_info: ContentInfo | null, archive_info: ArchiveInfo | null, flag_create_archive: boolean, };
+export type MetadataOption = { id: string | null, title?: string | null, alias: Array<string> | null, tags: Array<string> | null, collection: string | null, description: string | null, 
+/**
+ * Hash of the cover image, replaces the current cover
+ */
+image: string | null, 
+/**
+ * Replaces all images, applied before `image`
+ */
+images: Array<MetadataImage> | null, rating: number | null, 
+/**
+ * Custom values to set, `null` removes one
+ */
+custom: { [key in string]?: CustomValue | null } | null, content_info: ContentInfo | null, 
+/**
+ * Archive of `archive_version`, [ArchiveInfo::None] removes the version
+ */
+archive_info: ArchiveInfo | null, 
+/**
+ * Version `archive_info` belongs to. Without it the latest version is
+ * replaced, or a created archive is stored as the game version.
+ */
+archive_version: string | null, flag_create_archive: boolean, };
+
+/**
+ * Fields of [Metadata] needed to render a list or grid item
+ */
+export type MetadataSummary = { id: string, title: string, tags: Array<string>, collection: string | null, 
+/**
+ * Hash of the cover image
+ */
+image: string | null, rating: number, 
+/**
+ * Name of the [super::ContentInfo] variant
+ */
+content_type: string, archive_size: number, deployed: boolean, create_time: string, update_time: string, };
+
+export type MigrationFailure = { id: string, 
+/**
+ * The record is a trashed entry
+ */
+trashed: boolean, error: string, };
+
+export type MigrationReport = { from_version: number, to_version: number, total: number, steps: Array<MigrationStepReport>, 
+/**
+ * Records that can not be read after all steps are applied
+ */
+failed: Array<MigrationFailure>, };
+
+export type MigrationStepReport = { version: number, description: string, changed: number, };
+
+/**
+ * Novels and light novels
+ */
+export type NovelData = { author: string | null, publisher: string | null, pages: number | null, distribution: GameDistribution, };
 
 export type OtherDistributionData = { name: string, id: string, };
 
+/**
+ * Launch settings and play time of an entry, the sessions themselves are kept
+ * in the library
+ */
+export type PlayInfo = { 
+/**
+ * Executable relative to the deployment directory, detected on launch if
+ * not set
+ */
+executable: string | null, 
+/**
+ * Total seconds played
+ */
+playtime: number, last_played: string | null, };
+
+export type PlaySession = { start: string, end: string, 
+/**
+ * Seconds between start and end
+ */
+duration: number, };
+
+/**
+ * What a provider supports
+ */
+export type ProviderCapabilities = { 
+/**
+ * [MetadataProvider::lookup] by the id on the site
+ */
+lookup: boolean, 
+/**
+ * [MetadataProvider::search] by keyword
+ */
+search: boolean, 
+/**
+ * Results carry image URLs
+ */
+images: boolean, };
+
+/**
+ * A search result, looked up by its id
+ */
+export type ProviderHit = { id: string, title: string, image: string | null, };
+
+export type ProviderInfo = { name: string, capabilities: ProviderCapabilities, };
+
+export type ProviderLookupArg = { 
+/**
+ * Name of a registered provider
+ */
+provider: string, id: string, };
+
+/**
+ * A work as found by a provider
+ */
+export type ProviderResult = { provider: string, 
+/**
+ * Id on the site, accepted by [MetadataProvider::lookup]
+ */
+id: string, title: string, alias: Array<string>, description: string | null, tags: Array<string>, 
+/**
+ * Developers, authors and performers
+ */
+creators: Array<string>, 
+/**
+ * Image URLs, the cover first
+ */
+images: Array<string>, content_info: ContentInfo, };
+
+export type ProviderSearchArg = { 
+/**
+ * Name of a registered provider
+ */
+provider: string, keyword: string, };
+
+/**
+ * Query string with sorting and pagination, see [crate::core::data::query]
+ */
+export type QueryArg = { query: string, sort: SortKey, desc: boolean, offset: number, limit: number | null, };
+
+/**
+ * One page of a sorted result
+ */
+export type QueryPage = { 
+/**
+ * Count of all matching entries, not only this page
+ */
+total: number, items: Array<Metadata>, };
+
+/**
+ * Page order of a comic
+ */
+export type ReadingDirection = "RightToLeft" | "LeftToRight" | "Vertical";
+
+export type RelationEdge = { from: string, kind: RelationKind, to: string, };
+
+export type RelationGraph = { nodes: Array<MetadataSummary>, 
+/**
+ * Each link once, as seen from the entry nearer to the root
+ */
+edges: Array<RelationEdge>, };
+
+/**
+ * Type of a link from one entry to another, read as "entry is `kind` other"
+ */
+export type RelationKind = "SequelOf" | "PrequelOf" | "DlcOf" | "HasDlc" | "PatchFor" | "HasPatch" | "TranslationOf" | "HasTranslation" | "RemakeOf" | "HasRemake" | "BundledWith";
+
+/**
+ * Engines that keep their saves inside the game directory
+ */
+export type SaveEngine = "RpgMakerMv" | "RpgMakerMz" | "RpgMakerVxAce" | "RpgMakerVx" | "RpgMakerXp" | "RenPy" | "WolfRpg" | "KiriKiri";
+
+/**
+ * Where an entry keeps its save data
+ */
+export type SaveInfo = { 
+/**
+ * Save files or directories, relative paths are inside the deployment.
+ * The last component may contain `*`. Empty uses the defaults of the
+ * detected [SaveEngine].
+ */
+locations: Array<string>, };
+
+export type SaveLocations = { 
+/**
+ * Engine detected in the deployment
+ */
+engine: SaveEngine | null, 
+/**
+ * Configured locations, or the defaults of the engine
+ */
+locations: Array<string>, 
+/**
+ * Whether `locations` are the defaults of the engine
+ */
+default: boolean, 
+/**
+ * Save files and directories found at the locations
+ */
+paths: Array<string>, };
+
+export type SaveSnapshot = { 
+/**
+ * Directory name, used to restore the snapshot
+ */
+name: string, time: string, trigger: SaveTrigger, 
+/**
+ * Locations at the time of the snapshot, in the order they are stored
+ */
+locations: Array<string>, files: number, size: number, };
+
+/**
+ * What caused a snapshot, appended to its name
+ */
+export type SaveTrigger = "Manual" | "DeployOff" | "Restore";
+
+export type SearchResult = { score: number, metadata: Metadata, };
+
+/**
+ * Applications, tools and assets
+ */
+export type SoftwareData = { version: string, developer: string | null, sys_platform: Array<GameSysPlatform>, distribution: GameDistribution, };
+
+/**
+ * Keys usable for sorting the library
+ */
+export type SortKey = "Title" | "CreateTime" | "UpdateTime" | "Rating" | "ArchiveSize";
+
+export type StatCount = { key: string, value: number, };
+
 export type SteamDistributionData = { app_id: string, };
+
+/**
+ * One page of [MetadataSummary]
+ */
+export type SummaryPage = { 
+/**
+ * Count of all entries, not only this page
+ */
+total: number, items: Array<MetadataSummary>, };
+
+export type TagInfo = { tag: TagRecord, 
+/**
+ * Whether the tag is in the registry or only used by entries
+ */
+registered: boolean, 
+/**
+ * Entries using the tag
+ */
+count: number, };
+
+/**
+ * A registered tag, entries using one of its aliases are written with the
+ * canonical name instead
+ */
+export type TagRecord = { name: string, aliases: Array<string>, parent: string | null, color: string | null, category: string | null, };
+
+export type TrashEntry = { metadata: Metadata, delete_time: string, };
+
+/**
+ * Anime and other videos
+ */
+export type VideoData = { studio: string | null, 
+/**
+ * Length in seconds
+ */
+duration: number | null, distribution: GameDistribution, };
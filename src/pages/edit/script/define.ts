@@ -11,6 +11,11 @@ export const enum ArchiveTypeEnum {
 export const enum ContentTypeEnum {
   Undefined = 'Undefined',
   Game = 'Game',
+  Comic = 'Comic',
+  Audio = 'Audio',
+  Video = 'Video',
+  Novel = 'Novel',
+  Software = 'Software',
 }
 
 export const GameTypeEnum = {
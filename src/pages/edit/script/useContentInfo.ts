@@ -30,6 +30,31 @@ const defaultGameData = (): GameData => ({
   },
 });
 
+// Default content of each type other than Game and Undefined
+const defaultOtherContentInfo = (type: ContentInfo['type']): ContentInfo | undefined => {
+  const distribution: GameDistribution = { type: GameDistributionEnum.Unknown };
+  switch (type) {
+    case ContentTypeEnum.Comic:
+      return {
+        type,
+        data: { author: null, pages: null, direction: 'RightToLeft', distribution },
+      };
+    case ContentTypeEnum.Audio:
+      return { type, data: { circle: null, voice_actors: [], tracks: [], distribution } };
+    case ContentTypeEnum.Video:
+      return { type, data: { studio: null, duration: null, distribution } };
+    case ContentTypeEnum.Novel:
+      return { type, data: { author: null, publisher: null, pages: null, distribution } };
+    case ContentTypeEnum.Software:
+      return {
+        type,
+        data: { version: '1.0.0', developer: null, sys_platform: [], distribution },
+      };
+    default:
+      return undefined;
+  }
+};
+
 export const useContentInfo = (edit: UseEdit) => {
   const { t } = useI18n();
   const { editData, updateField } = edit;
@@ -418,8 +443,9 @@ export const useContentInfo = (edit: UseEdit) => {
           break;
         }
         default: {
-          console.warn(`Unsupported content type change to ${val}`);
-          updateField('content_info', defaultContentInfo());
+          const other = defaultOtherContentInfo(val);
+          if (!other) console.warn(`Unsupported content type change to ${val}`);
+          updateField('content_info', other ?? defaultContentInfo());
         }
       }
     },
@@ -465,6 +491,10 @@ export const useContentInfo = (edit: UseEdit) => {
       if (data.description && !editData.value.description) {
         updateField('description', data.description.join('\n'));
       }
+      if (data.content_info.type !== ContentTypeEnum.Game) {
+        // Not a game after all, keep the DLSite id under the guessed type
+        updateField('content_info', data.content_info);
+      }
       if (data.og_image && !editData.value.image) {
         loading.show({
           message: t('page.edit.content-info.loading.fetch-info-image', [
@@ -54,10 +54,13 @@ export const useEdit = (id: Ref<string>, formRef: Ref<QForm>) => {
       tags: copy?.tags ?? null,
       collection: copy?.collection ?? null,
       description: copy?.description ?? null,
-      image: copy?.image ?? null,
+      image: copy?.images.find((i) => i.role === 'Cover')?.hash ?? null,
+      images: null,
       rating: copy?.rating ?? null,
+      custom: null,
       content_info: copy?.content_info ?? null,
-      archive_info: copy?.archive_info ?? null,
+      archive_info: copy?.archives.at(-1)?.archive ?? null,
+      archive_version: null,
       flag_create_archive: false,
     };
   };
@@ -25,6 +25,11 @@ export const useEditDefine = () => {
   const contentTypeOptions = computed((): QSelectOption[] => [
     { label: t('page.edit.content-info.options.type.undefined'), value: ContentTypeEnum.Undefined },
     { label: t('page.edit.content-info.options.type.game'), value: ContentTypeEnum.Game },
+    { label: t('page.edit.content-info.options.type.comic'), value: ContentTypeEnum.Comic },
+    { label: t('page.edit.content-info.options.type.audio'), value: ContentTypeEnum.Audio },
+    { label: t('page.edit.content-info.options.type.video'), value: ContentTypeEnum.Video },
+    { label: t('page.edit.content-info.options.type.novel'), value: ContentTypeEnum.Novel },
+    { label: t('page.edit.content-info.options.type.software'), value: ContentTypeEnum.Software },
   ]);
 
   const gameTypeOptions = computed((): QSelectOption[] => [
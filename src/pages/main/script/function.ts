@@ -21,8 +21,12 @@ export function extractArchiveSize(archiveInfo: ArchiveInfo): number {
   }
 }
 
+export function extractArchivesSize(data: Metadata): number {
+  return data.archives.reduce((sum, version) => sum + extractArchiveSize(version.archive), 0);
+}
+
 export function isDeployable(data: Metadata): boolean {
-  return data.archive_info.type !== 'None' && data.deploy_info.type === 'None';
+  return data.archives.length > 0 && data.deploy_info.type === 'None';
 }
 
 export function isDeployed(data: Metadata): boolean {
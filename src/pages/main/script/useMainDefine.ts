@@ -5,7 +5,7 @@ import { computed } from 'vue';
 import { useI18n } from 'vue-i18n';
 import { formatBytes } from '@/api/util';
 import { FilterTypeEnum } from '@/pages/main/script/define';
-import { extractArchiveSize, joinArray } from '@/pages/main/script/function';
+import { extractArchivesSize, joinArray } from '@/pages/main/script/function';
 
 export const useMainDefine = () => {
   const { t } = useI18n();
@@ -60,7 +60,7 @@ export const useMainDefine = () => {
       name: 'archive_size',
       label: t('page.main.table.archive-size'),
       classes: 'r-no-sel',
-      field: (row) => formatBytes(extractArchiveSize(row.archive_info)),
+      field: (row) => formatBytes(extractArchivesSize(row)),
     },
     {
       name: 'create_time',
@@ -81,7 +81,7 @@ export const useMainDefine = () => {
     {
       name: 'image',
       label: t('page.main.table.image'),
-      field: 'image',
+      field: (row: Metadata) => row.images.find((i) => i.role === 'Cover')?.hash ?? null,
     },
   ]);
 
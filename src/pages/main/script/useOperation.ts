@@ -19,7 +19,7 @@ export const useOperation = () => {
   const { t } = useI18n();
   const { sync } = useDatabaseStore();
   const { syncDeploymentCache } = useTableStore();
-  const { loading } = useQuasar();
+  const { loading, dialog } = useQuasar();
   const { notifySuccess, notifyError } = useNotify();
   const { tooltip } = useTray();
 
@@ -90,6 +90,7 @@ export const useOperation = () => {
         await Command.metadataDeploy(id, {
           use_config_dir: true,
           target_dir: null,
+          version: null,
         });
         await sync();
         await syncDeploymentCache();
@@ -141,6 +142,7 @@ export const useOperation = () => {
             await Command.metadataDeploy(id, {
               use_config_dir: false,
               target_dir: path,
+              version: null,
             });
             await sync();
             await syncDeploymentCache();
@@ -167,20 +169,29 @@ export const useOperation = () => {
     }
   };
 
-  const handleDeployOff = async (id: string) => {
+  const handleDeployOff = async (id: string, force = false) => {
     console.info(`Un-deploying item with id: ${id}`);
     loading.show({
       message: t('page.main.loading.deploy-off', [id]),
     });
     try {
-      await Command.metadataDeployOff(id);
+      await Command.metadataDeployOff(id, force);
       await sync();
       await syncDeploymentCache();
 
       notifySuccess(t('page.main.notify.deploy-off.success', [id]));
     } catch (e) {
       console.error(e);
-      notifyError(t('page.main.notify.deploy-off.fail', [id]), e);
+      if (force) {
+        notifyError(t('page.main.notify.deploy-off.fail', [id]), e);
+      } else {
+        dialog({
+          title: t('page.main.notify.deploy-off.fail', [id]),
+          message: t('page.main.deploy.confirm-force-deploy-off', [String(e)]),
+          ok: t('general.ok'),
+          cancel: t('general.cancel'),
+        }).onOk(() => handleDeployOff(id, true));
+      }
     } finally {
       loading.hide();
     }
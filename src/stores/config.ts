@@ -20,6 +20,25 @@ export const useConfigStore = defineStore(
       lang: 'zh-CN',
       path_data: '',
       path_deploy: null,
+      trash_retention_days: 30,
+      backup: {
+        retention: 5,
+        on_startup: true,
+        on_shutdown: false,
+        before_import: true,
+        before_migration: true,
+        interval_hours: 0,
+        dir: null,
+      },
+      profile: 'default',
+      profiles: [],
+      custom_fields: [],
+      archive_retention: {
+        keep_versions: 0,
+        keep_days: 0,
+      },
+      save_retention: 10,
+      history_limit: 5000,
     });
 
     const isDevMode = computed(() => frontend.value.devMode);
@@ -28,11 +47,18 @@ export const useConfigStore = defineStore(
     const lang = computed(() => backend.value.lang);
     const pathData = computed(() => backend.value.path_data);
     const pathDeploy = computed(() => backend.value.path_deploy);
+    const profile = computed(() => backend.value.profile);
+    const profiles = computed(() => backend.value.profiles);
+    const customFields = computed(() => backend.value.custom_fields);
 
     async function sync() {
       set(backend, await Command.configGet());
     }
 
+    async function switchProfile(name: string) {
+      set(backend, await Command.configProfileSwitch(name));
+    }
+
     function toggleDevMode() {
       frontend.value.devMode = !frontend.value.devMode;
     }
@@ -63,8 +89,12 @@ export const useConfigStore = defineStore(
       lang,
       pathData,
       pathDeploy,
+      profile,
+      profiles,
+      customFields,
       // Action
       sync,
+      switchProfile,
       toggleDevMode,
       toggleDarkMode,
       setLanguage,
@@ -2,7 +2,7 @@ import type { Metadata, MetadataOption } from '@/api/types';
 import { defineStore } from 'pinia';
 import { computed, ref } from 'vue';
 import { Command } from '@/api/cmd';
-import { extractArchiveSize } from '@/pages/main/script/function';
+import { extractArchivesSize } from '@/pages/main/script/function';
 
 export const useDatabaseStore = defineStore('database', () => {
   // State
@@ -11,7 +11,7 @@ export const useDatabaseStore = defineStore('database', () => {
   // Getters
   const size = computed(() => items.value.length);
   const totalFileSize = computed(() =>
-    items.value.reduce((sum, item) => sum + extractArchiveSize(item.archive_info), 0),
+    items.value.reduce((sum, item) => sum + extractArchivesSize(item), 0),
   );
 
   // Actions